@@ -0,0 +1,134 @@
+//! Compares the memory and parse time of the `ast` types and of an `Arena` on a generated api
+//! reference. Run with `cargo bench --bench ast`, `ITEMS=100000` sets the size of the reference.
+
+use markup::{arena::Arena, parser::Parser};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    fmt::Write,
+    hint::black_box,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+/// Counts the bytes allocated so memory can be measured without any dependency.
+struct Counting;
+
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let live = LIVE.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+        PEAK.fetch_max(live, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        LIVE.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if new_size > layout.size() {
+            let grown = new_size - layout.size();
+            let live = LIVE.fetch_add(grown, Ordering::Relaxed) + grown;
+            PEAK.fetch_max(live, Ordering::Relaxed);
+        } else {
+            LIVE.fetch_sub(layout.size() - new_size, Ordering::Relaxed);
+        }
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+const RUNS: usize = 5;
+
+/// An api reference with `items` documented functions spread over modules of 50.
+fn reference(items: usize) -> String {
+    let mut source = String::new();
+    for module in 0..items.div_ceil(50) {
+        writeln!(source, "section(id: \"mod-{module}\", class: [module]) {{").unwrap();
+        writeln!(source, "    h2 \"module_{module}\"").unwrap();
+        for item in module * 50..items.min((module + 1) * 50) {
+            write!(
+                source,
+                r#"    div(class: [item, function], kind: fn, since: {{major: 1, minor: {item}}}) {{
+        h3(id: "item-{item}") {{ code "function_{item}" }}
+        p {{ "Does the work of item " b "{item}" " and returns " a(href: "mod-{module}.mu#item-{next}") "the next one" "." }}
+        code-block(lang: rs, highlights: [1, 3..=4]) {{ "pub fn function_{item}(input: &str) -> usize {{\n    input.len()\n}}" }}
+        hr;
+    }}
+"#,
+                next = item + 1
+            )
+            .unwrap();
+        }
+        writeln!(source, "}}").unwrap();
+    }
+    source
+}
+
+/// The fastest of a few runs of `f`, the bytes its result holds on to and the most bytes that
+/// were allocated at once while it ran.
+fn measure<T>(mut f: impl FnMut() -> T) -> (Duration, usize, usize) {
+    let mut fastest = Duration::MAX;
+    let mut retained = 0;
+    let mut peak = 0;
+    for _ in 0..RUNS {
+        let before = LIVE.load(Ordering::Relaxed);
+        PEAK.store(before, Ordering::Relaxed);
+        let start = Instant::now();
+        let result = black_box(f());
+        fastest = fastest.min(start.elapsed());
+        retained = LIVE.load(Ordering::Relaxed) - before;
+        peak = PEAK.load(Ordering::Relaxed) - before;
+        drop(result);
+    }
+    (fastest, retained, peak)
+}
+
+fn mib(bytes: usize) -> String {
+    format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+fn main() {
+    let items = std::env::var("ITEMS")
+        .ok()
+        .and_then(|items| items.parse().ok())
+        .unwrap_or(20_000);
+    let source = reference(items);
+    let arena = Arena::parse(source.as_bytes()).unwrap();
+    println!(
+        "{items} items, {} nodes, {} of source",
+        arena.len(),
+        mib(source.len())
+    );
+    println!("{:<18}{:>12}{:>14}{:>14}", "", "time", "retained", "peak");
+    let ast = Parser::new().parse(source.as_bytes()).unwrap();
+    let rows = [
+        (
+            "parse to ast",
+            measure(|| Parser::new().parse(source.as_bytes()).unwrap()),
+        ),
+        (
+            "parse to arena",
+            measure(|| Arena::parse(source.as_bytes()).unwrap()),
+        ),
+        (
+            "parse via ast",
+            measure(|| Arena::from_nodes(&Parser::new().parse(source.as_bytes()).unwrap())),
+        ),
+        ("ast to arena", measure(|| Arena::from_nodes(&ast))),
+        ("arena to ast", measure(|| arena.to_nodes())),
+    ];
+    for (name, (time, retained, peak)) in rows {
+        println!(
+            "{name:<18}{:>12}{:>14}{:>14}",
+            format!("{:.1?}", time),
+            mib(retained),
+            mib(peak)
+        );
+    }
+}
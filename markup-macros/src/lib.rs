@@ -0,0 +1,440 @@
+//! The `markup!` macro, which parses markup at compile time and expands to the nodes of
+//! `markup::ast`.
+
+use proc_macro2::{Delimiter, Group, Ident, Literal, Span, TokenStream, TokenTree};
+use quote::{quote, quote_spanned};
+use std::iter::Peekable;
+
+/// Parses markup at compile time and expands to a `Vec<markup::ast::Node>`.
+///
+/// Strings are rust string literals, so they can use escapes and raw strings. `#{expr}` splices a
+/// rust expression: attribute values take anything that converts into a `markup::ast::Literal`
+/// and children anything implementing `markup::ast::IntoNodes`, like tags, nodes, strings and
+/// `Vec`s or `Option`s of them. Syntax errors are reported at the offending token.
+///
+/// ```
+/// use markup_macros::markup;
+///
+/// let lang = "rs";
+/// let nodes = markup! {
+///     p { "hello" }
+///     code-block(lang: #{lang}, highlights: [1, 3..=5]) { "fn main() {}" }
+/// };
+/// assert_eq!(nodes.len(), 2);
+/// ```
+#[proc_macro]
+pub fn markup(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    expand(input.into()).into()
+}
+
+fn expand(input: TokenStream) -> TokenStream {
+    match Parser::new(input, Span::call_site()).nodes() {
+        Ok(nodes) => {
+            let var = nodes_var();
+            quote! {{
+                let mut #var = ::std::vec::Vec::<::markup::ast::Node>::new();
+                #(#nodes)*
+                #var
+            }}
+        }
+        Err(error) => error.to_compile_error(),
+    }
+}
+
+/// The vector nodes are pushed into, hygienic so spliced expressions can't see it.
+fn nodes_var() -> Ident {
+    Ident::new("nodes", Span::mixed_site())
+}
+
+#[derive(Debug)]
+struct Error {
+    span: Span,
+    message: String,
+}
+
+impl Error {
+    fn new(span: Span, message: impl Into<String>) -> Self {
+        Self {
+            span,
+            message: message.into(),
+        }
+    }
+
+    fn to_compile_error(&self) -> TokenStream {
+        let message = &self.message;
+        quote_spanned!(self.span=> ::core::compile_error!(#message))
+    }
+}
+
+type ParseResult<T> = Result<T, Error>;
+
+/// Parses the tokens of a macro input or of one of its groups, `end` is where errors about a
+/// missing token point.
+struct Parser {
+    tokens: Peekable<proc_macro2::token_stream::IntoIter>,
+    end: Span,
+}
+
+impl Parser {
+    fn new(tokens: TokenStream, end: Span) -> Self {
+        Self {
+            tokens: tokens.into_iter().peekable(),
+            end,
+        }
+    }
+
+    fn group(group: &Group) -> Self {
+        Self::new(group.stream(), group.span_close())
+    }
+
+    fn is_at_end(&mut self) -> bool {
+        self.tokens.peek().is_none()
+    }
+
+    fn next(&mut self, expected: &str) -> ParseResult<TokenTree> {
+        self.tokens
+            .next()
+            .ok_or_else(|| Error::new(self.end, format!("Expected {expected}")))
+    }
+
+    fn next_is_punct(&mut self, c: char) -> bool {
+        matches!(self.tokens.peek(), Some(TokenTree::Punct(p)) if p.as_char() == c)
+    }
+
+    fn punct(&mut self, c: char) -> ParseResult<()> {
+        match self.next(&format!("`{c}`"))? {
+            TokenTree::Punct(p) if p.as_char() == c => Ok(()),
+            token => Err(Error::new(token.span(), format!("Expected `{c}`"))),
+        }
+    }
+
+    /// Nodes until the end of the input, each expands to a statement pushing into the nodes
+    /// vector.
+    fn nodes(&mut self) -> ParseResult<Vec<TokenStream>> {
+        let mut nodes = vec![];
+        while !self.is_at_end() {
+            nodes.push(self.node()?);
+        }
+        Ok(nodes)
+    }
+
+    fn node(&mut self) -> ParseResult<TokenStream> {
+        let var = nodes_var();
+        match self.next("a node")? {
+            TokenTree::Literal(literal) if is_string(&literal) => Ok(quote! {
+                #var.push(::markup::ast::Node::String(::std::borrow::Cow::Borrowed(#literal)));
+            }),
+            TokenTree::Punct(p) if p.as_char() == '#' => {
+                let expr = self.splice()?;
+                Ok(quote! {
+                    #var.extend(::markup::ast::IntoNodes::into_nodes(#expr));
+                })
+            }
+            TokenTree::Ident(ident) => {
+                let tag = self.tag(ident)?;
+                Ok(quote! {
+                    #var.push(::markup::ast::Node::Tag(#tag));
+                })
+            }
+            token => Err(Error::new(
+                token.span(),
+                "Expected a tag, a string or a `#{...}` splice",
+            )),
+        }
+    }
+
+    /// The `{...}` after a `#`.
+    fn splice(&mut self) -> ParseResult<TokenStream> {
+        match self.next("`{` after `#`")? {
+            TokenTree::Group(group) if group.delimiter() == Delimiter::Brace => {
+                if group.stream().is_empty() {
+                    return Err(Error::new(group.span(), "Expected an expression"));
+                }
+                Ok(group.stream())
+            }
+            token => Err(Error::new(token.span(), "Expected `{` after `#`")),
+        }
+    }
+
+    fn tag(&mut self, first: Ident) -> ParseResult<TokenStream> {
+        let name = self.name(first)?;
+        let attributes = match self.tokens.peek() {
+            Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Parenthesis => {
+                let group = group.clone();
+                self.tokens.next();
+                Parser::group(&group).attributes()?
+            }
+            _ => vec![],
+        };
+        let var = nodes_var();
+        let (children, body) = match self.next("`{`, `;` or a string")? {
+            TokenTree::Group(group) if group.delimiter() == Delimiter::Brace => {
+                (Parser::group(&group).nodes()?, quote!(Block))
+            }
+            TokenTree::Punct(p) if p.as_char() == ';' => (vec![], quote!(None)),
+            TokenTree::Literal(literal) if is_string(&literal) => (
+                vec![quote! {
+                    #var.push(::markup::ast::Node::String(::std::borrow::Cow::Borrowed(#literal)));
+                }],
+                quote!(String),
+            ),
+            token => return Err(Error::new(token.span(), "Expected `{`, `;` or a string")),
+        };
+        Ok(quote! {
+            ::markup::ast::Tag {
+                name: ::std::borrow::Cow::Borrowed(#name),
+                attributes: ::std::vec![#(#attributes),*],
+                children: {
+                    let mut #var = ::std::vec::Vec::<::markup::ast::Node>::new();
+                    #(#children)*
+                    #var
+                },
+                body: ::markup::ast::Body::#body,
+            }
+        })
+    }
+
+    /// A tag or attribute name, rust splits names like `code-block` into several tokens.
+    fn name(&mut self, first: Ident) -> ParseResult<String> {
+        let mut name = first.to_string();
+        if let Some(raw) = name.strip_prefix("r#") {
+            name = raw.to_string();
+        }
+        if !name.starts_with(|c: char| c.is_alphabetic()) {
+            return Err(Error::new(first.span(), "Names must start with a letter"));
+        }
+        while self.next_is_punct('-') {
+            self.tokens.next();
+            match self.next("the rest of the name after `-`")? {
+                TokenTree::Ident(part) => name.push_str(&format!("-{part}")),
+                TokenTree::Literal(part) if part.to_string().parse::<u32>().is_ok() => {
+                    name.push_str(&format!("-{part}"))
+                }
+                token => {
+                    return Err(Error::new(
+                        token.span(),
+                        "Expected the rest of the name after `-`",
+                    ))
+                }
+            }
+        }
+        Ok(name)
+    }
+
+    fn attributes(&mut self) -> ParseResult<Vec<TokenStream>> {
+        let mut attributes = vec![];
+        while !self.is_at_end() {
+            if !attributes.is_empty() {
+                self.punct(',')?;
+            }
+            let name = match self.next("an attribute name")? {
+                TokenTree::Ident(ident) => self.name(ident)?,
+                token => return Err(Error::new(token.span(), "Expected an attribute name")),
+            };
+            //An attribute without a value is a flag like `details(open) {}`.
+            let value = if self.is_at_end() || self.next_is_punct(',') {
+                quote!(::markup::ast::Literal::Flag)
+            } else {
+                self.punct(':')?;
+                self.literal()?
+            };
+            attributes.push(quote! {
+                ::markup::ast::Attribute::new(#name, #value)
+            });
+        }
+        Ok(attributes)
+    }
+
+    fn literal(&mut self) -> ParseResult<TokenStream> {
+        const EXPECTED: &str =
+            "Expected a number, a string, a boolean, an identifier, a list, a map, a range or a `#{...}` splice";
+        match self.next("a value")? {
+            TokenTree::Literal(literal) if is_string(&literal) => Ok(quote! {
+                ::markup::ast::Literal::String(::std::borrow::Cow::Borrowed(#literal))
+            }),
+            TokenTree::Literal(literal) => {
+                let n = number(&literal)?;
+                if self.next_is_punct('.') {
+                    self.range(n, literal.span())
+                } else {
+                    Ok(quote!(::markup::ast::Literal::Number(#n)))
+                }
+            }
+            TokenTree::Group(group) if group.delimiter() == Delimiter::Bracket => {
+                let mut parser = Parser::group(&group);
+                let mut items = vec![];
+                while !parser.is_at_end() {
+                    if !items.is_empty() {
+                        parser.punct(',')?;
+                    }
+                    items.push(parser.literal()?);
+                }
+                Ok(quote!(::markup::ast::Literal::List(
+                    ::std::vec![#(#items),*]
+                )))
+            }
+            TokenTree::Group(group) if group.delimiter() == Delimiter::Brace => {
+                let mut parser = Parser::group(&group);
+                let mut keys: Vec<String> = vec![];
+                let mut values = vec![];
+                while !parser.is_at_end() {
+                    if !keys.is_empty() {
+                        parser.punct(',')?;
+                    }
+                    let key = match parser.next("a key")? {
+                        TokenTree::Ident(ident) => {
+                            let span = ident.span();
+                            let key = parser.name(ident)?;
+                            if keys.contains(&key) {
+                                return Err(Error::new(span, format!("Duplicate key `{key}`")));
+                            }
+                            key
+                        }
+                        token => return Err(Error::new(token.span(), "Expected a key")),
+                    };
+                    parser.punct(':')?;
+                    values.push(parser.literal()?);
+                    keys.push(key);
+                }
+                Ok(quote!(::markup::ast::Literal::Map(::std::vec![
+                    #((::std::borrow::Cow::Borrowed(#keys), #values)),*
+                ])))
+            }
+            TokenTree::Punct(p) if p.as_char() == '#' => {
+                let expr = self.splice()?;
+                Ok(quote!(::core::convert::Into::<::markup::ast::Literal>::into(#expr)))
+            }
+            TokenTree::Ident(ident) if ident == "true" || ident == "false" => {
+                Ok(quote!(::markup::ast::Literal::Bool(#ident)))
+            }
+            TokenTree::Ident(ident) => {
+                let name = self.name(ident)?;
+                Ok(quote! {
+                    ::markup::ast::Literal::Identifier(::std::borrow::Cow::Borrowed(#name))
+                })
+            }
+            token => Err(Error::new(token.span(), EXPECTED)),
+        }
+    }
+
+    /// The rest of a range after its start, `..end`, `..=end` or `..` and an optional `by step`.
+    fn range(&mut self, start: u32, span: Span) -> ParseResult<TokenStream> {
+        self.punct('.')?;
+        self.punct('.')?;
+        let inclusive = self.next_is_punct('=');
+        let end = if inclusive {
+            self.tokens.next();
+            match self.next("the end of the range")? {
+                TokenTree::Literal(literal) => Some(number(&literal)?),
+                token => return Err(Error::new(token.span(), "Expected the end of the range")),
+            }
+        } else {
+            match self.tokens.peek() {
+                Some(TokenTree::Literal(literal)) => {
+                    let end = number(literal)?;
+                    self.tokens.next();
+                    Some(end)
+                }
+                _ => None,
+            }
+        };
+        if let Some(end) = end.filter(|end| *end < start) {
+            return Err(Error::new(
+                span,
+                format!(
+                    "Reversed range {start}..{end}, the start of a range can't be after its end"
+                ),
+            ));
+        }
+        let step = match self.tokens.peek() {
+            Some(TokenTree::Ident(ident)) if ident == "by" => {
+                self.tokens.next();
+                match self.next("a step after `by`")? {
+                    TokenTree::Literal(literal) => match number(&literal)? {
+                        0 => {
+                            return Err(Error::new(
+                                literal.span(),
+                                "Range step must be greater than zero",
+                            ))
+                        }
+                        step => Some(step),
+                    },
+                    token => return Err(Error::new(token.span(), "Expected a step after `by`")),
+                }
+            }
+            _ => None,
+        };
+        let end = option(end);
+        let step = option(step);
+        Ok(quote! {
+            ::markup::ast::Literal::Range {
+                start: #start,
+                end: #end,
+                inclusive: #inclusive,
+                step: #step,
+            }
+        })
+    }
+}
+
+fn is_string(literal: &Literal) -> bool {
+    let literal = literal.to_string();
+    literal.starts_with('"') || literal.starts_with("r\"") || literal.starts_with("r#")
+}
+
+fn number(literal: &Literal) -> ParseResult<u32> {
+    literal.to_string().parse().map_err(|_| {
+        Error::new(
+            literal.span(),
+            format!("Expected a number between 0 and {}", u32::MAX),
+        )
+    })
+}
+
+fn option(n: Option<u32>) -> TokenStream {
+    match n {
+        Some(n) => quote!(::core::option::Option::Some(#n)),
+        None => quote!(::core::option::Option::None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Parser;
+    use pretty_assertions::assert_eq;
+    use proc_macro2::Span;
+
+    fn error(input: &str) -> String {
+        Parser::new(input.parse().unwrap(), Span::call_site())
+            .nodes()
+            .unwrap_err()
+            .message
+    }
+
+    #[test]
+    fn syntax_errors() {
+        assert_eq!(error("p"), "Expected `{`, `;` or a string");
+        assert_eq!(error("p 1"), "Expected `{`, `;` or a string");
+        assert_eq!(error("p(id \"x\") {}"), "Expected `:`");
+        assert_eq!(error("p(id: ) {}"), "Expected a value");
+        assert_eq!(
+            error("p(id: -1) {}"),
+            "Expected a number, a string, a boolean, an identifier, a list, a map, a range or a `#{...}` splice"
+        );
+        assert_eq!(error("image(size: {w: 1, w: 2}) {}"), "Duplicate key `w`");
+        assert_eq!(
+            error("code-block(lines: 5..3) {}"),
+            "Reversed range 5..3, the start of a range can't be after its end"
+        );
+        assert_eq!(
+            error("code-block(lines: 1.. by 0) {}"),
+            "Range step must be greater than zero"
+        );
+        assert_eq!(
+            error("p { 1 }"),
+            "Expected a tag, a string or a `#{...}` splice"
+        );
+        assert_eq!(error("p { #{} }"), "Expected an expression");
+        assert_eq!(error("code- {}"), "Expected the rest of the name after `-`");
+    }
+}
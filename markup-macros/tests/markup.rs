@@ -0,0 +1,56 @@
+use markup::{
+    ast::{Node, Tag},
+    parser::Parser,
+};
+use markup_macros::markup;
+use pretty_assertions::assert_eq;
+
+#[test]
+fn expands_to_the_parsed_nodes() {
+    let source = r##"
+        section(id: "intro") {
+            "hello "
+            code-block(lang: "rs", highlights: [1, 3..=5, 8.. by 2], width: 80) { "fn main() {}" }
+            hr-2 {}
+            details(open, theme: dark-blue, wrap: false) {}
+            hr;
+            p "text"
+            image(size: {w: 640, max-h: 480}, crops: [{}, {at: 1..3}]) {}
+            pre(title: "say \"hi\"") { r#"{"a": "b"}"# }
+        }
+    "##;
+    let expected = Parser::new().parse(source.as_bytes()).unwrap();
+    let nodes = markup! {
+        section(id: "intro") {
+            "hello "
+            code-block(lang: "rs", highlights: [1, 3..=5, 8.. by 2], width: 80) { r"fn main() {}" }
+            hr-2 {}
+            details(open, theme: dark-blue, wrap: false) {}
+            hr;
+            p "text"
+            image(size: {w: 640, max-h: 480}, crops: [{}, {at: 1..3}]) {}
+            pre(title: "say \"hi\"") { r#"{"a": "b"}"# }
+        }
+    };
+    assert_eq!(nodes, expected);
+}
+
+#[test]
+fn splices() {
+    let lang = String::from("ts");
+    let lines = vec![1, 2];
+    let items = ["a", "b"];
+    let footer: Option<Tag> = None;
+    let nodes = markup! {
+        code-block(lang: #{lang.clone()}, highlights: #{lines}, span: #{2..4}) {}
+        ul {
+            #{items.iter().map(|item| markup! { li { #{*item} } }).collect::<Vec<_>>()}
+            #{footer}
+        }
+    };
+    let expected = Parser::new()
+        .parse(br#"code-block(lang: "ts", highlights: [1, 2], span: 2..4) {} ul { li { "a" } li { "b" } }"#)
+        .unwrap();
+    assert_eq!(nodes, expected);
+    assert!(matches!(&nodes[1], Node::Tag(ul) if ul.children.len() == 2));
+}
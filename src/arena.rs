@@ -0,0 +1,516 @@
+use crate::{
+    ast::{Attribute, Body, Literal, Node, Tag},
+    parser::{Parser, ParsingError},
+};
+use std::{borrow::Cow, collections::HashMap};
+
+/// A compact representation of a document for very large ones, like generated api references.
+///
+/// Instead of a [`Tag`] owning vectors of attributes and children, every node of an arena is in
+/// one vector and a tag refers to its children, which are next to each other, by their indices.
+/// The same goes for attributes, list items and map entries. Tag names, attribute names, map keys
+/// and identifiers are interned so a name repeated on every node is stored once, and strings are
+/// copied into a single buffer so the arena doesn't borrow its source.
+///
+/// An arena is read through [`NodeRef`] and [`TagRef`] or converted back to the [`ast`](crate::ast)
+/// types with [`Arena::to_nodes`], which borrows every string from the arena.
+///
+/// ```
+/// use markup::arena::{Arena, NodeRef};
+///
+/// let arena = Arena::parse(br#"ul { li(id: "a") "A" li "B" }"#).unwrap();
+/// let NodeRef::Tag(list) = arena.root(0).unwrap() else { unreachable!() };
+/// assert_eq!(list.name(), "ul");
+/// assert_eq!(list.children().count(), 2);
+/// assert_eq!(arena.len(), 5);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Arena {
+    names: Interner,
+    text: String,
+    nodes: Vec<NodeData>,
+    /// The attributes of tags and the entries of maps.
+    entries: Vec<Entry>,
+    /// The items of lists.
+    items: Vec<LiteralData>,
+    roots: Span,
+}
+
+/// A name interned by an [`Arena`], two names are equal if their symbols are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(u32);
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Interner {
+    symbols: HashMap<Box<str>, Symbol>,
+    names: Vec<Box<str>>,
+}
+
+/// A range of one of the vectors of an [`Arena`], or of its text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Span {
+    start: u32,
+    len: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NodeData {
+    Tag {
+        name: Symbol,
+        body: Body,
+        attributes: Span,
+        children: Span,
+    },
+    String(Span),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Entry {
+    name: Symbol,
+    value: LiteralData,
+}
+
+/// A [`Literal`] without any allocation of its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LiteralData {
+    Number(u32),
+    String(Span),
+    List(Span),
+    Bool(bool),
+    Flag,
+    Identifier(Symbol),
+    Map(Span),
+    Range {
+        start: u32,
+        end: Option<u32>,
+        inclusive: bool,
+        step: Option<u32>,
+    },
+}
+
+/// A node of an [`Arena`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeRef<'a> {
+    Tag(TagRef<'a>),
+    String(&'a str),
+}
+
+/// A tag of an [`Arena`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TagRef<'a> {
+    arena: &'a Arena,
+    name: Symbol,
+    body: Body,
+    attributes: Span,
+    children: Span,
+}
+
+impl Interner {
+    fn intern(&mut self, name: &str) -> Symbol {
+        if let Some(symbol) = self.symbols.get(name) {
+            return *symbol;
+        }
+        let symbol = Symbol(index(self.names.len()));
+        self.names.push(name.into());
+        self.symbols.insert(name.into(), symbol);
+        symbol
+    }
+}
+
+impl Span {
+    fn new(start: usize, len: usize) -> Self {
+        Self {
+            start: index(start),
+            len: index(len),
+        }
+    }
+
+    fn range(self) -> std::ops::Range<usize> {
+        self.start as usize..(self.start + self.len) as usize
+    }
+}
+
+/// `i` as an index of an arena, which holds at most `u32::MAX` of anything.
+fn index(i: usize) -> u32 {
+    u32::try_from(i).expect("an arena holds at most u32::MAX nodes, entries, items or bytes")
+}
+
+impl Arena {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses `source` straight into an arena, the [`ast`](crate::ast) nodes of the document are
+    /// never all built.
+    pub fn parse(source: &[u8]) -> Result<Self, ParsingError<'_>> {
+        let mut parser = Parser::new();
+        parser.start(source)?;
+        let mut arena = Self::new();
+        //The nodes of the blocks being parsed, they are added together when their block closes so
+        //siblings stay next to each other.
+        let mut open = vec![];
+        while !parser.is_at_end() {
+            arena.parse_node(&mut parser, &mut open)?;
+        }
+        arena.roots = arena.push_siblings(&mut open, 0);
+        Ok(arena)
+    }
+
+    pub fn from_nodes(nodes: &[Node]) -> Self {
+        let mut arena = Self::new();
+        arena.roots = arena.push_nodes(nodes);
+        arena
+    }
+
+    /// The nodes of the arena as [`ast`](crate::ast) nodes borrowing their strings from it.
+    pub fn to_nodes(&self) -> Vec<Node<'_>> {
+        self.roots().map(NodeRef::to_node).collect()
+    }
+
+    /// The number of nodes, tags and strings, in the arena.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// The top level nodes.
+    pub fn roots(&self) -> impl ExactSizeIterator<Item = NodeRef<'_>> {
+        self.roots.range().map(|i| self.node(i))
+    }
+
+    /// The `i`th top level node.
+    pub fn root(&self, i: usize) -> Option<NodeRef<'_>> {
+        (i < self.roots.len as usize).then(|| self.node(self.roots.start as usize + i))
+    }
+
+    /// The symbol of `name` if a node of the arena uses it, comparing symbols is cheaper than
+    /// comparing names.
+    pub fn symbol(&self, name: &str) -> Option<Symbol> {
+        self.names.symbols.get(name).copied()
+    }
+
+    pub fn name(&self, symbol: Symbol) -> &str {
+        &self.names.names[symbol.0 as usize]
+    }
+
+    fn node(&self, i: usize) -> NodeRef<'_> {
+        match self.nodes[i] {
+            NodeData::Tag {
+                name,
+                body,
+                attributes,
+                children,
+            } => NodeRef::Tag(TagRef {
+                arena: self,
+                name,
+                body,
+                attributes,
+                children,
+            }),
+            NodeData::String(text) => NodeRef::String(self.text(text)),
+        }
+    }
+
+    fn text(&self, span: Span) -> &str {
+        &self.text[span.range()]
+    }
+
+    fn push_text(&mut self, s: &str) -> Span {
+        let span = Span::new(self.text.len(), s.len());
+        self.text.push_str(s);
+        span
+    }
+
+    /// Parses the next node of `parser` and its descendants, the node is left at the end of `open`.
+    fn parse_node<'s>(
+        &mut self,
+        parser: &mut Parser<'s>,
+        open: &mut Vec<NodeData>,
+    ) -> Result<(), ParsingError<'s>> {
+        let node = match parser.open_node()? {
+            Node::String(s) => NodeData::String(self.push_text(&s)),
+            Node::Tag(tag) => {
+                let name = self.names.intern(&tag.name);
+                let attributes = self.push_entries(
+                    tag.attributes
+                        .iter()
+                        .map(|attribute| (attribute.name(), attribute.value())),
+                );
+                let children = if tag.body == Body::Block {
+                    let first = open.len();
+                    while !parser.close_block()? {
+                        self.parse_node(parser, open)?;
+                    }
+                    self.push_siblings(open, first)
+                } else {
+                    self.push_nodes(&tag.children)
+                };
+                NodeData::Tag {
+                    name,
+                    body: tag.body,
+                    attributes,
+                    children,
+                }
+            }
+        };
+        open.push(node);
+        Ok(())
+    }
+
+    /// Moves the nodes of `open` from `first` on to the arena.
+    fn push_siblings(&mut self, open: &mut Vec<NodeData>, first: usize) -> Span {
+        let span = Span::new(self.nodes.len(), open.len() - first);
+        self.nodes.extend(open.drain(first..));
+        span
+    }
+
+    /// Adds `nodes` next to each other, after their descendants like [`Arena::parse`] does.
+    fn push_nodes(&mut self, nodes: &[Node]) -> Span {
+        let nodes: Vec<NodeData> = nodes
+            .iter()
+            .map(|node| match node {
+                Node::String(s) => NodeData::String(self.push_text(s)),
+                Node::Tag(tag) => NodeData::Tag {
+                    name: self.names.intern(&tag.name),
+                    body: tag.body,
+                    attributes: self.push_entries(
+                        tag.attributes
+                            .iter()
+                            .map(|attribute| (attribute.name(), attribute.value())),
+                    ),
+                    children: self.push_nodes(&tag.children),
+                },
+            })
+            .collect();
+        let span = Span::new(self.nodes.len(), nodes.len());
+        self.nodes.extend(nodes);
+        span
+    }
+
+    fn push_entries<'l>(
+        &mut self,
+        entries: impl ExactSizeIterator<Item = (&'l str, &'l Literal<'l>)>,
+    ) -> Span {
+        let span = Span::new(self.entries.len(), entries.len());
+        let placeholder = Entry {
+            name: Symbol(0),
+            value: LiteralData::Flag,
+        };
+        self.entries
+            .resize(self.entries.len() + entries.len(), placeholder);
+        for (i, (name, value)) in span.range().zip(entries) {
+            self.entries[i] = Entry {
+                name: self.names.intern(name),
+                value: self.push_literal(value),
+            };
+        }
+        span
+    }
+
+    fn push_literal(&mut self, literal: &Literal) -> LiteralData {
+        match literal {
+            Literal::Number(n) => LiteralData::Number(*n),
+            Literal::String(s) => LiteralData::String(self.push_text(s)),
+            Literal::List(items) => {
+                let span = Span::new(self.items.len(), items.len());
+                self.items
+                    .resize(self.items.len() + items.len(), LiteralData::Flag);
+                for (i, item) in span.range().zip(items) {
+                    self.items[i] = self.push_literal(item);
+                }
+                LiteralData::List(span)
+            }
+            Literal::Bool(b) => LiteralData::Bool(*b),
+            Literal::Flag => LiteralData::Flag,
+            Literal::Identifier(name) => LiteralData::Identifier(self.names.intern(name)),
+            Literal::Map(entries) => LiteralData::Map(
+                self.push_entries(entries.iter().map(|(key, value)| (key.as_ref(), value))),
+            ),
+            Literal::Range {
+                start,
+                end,
+                inclusive,
+                step,
+            } => LiteralData::Range {
+                start: *start,
+                end: *end,
+                inclusive: *inclusive,
+                step: *step,
+            },
+        }
+    }
+
+    fn literal(&self, literal: LiteralData) -> Literal<'_> {
+        match literal {
+            LiteralData::Number(n) => Literal::Number(n),
+            LiteralData::String(text) => Literal::String(Cow::Borrowed(self.text(text))),
+            LiteralData::List(items) => Literal::List(
+                self.items[items.range()]
+                    .iter()
+                    .map(|item| self.literal(*item))
+                    .collect(),
+            ),
+            LiteralData::Bool(b) => Literal::Bool(b),
+            LiteralData::Flag => Literal::Flag,
+            LiteralData::Identifier(name) => Literal::Identifier(Cow::Borrowed(self.name(name))),
+            LiteralData::Map(entries) => Literal::Map(
+                self.entries[entries.range()]
+                    .iter()
+                    .map(|entry| {
+                        (
+                            Cow::Borrowed(self.name(entry.name)),
+                            self.literal(entry.value),
+                        )
+                    })
+                    .collect(),
+            ),
+            LiteralData::Range {
+                start,
+                end,
+                inclusive,
+                step,
+            } => Literal::Range {
+                start,
+                end,
+                inclusive,
+                step,
+            },
+        }
+    }
+}
+
+impl<'a> NodeRef<'a> {
+    pub fn to_node(self) -> Node<'a> {
+        match self {
+            NodeRef::Tag(tag) => Node::Tag(tag.to_tag()),
+            NodeRef::String(s) => Node::String(Cow::Borrowed(s)),
+        }
+    }
+}
+
+impl<'a> TagRef<'a> {
+    pub fn name(&self) -> &'a str {
+        self.arena.name(self.name)
+    }
+
+    pub fn symbol(&self) -> Symbol {
+        self.name
+    }
+
+    pub fn body(&self) -> Body {
+        self.body
+    }
+
+    pub fn children(&self) -> impl ExactSizeIterator<Item = NodeRef<'a>> {
+        let arena = self.arena;
+        self.children.range().map(|i| arena.node(i))
+    }
+
+    /// The names and values of the attributes, values are decoded as they are read.
+    pub fn attributes(&self) -> impl ExactSizeIterator<Item = (&'a str, Literal<'a>)> {
+        let arena = self.arena;
+        arena.entries[self.attributes.range()]
+            .iter()
+            .map(|entry| (arena.name(entry.name), arena.literal(entry.value)))
+    }
+
+    /// The value of the attribute `name`, if the tag has one.
+    pub fn attr(&self, name: &str) -> Option<Literal<'a>> {
+        let name = self.arena.symbol(name)?;
+        let entry = self.arena.entries[self.attributes.range()]
+            .iter()
+            .find(|entry| entry.name == name)?;
+        Some(self.arena.literal(entry.value))
+    }
+
+    pub fn to_tag(self) -> Tag<'a> {
+        Tag {
+            name: Cow::Borrowed(self.name()),
+            attributes: self
+                .attributes()
+                .map(|(name, value)| Attribute::new(name, value))
+                .collect(),
+            children: self.children().map(NodeRef::to_node).collect(),
+            body: self.body,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Arena, Entry, LiteralData, NodeData, NodeRef};
+    use crate::{
+        ast::{Attribute, Literal, Node},
+        parser::Parser,
+    };
+    use pretty_assertions::assert_eq;
+
+    const SOURCE: &str = r#"
+section(id: "api") {
+    h2 "API"
+    item(kind: fn, lines: 1..=20 by 2, size: {w: 640, h: 480}, tags: [a, "b", [1]], open) {
+        code "parse"
+        p { "Parses " em "everything" "." }
+        hr;
+    }
+    item(kind: fn) "to_string"
+}
+p "end"
+"#;
+
+    #[test]
+    fn converts_to_and_from_ast_nodes() {
+        let ast = Parser::new().parse(SOURCE.as_bytes()).unwrap();
+        let arena = Arena::parse(SOURCE.as_bytes()).unwrap();
+        assert_eq!(arena.to_nodes(), ast);
+        assert_eq!(Arena::from_nodes(&arena.to_nodes()), arena);
+        assert_eq!(Arena::from_nodes(&ast), arena);
+        assert_eq!(arena.len(), 16);
+        for source in [&b"p { a(href: ) }"[..], b"p { a \"b\""] {
+            assert_eq!(
+                Arena::parse(source).unwrap_err(),
+                Parser::new().parse(source).unwrap_err()
+            );
+        }
+        assert_eq!(arena.roots().len(), 2);
+    }
+
+    #[test]
+    fn reads_tags_in_place() {
+        let arena = Arena::parse(SOURCE.as_bytes()).unwrap();
+        let Some(NodeRef::Tag(section)) = arena.root(0) else {
+            panic!("expected a tag");
+        };
+        let items: Vec<_> = section
+            .children()
+            .filter_map(|child| match child {
+                NodeRef::Tag(tag) if tag.symbol() == arena.symbol("item").unwrap() => Some(tag),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].attr("kind"), items[1].attr("kind"));
+        assert_eq!(
+            items[0].attr("size").unwrap().get("h"),
+            Some(&Literal::Number(480))
+        );
+        assert_eq!(items[0].attr("open"), Some(Literal::Flag));
+        assert_eq!(items[0].attr("missing"), None);
+        assert_eq!(
+            items[1].children().next(),
+            Some(NodeRef::String("to_string"))
+        );
+        assert_eq!(arena.root(2), None);
+    }
+
+    #[test]
+    fn nodes_and_literals_stay_small() {
+        use std::mem::size_of;
+        assert!(size_of::<NodeData>() <= size_of::<Node>());
+        assert!(size_of::<LiteralData>() <= size_of::<Literal>());
+        assert!(size_of::<Entry>() <= size_of::<Attribute>());
+    }
+}
@@ -1,54 +1,488 @@
-use std::fmt::Display;
+use std::{
+    borrow::Cow,
+    fmt::Display,
+    ops::{Range, RangeFrom, RangeInclusive},
+};
 
 use serde::{Deserialize, Serialize};
 
+use crate::lexer::Quoted;
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Tag<'source> {
-    pub name: &'source str,
+    #[serde(borrow)]
+    pub name: Cow<'source, str>,
     pub attributes: Vec<Attribute<'source>>,
     pub children: Vec<Node<'source>>,
+    #[serde(default)]
+    pub body: Body,
+}
+
+/// How the body of a tag is written. Only printers look at it, a `hr;` has no children just like
+/// a `hr {}` and a `p "text"` has a single string child just like a `p { "text" }`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Body {
+    /// `p { ... }`, including the empty `hr {}`.
+    #[default]
+    Block,
+    /// `hr;` or `image(src: "a.png");`.
+    None,
+    /// `p "text"`.
+    String,
+}
+
+impl<'source> Tag<'source> {
+    /// A tag without attributes or children, [`Tag::with_attr`] and [`Tag::with_child`] build it
+    /// up. Names and strings can be borrowed or owned.
+    ///
+    /// ```
+    /// use markup::{ast::{Node, Tag}, printer};
+    ///
+    /// let tag = Tag::new("p")
+    ///     .with_attr("id", "intro")
+    ///     .with_attr("lines", vec![1..3, 5..7])
+    ///     .with_child("hello ")
+    ///     .with_child(Tag::new("b").with_child(format!("{}!", "world")));
+    /// assert_eq!(
+    ///     printer::print_tag(&tag),
+    ///     "p(id: \"intro\", lines: [1..3, 5..7]) {\n    \"hello \"\n    b { \"world!\" }\n}\n"
+    /// );
+    /// ```
+    pub fn new(name: impl Into<Cow<'source, str>>) -> Self {
+        Self {
+            name: name.into(),
+            attributes: vec![],
+            children: vec![],
+            body: Body::Block,
+        }
+    }
+
+    pub fn with_attr(
+        mut self,
+        name: impl Into<Cow<'source, str>>,
+        value: impl Into<Literal<'source>>,
+    ) -> Self {
+        self.attributes.push(Attribute::new(name, value));
+        self
+    }
+
+    pub fn with_child(mut self, child: impl Into<Node<'source>>) -> Self {
+        self.children.push(child.into());
+        self
+    }
+
+    pub fn with_children<N: Into<Node<'source>>>(
+        mut self,
+        children: impl IntoIterator<Item = N>,
+    ) -> Self {
+        self.children.extend(children.into_iter().map(Into::into));
+        self
+    }
+
+    /// Copies every borrowed string so the tag no longer borrows from its source.
+    pub fn into_owned(self) -> Tag<'static> {
+        Tag {
+            name: Cow::Owned(self.name.into_owned()),
+            attributes: self
+                .attributes
+                .into_iter()
+                .map(Attribute::into_owned)
+                .collect(),
+            children: self.children.into_iter().map(Node::into_owned).collect(),
+            body: self.body,
+        }
+    }
+}
+
+/// A whole document, as returned by [`Parser::parse_document`](crate::parser::Parser::parse_document):
+/// its metadata and its content.
+///
+/// The metadata is a `meta` tag at the very start of the document, like
+/// `meta(title: "Guide", authors: ["a", "b"], date: "2026-01-01");`, so renderers can put it in
+/// an html `<head>`, a LaTeX preamble or a site index instead of the body. [`Document::get`] and
+/// [`Document::metadata`] read it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Document<'source> {
+    #[serde(borrow)]
+    pub meta: Option<Tag<'source>>,
+    pub nodes: Vec<Node<'source>>,
+}
+
+impl<'source> Document<'source> {
+    /// The metadata followed by the content, the nodes [`Parser::parse`](crate::parser::Parser::parse)
+    /// returns for the same source.
+    pub fn into_nodes(self) -> Vec<Node<'source>> {
+        self.meta
+            .map(Node::Tag)
+            .into_iter()
+            .chain(self.nodes)
+            .collect()
+    }
+
+    pub fn into_owned(self) -> Document<'static> {
+        Document {
+            meta: self.meta.map(Tag::into_owned),
+            nodes: self.nodes.into_iter().map(Node::into_owned).collect(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Node<'source> {
+    #[serde(borrow)]
     Tag(Tag<'source>),
-    String(&'source str),
+    String(Cow<'source, str>),
+}
+
+impl<'source> Node<'source> {
+    pub fn into_owned(self) -> Node<'static> {
+        match self {
+            Node::Tag(tag) => Node::Tag(tag.into_owned()),
+            Node::String(s) => Node::String(Cow::Owned(s.into_owned())),
+        }
+    }
+}
+
+impl<'source> From<Tag<'source>> for Node<'source> {
+    fn from(tag: Tag<'source>) -> Self {
+        Self::Tag(tag)
+    }
+}
+
+impl<'source> From<&'source str> for Node<'source> {
+    fn from(s: &'source str) -> Self {
+        Self::String(s.into())
+    }
+}
+
+impl From<String> for Node<'_> {
+    fn from(s: String) -> Self {
+        Self::String(s.into())
+    }
+}
+
+impl<'source> From<Cow<'source, str>> for Node<'source> {
+    fn from(s: Cow<'source, str>) -> Self {
+        Self::String(s)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Attribute<'source> {
-    pub(crate) name: &'source str,
+    #[serde(borrow)]
+    pub(crate) name: Cow<'source, str>,
     pub(crate) value: Literal<'source>,
 }
 
+impl<'source> Attribute<'source> {
+    pub fn new(name: impl Into<Cow<'source, str>>, value: impl Into<Literal<'source>>) -> Self {
+        Self {
+            name: name.into(),
+            value: value.into(),
+        }
+    }
+
+    pub fn into_owned(self) -> Attribute<'static> {
+        Attribute {
+            name: Cow::Owned(self.name.into_owned()),
+            value: self.value.into_owned(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn value(&self) -> &Literal<'source> {
+        &self.value
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Literal<'source> {
     Number(u32),
-    String(&'source str),
+    #[serde(borrow)]
+    String(Cow<'source, str>),
     List(Vec<Literal<'source>>),
-    Range { start: u32, end: Option<u32> },
+    Bool(bool),
+    /// An attribute written without a value like the `open` of `details(open) {}`, it means
+    /// `true` to anything reading it as a boolean.
+    Flag,
+    /// A bare identifier used as an enum-like value, like the `ts` of `lang: ts`.
+    Identifier(Cow<'source, str>),
+    /// `{w: 640, h: 480}`, the entries are kept in source order and keys are unique.
+    Map(Vec<(Cow<'source, str>, Literal<'source>)>),
+    /// `start..end` excludes `end` like rust ranges do while `start..=end` includes it, `start..`
+    /// runs until whatever bound the consumer resolves it against. `by step` only keeps every
+    /// `step`th number starting from `start`. See [`LineSet`](crate::range::LineSet).
+    Range {
+        start: u32,
+        end: Option<u32>,
+        inclusive: bool,
+        step: Option<u32>,
+    },
+}
+
+impl<'source> Literal<'source> {
+    /// What kind of literal this is, as used in error messages.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Number(_) => "a number",
+            Self::String(_) => "a string",
+            Self::List(_) => "a list",
+            Self::Bool(_) => "a boolean",
+            Self::Flag => "a flag",
+            Self::Identifier(_) => "an identifier",
+            Self::Map(_) => "a map",
+            Self::Range { .. } => "a range",
+        }
+    }
+
+    /// The value of `key` if this is a map that has it.
+    pub fn get(&self, key: &str) -> Option<&Literal<'source>> {
+        match self {
+            Self::Map(entries) => entries
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn into_owned(self) -> Literal<'static> {
+        match self {
+            Self::Number(n) => Literal::Number(n),
+            Self::String(s) => Literal::String(Cow::Owned(s.into_owned())),
+            Self::List(items) => {
+                Literal::List(items.into_iter().map(Literal::into_owned).collect())
+            }
+            Self::Bool(b) => Literal::Bool(b),
+            Self::Flag => Literal::Flag,
+            Self::Identifier(name) => Literal::Identifier(Cow::Owned(name.into_owned())),
+            Self::Map(entries) => Literal::Map(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (Cow::Owned(key.into_owned()), value.into_owned()))
+                    .collect(),
+            ),
+            Self::Range {
+                start,
+                end,
+                inclusive,
+                step,
+            } => Literal::Range {
+                start,
+                end,
+                inclusive,
+                step,
+            },
+        }
+    }
 }
 
 impl<'source> Display for Literal<'source> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Number(n) => write!(f, "{n}"),
-            Self::String(s) => write!(f, "{s}"),
-            Self::Range { start, end } => {
-                write!(
-                    f,
-                    "{start}..{}",
-                    end.map_or(Self::String(""), |end| Self::Number(end))
-                )
+            Self::String(s) => write!(f, "{}", Quoted(s)),
+            Self::Bool(b) => write!(f, "{b}"),
+            //Printers write flags as the attribute's name alone.
+            Self::Flag => write!(f, "true"),
+            Self::Identifier(name) => write!(f, "{name}"),
+            Self::Range {
+                start,
+                end,
+                inclusive,
+                step,
+            } => {
+                match (end, inclusive) {
+                    (Some(end), true) => write!(f, "{start}..={end}")?,
+                    (Some(end), false) => write!(f, "{start}..{end}")?,
+                    (None, _) => write!(f, "{start}..")?,
+                }
+                match step {
+                    Some(step) => write!(f, " by {step}"),
+                    None => Ok(()),
+                }
             }
             Self::List(ls) => {
                 write!(f, "[")?;
-                for l in 0..ls.len() - 1 {
-                    write!(f, "{},", ls[l])?;
+                for (i, l) in ls.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{l}")?;
                 }
-                write!(f, "{}", ls.last().map_or(Self::String(""), |l| l.clone()))?;
                 write!(f, "]")
             }
+            Self::Map(entries) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{key}: {value}")?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+impl From<u32> for Literal<'_> {
+    fn from(n: u32) -> Self {
+        Self::Number(n)
+    }
+}
+
+impl From<bool> for Literal<'_> {
+    fn from(b: bool) -> Self {
+        Self::Bool(b)
+    }
+}
+
+impl<'source> From<&'source str> for Literal<'source> {
+    fn from(s: &'source str) -> Self {
+        Self::String(s.into())
+    }
+}
+
+impl From<String> for Literal<'_> {
+    fn from(s: String) -> Self {
+        Self::String(s.into())
+    }
+}
+
+impl<'source> From<Cow<'source, str>> for Literal<'source> {
+    fn from(s: Cow<'source, str>) -> Self {
+        Self::String(s)
+    }
+}
+
+impl<'source, T: Into<Literal<'source>>> From<Vec<T>> for Literal<'source> {
+    fn from(items: Vec<T>) -> Self {
+        Self::List(items.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<'source, T: Into<Literal<'source>>, const N: usize> From<[T; N]> for Literal<'source> {
+    fn from(items: [T; N]) -> Self {
+        Self::List(items.into_iter().map(Into::into).collect())
+    }
+}
+
+impl From<Range<u32>> for Literal<'_> {
+    fn from(range: Range<u32>) -> Self {
+        Self::Range {
+            start: range.start,
+            end: Some(range.end),
+            inclusive: false,
+            step: None,
+        }
+    }
+}
+
+impl From<RangeInclusive<u32>> for Literal<'_> {
+    fn from(range: RangeInclusive<u32>) -> Self {
+        Self::Range {
+            start: *range.start(),
+            end: Some(*range.end()),
+            inclusive: true,
+            step: None,
         }
     }
 }
+
+impl From<RangeFrom<u32>> for Literal<'_> {
+    fn from(range: RangeFrom<u32>) -> Self {
+        Self::Range {
+            start: range.start,
+            end: None,
+            inclusive: false,
+            step: None,
+        }
+    }
+}
+
+/// Values that can be spliced into the children of a tag, see the `markup!` macro of the
+/// `markup-macros` crate.
+pub trait IntoNodes<'source> {
+    fn into_nodes(self) -> Vec<Node<'source>>;
+}
+
+impl<'source> IntoNodes<'source> for Node<'source> {
+    fn into_nodes(self) -> Vec<Node<'source>> {
+        vec![self]
+    }
+}
+
+impl<'source> IntoNodes<'source> for Tag<'source> {
+    fn into_nodes(self) -> Vec<Node<'source>> {
+        vec![Node::Tag(self)]
+    }
+}
+
+impl<'source> IntoNodes<'source> for &'source str {
+    fn into_nodes(self) -> Vec<Node<'source>> {
+        vec![Node::String(self.into())]
+    }
+}
+
+impl<'source> IntoNodes<'source> for String {
+    fn into_nodes(self) -> Vec<Node<'source>> {
+        vec![Node::String(self.into())]
+    }
+}
+
+impl<'source> IntoNodes<'source> for Cow<'source, str> {
+    fn into_nodes(self) -> Vec<Node<'source>> {
+        vec![Node::String(self)]
+    }
+}
+
+impl<'source, T: IntoNodes<'source>> IntoNodes<'source> for Vec<T> {
+    fn into_nodes(self) -> Vec<Node<'source>> {
+        self.into_iter().flat_map(IntoNodes::into_nodes).collect()
+    }
+}
+
+impl<'source, T: IntoNodes<'source>> IntoNodes<'source> for Option<T> {
+    fn into_nodes(self) -> Vec<Node<'source>> {
+        self.map_or_else(Vec::new, IntoNodes::into_nodes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Literal, Node, Tag};
+    use crate::parser::Parser;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn builder_matches_parser() {
+        let source =
+            r#"code-block(lang: "rs", highlights: [1, 3..=5], width: 80) { "fn main() {}" }"#;
+        let built = Tag::new("code-block")
+            .with_attr("lang", "rs")
+            .with_attr("highlights", [Literal::Number(1), (3..=5).into()])
+            .with_attr("width", 80)
+            .with_child("fn main() {}");
+        assert_eq!(
+            Parser::new().parse(source.as_bytes()).unwrap(),
+            vec![Node::Tag(built)]
+        );
+    }
+
+    #[test]
+    fn into_owned_outlives_the_source() {
+        let owned: Node<'static> = {
+            let source = String::from(r#"p(id: "x") { "text" }"#);
+            let mut ast = Parser::new().parse(source.as_bytes()).unwrap();
+            ast.remove(0).into_owned()
+        };
+        let expected = Tag::new(String::from("p"))
+            .with_attr("id", String::from("x"))
+            .with_children([String::from("text")]);
+        assert_eq!(owned, Node::Tag(expected));
+    }
+}
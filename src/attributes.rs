@@ -0,0 +1,536 @@
+use crate::{
+    ast::{Document, Literal, Tag},
+    de,
+    range::RangeOrNumber,
+};
+use serde::Deserialize;
+use std::{
+    borrow::Cow,
+    ops::{Range, RangeFrom, RangeInclusive},
+};
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq, Eq, Clone)]
+pub enum AttributeError {
+    #[error("Missing attribute `{}`", .name)]
+    Missing { name: String },
+    #[error("Attribute `{}` should be {}, got {}", .name, .expected, .got)]
+    UnexpectedKind {
+        name: String,
+        expected: String,
+        got: String,
+    },
+    #[error("Attribute `{}` is given {} times", .name, .count)]
+    Duplicate { name: String, count: usize },
+}
+
+/// A type an attribute value can be converted to with [`Tag::get`].
+pub trait FromLiteral<'l>: Sized {
+    /// What the type expects, as used in error messages, e.g. `a list of numbers`.
+    fn expected() -> String;
+
+    /// What a list or a map of the type expects, e.g. `numbers` for `a list of numbers`.
+    fn expected_plural() -> String {
+        format!("values that are {}", Self::expected())
+    }
+
+    /// Converts `literal`, returns `None` if it is of the wrong kind.
+    fn from_literal(literal: &'l Literal<'_>) -> Option<Self>;
+
+    /// The value of a missing attribute, missing attributes are an error unless this is
+    /// overridden like [`Option`] does.
+    fn missing() -> Option<Self> {
+        None
+    }
+}
+
+impl<'source> Tag<'source> {
+    /// The value of the first attribute called `name`.
+    pub fn attr(&self, name: &str) -> Option<&Literal<'source>> {
+        self.attributes
+            .iter()
+            .find(|attr| attr.name() == name)
+            .map(|attr| attr.value())
+    }
+
+    /// Converts the attribute `name` to `T`.
+    ///
+    /// ```
+    /// use markup::{ast::Node, parser::Parser, range::RangeOrNumber};
+    ///
+    /// let ast = Parser::new()
+    ///     .parse(br#"code-block(lang: "ts", width: 80, highlights: [1, 3..=5]) {}"#)
+    ///     .unwrap();
+    /// let Node::Tag(tag) = &ast[0] else { unreachable!() };
+    /// assert_eq!(tag.get::<&str>("lang"), Ok("ts"));
+    /// assert_eq!(tag.get::<u32>("width"), Ok(80));
+    /// assert_eq!(tag.get::<Option<u32>>("height"), Ok(None));
+    /// assert_eq!(tag.get::<Vec<RangeOrNumber>>("highlights").unwrap().len(), 2);
+    /// assert!(tag.get::<u32>("lang").is_err());
+    /// ```
+    pub fn get<'l, T: FromLiteral<'l>>(&'l self, name: &str) -> Result<T, AttributeError> {
+        let mut values = self
+            .attributes
+            .iter()
+            .filter(|attr| attr.name() == name)
+            .map(|attr| attr.value());
+        let Some(value) = values.next() else {
+            return T::missing().ok_or_else(|| AttributeError::Missing {
+                name: name.to_string(),
+            });
+        };
+        let others = values.count();
+        if others != 0 {
+            return Err(AttributeError::Duplicate {
+                name: name.to_string(),
+                count: others + 1,
+            });
+        }
+        T::from_literal(value).ok_or_else(|| AttributeError::UnexpectedKind {
+            name: name.to_string(),
+            expected: T::expected(),
+            got: value.kind().to_string(),
+        })
+    }
+
+    /// Fails on the first attribute name given more than once.
+    pub fn check_duplicate_attributes(&self) -> Result<(), AttributeError> {
+        for (i, attr) in self.attributes.iter().enumerate() {
+            let count = self.attributes[i..]
+                .iter()
+                .filter(|other| other.name() == attr.name())
+                .count();
+            let seen_before = self.attributes[..i]
+                .iter()
+                .any(|other| other.name() == attr.name());
+            if count > 1 && !seen_before {
+                return Err(AttributeError::Duplicate {
+                    name: attr.name().to_string(),
+                    count,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'source> Document<'source> {
+    /// Converts the metadata attribute `name` to `T` like [`Tag::get`], a document without a
+    /// `meta` tag has no attributes.
+    ///
+    /// ```
+    /// use markup::parser::Parser;
+    ///
+    /// let document = Parser::new()
+    ///     .parse_document(br#"meta(title: "Guide", authors: ["a", "b"]); h1 { "Guide" }"#)
+    ///     .unwrap();
+    /// assert_eq!(document.title(), Ok(Some("Guide")));
+    /// assert_eq!(document.authors(), Ok(vec!["a", "b"]));
+    /// assert_eq!(document.date(), Ok(None));
+    /// assert_eq!(document.get::<u32>("version").unwrap_err().to_string(), "Missing attribute `version`");
+    /// assert_eq!(document.nodes.len(), 1);
+    /// ```
+    pub fn get<'l, T: FromLiteral<'l>>(&'l self, name: &str) -> Result<T, AttributeError> {
+        match &self.meta {
+            Some(meta) => meta.get(name),
+            None => T::missing().ok_or_else(|| AttributeError::Missing {
+                name: name.to_string(),
+            }),
+        }
+    }
+
+    /// The `title` string.
+    pub fn title(&self) -> Result<Option<&str>, AttributeError> {
+        self.get("title")
+    }
+
+    /// The `authors` list of strings, empty if there is none.
+    pub fn authors(&self) -> Result<Vec<&str>, AttributeError> {
+        Ok(self.get::<Option<_>>("authors")?.unwrap_or_default())
+    }
+
+    /// The `date` string, left for the consumer to parse.
+    pub fn date(&self) -> Result<Option<&str>, AttributeError> {
+        self.get("date")
+    }
+
+    /// Deserializes the metadata attributes into `T` with [`from_attributes`](crate::from_attributes).
+    pub fn metadata<T: Deserialize<'source>>(&self) -> Result<T, de::Error> {
+        match &self.meta {
+            Some(meta) => de::from_attributes(meta),
+            None => de::from_attributes(&Tag::new("meta")),
+        }
+    }
+}
+
+macro_rules! from_number {
+    ($($ty:ty),*) => {
+        $(
+            impl<'l> FromLiteral<'l> for $ty {
+                fn expected() -> String {
+                    "a number".to_string()
+                }
+
+                fn expected_plural() -> String {
+                    "numbers".to_string()
+                }
+
+                fn from_literal(literal: &'l Literal<'_>) -> Option<Self> {
+                    match literal {
+                        Literal::Number(n) => (*n).try_into().ok(),
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+from_number!(u32, u64, usize, i64);
+
+/// Identifiers convert to strings too, so `lang: ts` and `lang: "ts"` read the same.
+impl<'l> FromLiteral<'l> for &'l str {
+    fn expected() -> String {
+        "a string".to_string()
+    }
+
+    fn expected_plural() -> String {
+        "strings".to_string()
+    }
+
+    fn from_literal(literal: &'l Literal<'_>) -> Option<Self> {
+        match literal {
+            Literal::String(s) | Literal::Identifier(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+/// Flags are `true`.
+impl<'l> FromLiteral<'l> for bool {
+    fn expected() -> String {
+        "a boolean".to_string()
+    }
+
+    fn expected_plural() -> String {
+        "booleans".to_string()
+    }
+
+    fn from_literal(literal: &'l Literal<'_>) -> Option<Self> {
+        match literal {
+            Literal::Bool(b) => Some(*b),
+            Literal::Flag => Some(true),
+            _ => None,
+        }
+    }
+}
+
+impl<'l> FromLiteral<'l> for String {
+    fn expected() -> String {
+        "a string".to_string()
+    }
+
+    fn expected_plural() -> String {
+        "strings".to_string()
+    }
+
+    fn from_literal(literal: &'l Literal<'_>) -> Option<Self> {
+        <&str>::from_literal(literal).map(str::to_string)
+    }
+}
+
+impl<'l> FromLiteral<'l> for Cow<'l, str> {
+    fn expected() -> String {
+        "a string".to_string()
+    }
+
+    fn expected_plural() -> String {
+        "strings".to_string()
+    }
+
+    fn from_literal(literal: &'l Literal<'_>) -> Option<Self> {
+        <&str>::from_literal(literal).map(Cow::Borrowed)
+    }
+}
+
+impl<'l> FromLiteral<'l> for &'l Literal<'l> {
+    fn expected() -> String {
+        "any literal".to_string()
+    }
+
+    fn expected_plural() -> String {
+        "literals".to_string()
+    }
+
+    fn from_literal(literal: &'l Literal<'_>) -> Option<Self> {
+        Some(literal)
+    }
+}
+
+impl<'l, T: FromLiteral<'l>> FromLiteral<'l> for Option<T> {
+    fn expected() -> String {
+        T::expected()
+    }
+
+    fn expected_plural() -> String {
+        T::expected_plural()
+    }
+
+    fn from_literal(literal: &'l Literal<'_>) -> Option<Self> {
+        T::from_literal(literal).map(Some)
+    }
+
+    fn missing() -> Option<Self> {
+        Some(None)
+    }
+}
+
+impl<'l, T: FromLiteral<'l>> FromLiteral<'l> for Vec<T> {
+    fn expected() -> String {
+        format!("a list of {}", T::expected_plural())
+    }
+
+    fn expected_plural() -> String {
+        format!("lists of {}", T::expected_plural())
+    }
+
+    fn from_literal(literal: &'l Literal<'_>) -> Option<Self> {
+        match literal {
+            Literal::List(items) => items.iter().map(T::from_literal).collect(),
+            _ => None,
+        }
+    }
+}
+
+/// Map literals, in source order.
+impl<'l, T: FromLiteral<'l>> FromLiteral<'l> for Vec<(&'l str, T)> {
+    fn expected() -> String {
+        format!("a map of {}", T::expected_plural())
+    }
+
+    fn expected_plural() -> String {
+        format!("maps of {}", T::expected_plural())
+    }
+
+    fn from_literal(literal: &'l Literal<'_>) -> Option<Self> {
+        match literal {
+            Literal::Map(entries) => entries
+                .iter()
+                .map(|(key, value)| Some((key.as_ref(), T::from_literal(value)?)))
+                .collect(),
+            _ => None,
+        }
+    }
+}
+
+impl<'l> FromLiteral<'l> for RangeOrNumber {
+    fn expected() -> String {
+        "a number or a range".to_string()
+    }
+
+    fn expected_plural() -> String {
+        "numbers or ranges".to_string()
+    }
+
+    fn from_literal(literal: &'l Literal<'_>) -> Option<Self> {
+        match *literal {
+            Literal::Number(n) => Some(RangeOrNumber::Number(n)),
+            Literal::Range {
+                start,
+                end,
+                inclusive,
+                step,
+            } => Some(RangeOrNumber::Range {
+                start,
+                end,
+                inclusive,
+                step,
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl<'l> FromLiteral<'l> for Range<u32> {
+    fn expected() -> String {
+        "a closed range without a step".to_string()
+    }
+
+    fn expected_plural() -> String {
+        "closed ranges without a step".to_string()
+    }
+
+    fn from_literal(literal: &'l Literal<'_>) -> Option<Self> {
+        match *literal {
+            Literal::Range {
+                start,
+                end: Some(end),
+                inclusive,
+                step: None,
+            } => Some(start..if inclusive { end.checked_add(1)? } else { end }),
+            _ => None,
+        }
+    }
+}
+
+impl<'l> FromLiteral<'l> for RangeInclusive<u32> {
+    fn expected() -> String {
+        "a closed range without a step".to_string()
+    }
+
+    fn expected_plural() -> String {
+        "closed ranges without a step".to_string()
+    }
+
+    fn from_literal(literal: &'l Literal<'_>) -> Option<Self> {
+        match *literal {
+            Literal::Range {
+                start,
+                end: Some(end),
+                inclusive: true,
+                step: None,
+            } => Some(start..=end),
+            //An empty `start..start` range can't be represented as an inclusive range.
+            Literal::Range {
+                start,
+                end: Some(end),
+                inclusive: false,
+                step: None,
+            } if end > start => Some(start..=end - 1),
+            _ => None,
+        }
+    }
+}
+
+impl<'l> FromLiteral<'l> for RangeFrom<u32> {
+    fn expected() -> String {
+        "an open range without a step".to_string()
+    }
+
+    fn expected_plural() -> String {
+        "open ranges without a step".to_string()
+    }
+
+    fn from_literal(literal: &'l Literal<'_>) -> Option<Self> {
+        match *literal {
+            Literal::Range {
+                start,
+                end: None,
+                step: None,
+                ..
+            } => Some(start..),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AttributeError;
+    use crate::{
+        ast::{Literal, Node, Tag},
+        parser::Parser,
+        range::RangeOrNumber,
+    };
+    use pretty_assertions::assert_eq;
+    use std::ops::Range;
+
+    fn tag(source: &str) -> Tag<'_> {
+        match Parser::new().parse(source.as_bytes()).unwrap().remove(0) {
+            Node::Tag(tag) => tag,
+            Node::String(_) => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn typed_values() {
+        let tag = tag(
+            r#"image(src: "a.png", width: 640, lines: [1, 3..=5], span: 2..4, fit: cover, lazy, size: {w: 640, h: 480}) {}"#,
+        );
+        assert_eq!(
+            tag.get::<Vec<(&str, u32)>>("size"),
+            Ok(vec![("w", 640), ("h", 480)])
+        );
+        assert_eq!(
+            tag.attr("size").and_then(|size| size.get("h")),
+            Some(&Literal::Number(480))
+        );
+        assert_eq!(tag.attr("src"), Some(&Literal::String("a.png".into())));
+        assert_eq!(tag.get::<String>("src"), Ok("a.png".to_string()));
+        assert_eq!(tag.get::<usize>("width"), Ok(640));
+        assert_eq!(tag.get::<&str>("fit"), Ok("cover"));
+        assert_eq!(tag.get::<bool>("lazy"), Ok(true));
+        assert_eq!(tag.get::<std::ops::Range<u32>>("span"), Ok(2..4));
+        assert_eq!(tag.get::<std::ops::RangeInclusive<u32>>("span"), Ok(2..=3));
+        assert_eq!(
+            tag.get::<Vec<RangeOrNumber>>("lines"),
+            Ok(vec![
+                RangeOrNumber::Number(1),
+                RangeOrNumber::Range {
+                    start: 3,
+                    end: Some(5),
+                    inclusive: true,
+                    step: None
+                }
+            ])
+        );
+    }
+
+    #[test]
+    fn errors_name_the_attribute() {
+        let tag = tag(r#"image(src: "a.png", lines: [1, "2"], size: {w: "1"}) {}"#);
+        assert_eq!(
+            tag.get::<u32>("width"),
+            Err(AttributeError::Missing {
+                name: "width".to_string()
+            })
+        );
+        assert_eq!(
+            tag.get::<u32>("src").unwrap_err().to_string(),
+            "Attribute `src` should be a number, got a string"
+        );
+        assert_eq!(
+            tag.get::<Vec<u32>>("lines").unwrap_err().to_string(),
+            "Attribute `lines` should be a list of numbers, got a list"
+        );
+        assert_eq!(
+            tag.get::<Vec<(&str, u32)>>("size").unwrap_err().to_string(),
+            "Attribute `size` should be a map of numbers, got a map"
+        );
+        assert_eq!(
+            tag.get::<Vec<RangeOrNumber>>("lines")
+                .unwrap_err()
+                .to_string(),
+            "Attribute `lines` should be a list of numbers or ranges, got a list"
+        );
+        assert_eq!(
+            tag.get::<Vec<Vec<u32>>>("lines").unwrap_err().to_string(),
+            "Attribute `lines` should be a list of lists of numbers, got a list"
+        );
+        assert_eq!(
+            tag.get::<Vec<(&str, Vec<u32>)>>("size")
+                .unwrap_err()
+                .to_string(),
+            "Attribute `size` should be a map of lists of numbers, got a map"
+        );
+        assert_eq!(
+            tag.get::<Vec<Range<u32>>>("lines").unwrap_err().to_string(),
+            "Attribute `lines` should be a list of closed ranges without a step, got a list"
+        );
+    }
+
+    #[test]
+    fn duplicates() {
+        let tag = tag(r#"p(id: "a", class: "x", id: "b", id: "c") {}"#);
+        let names: Vec<_> = tag.attributes.iter().map(|attr| attr.name()).collect();
+        assert_eq!(names, vec!["id", "class", "id", "id"]);
+        assert_eq!(tag.attr("id"), Some(&Literal::String("a".into())));
+        let duplicate = Err(AttributeError::Duplicate {
+            name: "id".to_string(),
+            count: 3,
+        });
+        assert_eq!(tag.get::<&str>("id"), duplicate);
+        assert_eq!(tag.check_duplicate_attributes(), duplicate.map(|_| ()));
+        assert_eq!(tag.get::<&str>("class"), Ok("x"));
+    }
+}
@@ -0,0 +1,5 @@
+//! A language server for markup documents speaking the protocol over stdio.
+
+fn main() -> std::io::Result<()> {
+    markup::lsp::run(std::io::stdin().lock(), std::io::stdout().lock())
+}
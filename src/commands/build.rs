@@ -0,0 +1,210 @@
+use super::{reject_unknown_options, take_flag, take_option, Error};
+use markup::site::{Build, Site, SiteError};
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::{
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+
+const USAGE: &str = "\
+Usage: markup build <SOURCE> <OUTPUT> [OPTIONS]
+
+Renders every `.mu` file of the SOURCE directory to html in the OUTPUT directory, mirroring the
+tree, and copies every other file. Links to `.mu` files are rewritten to the html pages and
+broken references are reported.
+
+Options:
+    --template <FILE>    Wraps pages in FILE, where `{{title}}`, `{{head}}`, `{{content}}` and
+                         `{{root}}` are replaced, instead of a plain html page
+    --pass <PASS>        Runs a pass over every page, can be repeated, see `markup transform --help`
+    --watch              Keeps running and rebuilds the pages affected by every change";
+
+/// How long to wait for more changes after one, editors often write a file in several steps.
+const DEBOUNCE: Duration = Duration::from_millis(50);
+
+pub fn run(mut args: Vec<String>) -> Result<ExitCode, Error> {
+    if take_flag(&mut args, "--help") || take_flag(&mut args, "-h") {
+        println!("{USAGE}");
+        return Ok(ExitCode::SUCCESS);
+    }
+    let watch = take_flag(&mut args, "--watch");
+    let (mut site, template) = site(&mut args, USAGE, None)?;
+    if watch {
+        return self::watch(&mut site, template.as_deref(), |_| {});
+    }
+    let start = Instant::now();
+    let build = site.build().map_err(|err| Error::Build(err.to_string()))?;
+    report(&site, &build, start);
+    if build.errors.is_empty() {
+        Ok(ExitCode::SUCCESS)
+    } else {
+        Ok(ExitCode::FAILURE)
+    }
+}
+
+/// The site described by `args` and the path of its template, shared by the commands that build
+/// one. Without an output directory in `args` the site is built into `default_output`, if any.
+pub fn site(
+    args: &mut Vec<String>,
+    usage: &str,
+    default_output: Option<&Path>,
+) -> Result<(Site, Option<PathBuf>), Error> {
+    let template = take_option(args, "--template")?.map(PathBuf::from);
+    let mut specs = vec![];
+    while let Some(spec) = take_option(args, "--pass")? {
+        specs.push(spec);
+    }
+    reject_unknown_options(args)?;
+    let (source, output) = match (args.as_slice(), default_output) {
+        ([source, output], _) => (PathBuf::from(source), PathBuf::from(output)),
+        ([source], Some(output)) => (PathBuf::from(source), output.to_path_buf()),
+        _ => return Err(Error::Usage(usage.to_string())),
+    };
+    let site = Site::new(source, output)
+        .passes(&specs)
+        .map_err(|err| Error::Usage(err.to_string()))?;
+    let site = match &template {
+        Some(path) => site.template(read_template(path)?),
+        None => site,
+    };
+    Ok((site, template))
+}
+
+fn read_template(path: &Path) -> Result<String, Error> {
+    std::fs::read_to_string(path).map_err(|source| Error::Io {
+        path: path.display().to_string(),
+        source,
+    })
+}
+
+/// Builds `site` and rebuilds it on every change to its source directory or its `template` until
+/// the process is stopped, calling `on_build` after every build that changed something or failed.
+/// Errors are reported and the watch goes on so they can be fixed.
+pub fn watch(
+    site: &mut Site,
+    template: Option<&Path>,
+    mut on_build: impl FnMut(Result<&Build, &SiteError>),
+) -> Result<ExitCode, Error> {
+    let (sender, receiver) = mpsc::channel();
+    let watch_error = |err: notify::Error| Error::Build(format!("can't watch for changes: {err}"));
+    let mut watcher = notify::recommended_watcher(sender).map_err(watch_error)?;
+    watcher
+        .watch(site.source(), RecursiveMode::Recursive)
+        .map_err(watch_error)?;
+    let template = template.map(|path| path.canonicalize().unwrap_or(path.to_path_buf()));
+    if let Some(template) = &template {
+        watcher
+            .watch(template, RecursiveMode::NonRecursive)
+            .map_err(watch_error)?;
+    }
+
+    let start = Instant::now();
+    let result = catch_panic(|| site.build());
+    match &result {
+        Ok(build) => report(site, build, start),
+        Err(err) => eprintln!("error: {err}"),
+    }
+    on_build(result.as_ref());
+    //A failed build is done again entirely and the paths of a failed rebuild are rebuilt along
+    //with the next ones, so an error is reported until it's fixed.
+    let mut complete = result.is_ok();
+    let mut failed = vec![];
+    eprintln!("watching {} for changes", site.source().display());
+    while let Ok(event) = receiver.recv() {
+        let mut paths = std::mem::take(&mut failed);
+        let mut collect = |event: notify::Result<notify::Event>| match event {
+            Ok(event) if !matches!(event.kind, EventKind::Access(_)) => paths.extend(event.paths),
+            Ok(_) => {}
+            Err(err) => eprintln!("error: {err}"),
+        };
+        collect(event);
+        while let Ok(event) = receiver.recv_timeout(DEBOUNCE) {
+            collect(event);
+        }
+        let start = Instant::now();
+        let template_changed = template
+            .as_ref()
+            .is_some_and(|template| paths.iter().any(|path| path == template));
+        if let (Some(template), true) = (&template, template_changed) {
+            match read_template(template) {
+                Ok(contents) => *site = site.clone().template(contents),
+                Err(err) => {
+                    eprintln!("error: {err}");
+                    continue;
+                }
+            }
+        }
+        let full = template_changed || !complete;
+        let result = catch_panic(|| {
+            if full {
+                site.build()
+            } else {
+                site.rebuild(&paths)
+            }
+        });
+        //A panic can leave the pages half updated.
+        if full || matches!(result, Err(SiteError::Panicked(_))) {
+            complete = result.is_ok();
+        }
+        match &result {
+            Ok(build)
+                if build.pages.is_empty()
+                    && build.assets.is_empty()
+                    && build.removed.is_empty() =>
+            {
+                continue
+            }
+            Ok(build) => report(site, build, start),
+            Err(err) => {
+                eprintln!("error: {err}");
+                failed = paths;
+            }
+        }
+        on_build(result.as_ref());
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Runs `build`, turning a panic into an error so the watch goes on.
+fn catch_panic(build: impl FnOnce() -> Result<Build, SiteError>) -> Result<Build, SiteError> {
+    panic::catch_unwind(AssertUnwindSafe(build)).unwrap_or_else(|payload| {
+        let message = match (
+            payload.downcast_ref::<&str>(),
+            payload.downcast_ref::<String>(),
+        ) {
+            (Some(message), _) => message.to_string(),
+            (_, Some(message)) => message.clone(),
+            _ => "no message".to_string(),
+        };
+        Err(SiteError::Panicked(message))
+    })
+}
+
+/// Prints the diagnostics of `build` and a one line summary.
+fn report(site: &Site, build: &Build, start: Instant) {
+    for error in &build.errors {
+        eprintln!("{error}");
+    }
+    let count = |n: usize, what: &str| match n {
+        1 => format!("1 {what}"),
+        n => format!("{n} {what}s"),
+    };
+    let mut summary = format!("built {}", count(build.pages.len(), "page"));
+    if !build.assets.is_empty() {
+        summary += &format!(", copied {}", count(build.assets.len(), "file"));
+    }
+    if !build.removed.is_empty() {
+        summary += &format!(", removed {}", count(build.removed.len(), "file"));
+    }
+    if !build.errors.is_empty() {
+        summary += &format!(", {}", count(build.errors.len(), "error"));
+    }
+    eprintln!(
+        "{summary} into {} in {}ms",
+        site.output().display(),
+        start.elapsed().as_millis()
+    );
+}
@@ -0,0 +1,47 @@
+use super::{reject_unknown_options, take_flag, Error};
+use markup::{
+    pass::CrossReferences,
+    project::{Project, ProjectError},
+};
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: markup check <FILE>...
+
+Parses FILEs as one project and reports, with their locations, every `ref` tag pointing to an
+unknown id and every id defined more than once across the files.";
+
+pub fn run(mut args: Vec<String>) -> Result<ExitCode, Error> {
+    if take_flag(&mut args, "--help") || take_flag(&mut args, "-h") {
+        println!("{USAGE}");
+        return Ok(ExitCode::SUCCESS);
+    }
+    reject_unknown_options(&args)?;
+    if args.is_empty() {
+        return Err(Error::Usage(USAGE.to_string()));
+    }
+    let mut project = Project::new();
+    for path in &args {
+        project.load(path).map_err(project_error)?;
+    }
+    let mut files = project.parse().map_err(project_error)?;
+    let (_, errors) = CrossReferences::default().resolve(&mut files);
+    for error in &errors {
+        eprintln!("{error}");
+    }
+    if errors.is_empty() {
+        Ok(ExitCode::SUCCESS)
+    } else {
+        Ok(ExitCode::FAILURE)
+    }
+}
+
+fn project_error(error: ProjectError) -> Error {
+    match error {
+        ProjectError::Io { path, source } => Error::Io { path, source },
+        ProjectError::Parse { at, message } => Error::Parse {
+            path: at.to_string(),
+            message,
+        },
+    }
+}
@@ -0,0 +1,79 @@
+use std::io::Read;
+use thiserror::Error;
+
+pub mod build;
+pub mod check;
+pub mod query;
+pub mod serve;
+pub mod toc;
+pub mod transform;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("{0}")]
+    Usage(String),
+    #[error("{path}: {source}")]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("{path}: {message}")]
+    Parse { path: String, message: String },
+    #[error("{0}")]
+    Build(String),
+}
+
+/// Reads the contents of `path`, `-` or no path at all reads from stdin.
+pub fn read_source(path: Option<&str>) -> Result<(String, String), Error> {
+    match path {
+        None | Some("-") => {
+            let mut source = String::new();
+            std::io::stdin()
+                .read_to_string(&mut source)
+                .map_err(|source| Error::Io {
+                    path: "<stdin>".to_string(),
+                    source,
+                })?;
+            Ok(("<stdin>".to_string(), source))
+        }
+        Some(path) => {
+            let source = std::fs::read_to_string(path).map_err(|source| Error::Io {
+                path: path.to_string(),
+                source,
+            })?;
+            Ok((path.to_string(), source))
+        }
+    }
+}
+
+/// Takes the value of an option like `--format json` out of `args`.
+pub fn take_option(args: &mut Vec<String>, name: &str) -> Result<Option<String>, Error> {
+    let Some(index) = args.iter().position(|arg| arg == name) else {
+        return Ok(None);
+    };
+    if index + 1 >= args.len() {
+        return Err(Error::Usage(format!("`{name}` expects a value")));
+    }
+    let value = args.remove(index + 1);
+    args.remove(index);
+    Ok(Some(value))
+}
+
+/// Takes a flag like `--count` out of `args` and returns whether it was present.
+pub fn take_flag(args: &mut Vec<String>, name: &str) -> bool {
+    match args.iter().position(|arg| arg == name) {
+        Some(index) => {
+            args.remove(index);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Fails on any argument left over that looks like an option.
+pub fn reject_unknown_options(args: &[String]) -> Result<(), Error> {
+    match args.iter().find(|arg| arg.starts_with("--")) {
+        Some(arg) => Err(Error::Usage(format!("unknown option `{arg}`"))),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,101 @@
+use super::{read_source, reject_unknown_options, take_flag, take_option, Error};
+use markup::{
+    ast::{Node, Tag},
+    parser::Parser,
+    printer,
+    selector::Selector,
+};
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: markup query <SELECTOR> [FILE] [OPTIONS]
+
+Prints the tags of FILE (or stdin) matching SELECTOR, e.g. `code-block:not([lang])`.
+
+Options:
+    --format <FORMAT>    Output format: `markup` (default), `json` or `text`
+    --count              Print the number of matches instead of the matches
+    --fail-if-empty      Exit with status 1 if nothing matches
+    --fail-if-any        Exit with status 1 if anything matches";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Markup,
+    Json,
+    Text,
+}
+
+pub fn run(mut args: Vec<String>) -> Result<ExitCode, Error> {
+    if take_flag(&mut args, "--help") || take_flag(&mut args, "-h") {
+        println!("{USAGE}");
+        return Ok(ExitCode::SUCCESS);
+    }
+    let format = match take_option(&mut args, "--format")?.as_deref() {
+        None | Some("markup") => Format::Markup,
+        Some("json") => Format::Json,
+        Some("text") => Format::Text,
+        Some(format) => return Err(Error::Usage(format!("unknown format `{format}`"))),
+    };
+    let count = take_flag(&mut args, "--count");
+    let fail_if_empty = take_flag(&mut args, "--fail-if-empty");
+    let fail_if_any = take_flag(&mut args, "--fail-if-any");
+    reject_unknown_options(&args)?;
+    let (selector, path) = match args.as_slice() {
+        [selector] => (selector, None),
+        [selector, path] => (selector, Some(path.as_str())),
+        _ => return Err(Error::Usage(USAGE.to_string())),
+    };
+    let selector = Selector::parse(selector)
+        .map_err(|err| Error::Usage(format!("invalid selector: {err}")))?;
+
+    let (path, source) = read_source(path)?;
+    let ast = Parser::new()
+        .parse(source.as_bytes())
+        .map_err(|err| Error::Parse {
+            path,
+            message: err.to_string(),
+        })?;
+    let matches = selector.select(&ast);
+
+    if count {
+        println!("{}", matches.len());
+    } else {
+        match format {
+            Format::Markup => {
+                for tag in &matches {
+                    print!("{}", printer::print_tag(tag));
+                }
+            }
+            Format::Json => println!(
+                "{}",
+                serde_json::to_string_pretty(&matches).expect("the ast is always serializable")
+            ),
+            Format::Text => {
+                for tag in &matches {
+                    println!("{}", text_content(tag));
+                }
+            }
+        }
+    }
+
+    if (fail_if_empty && matches.is_empty()) || (fail_if_any && !matches.is_empty()) {
+        Ok(ExitCode::FAILURE)
+    } else {
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+/// Concatenates every string inside `tag`.
+fn text_content(tag: &Tag) -> String {
+    fn collect(nodes: &[Node], out: &mut String) {
+        for node in nodes {
+            match node {
+                Node::String(s) => out.push_str(s),
+                Node::Tag(tag) => collect(&tag.children, out),
+            }
+        }
+    }
+    let mut out = String::new();
+    collect(&tag.children, &mut out);
+    out
+}
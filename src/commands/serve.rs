@@ -0,0 +1,54 @@
+use super::{build, take_flag, take_option, Error};
+use markup::serve::{error_page, Server};
+use std::{net::TcpListener, path::PathBuf, process::ExitCode, thread};
+
+const USAGE: &str = "\
+Usage: markup serve <SOURCE> [OUTPUT] [OPTIONS]
+
+Builds the site of the SOURCE directory like `markup build` does, into OUTPUT or a temporary
+directory, serves it on localhost and reloads the pages open in a browser whenever a file changes.
+Build errors, like syntax errors, are shown in the browser in place of the pages. The temporary
+directory, `markup-serve-<PID>` in the system's, is left behind when the server stops.
+
+Options:
+    --port <PORT>        Listens on PORT instead of 8000
+    --template <FILE>    Wraps pages in FILE, see `markup build --help`
+    --pass <PASS>        Runs a pass over every page, can be repeated, see `markup transform --help`";
+
+const DEFAULT_PORT: u16 = 8000;
+
+pub fn run(mut args: Vec<String>) -> Result<ExitCode, Error> {
+    if take_flag(&mut args, "--help") || take_flag(&mut args, "-h") {
+        println!("{USAGE}");
+        return Ok(ExitCode::SUCCESS);
+    }
+    let port = match take_option(&mut args, "--port")? {
+        Some(port) => port
+            .parse()
+            .map_err(|_| Error::Usage(format!("invalid port `{port}`")))?,
+        None => DEFAULT_PORT,
+    };
+    let default_output = default_output();
+    let (mut site, template) = build::site(&mut args, USAGE, Some(&default_output))?;
+    let address = format!("127.0.0.1:{port}");
+    let listener = TcpListener::bind(&address).map_err(|source| Error::Io {
+        path: address.clone(),
+        source,
+    })?;
+    let server = Server::new(site.output());
+    let listening = server.clone();
+    thread::spawn(move || listening.listen(listener));
+    eprintln!("serving {} on http://{address}/", site.output().display());
+    let source = site.source().to_path_buf();
+    build::watch(&mut site, template.as_deref(), |result| match result {
+        Ok(_) => server.reload(),
+        Err(err) => server.fail(error_page(err, &source)),
+    })
+}
+
+/// Where the site is built without an output directory, the same for every run of the process.
+/// It stays behind once the process is stopped, which is the only way `serve` ends, so the last
+/// build can still be looked at and the system cleans it up with its other temporary files.
+fn default_output() -> PathBuf {
+    std::env::temp_dir().join(format!("markup-serve-{}", std::process::id()))
+}
@@ -0,0 +1,46 @@
+use super::{read_source, reject_unknown_options, take_flag, take_option, Error};
+use markup::{
+    parser::Parser,
+    pass::{Pass, Toc},
+};
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: markup toc [FILE] [OPTIONS]
+
+Prints the outline of the headings of FILE (or stdin) as json, with the ids the `toc` pass gives
+them.
+
+Options:
+    --headings <A,B>    The heading tags from the top level down, `h1` to `h6` by default";
+
+pub fn run(mut args: Vec<String>) -> Result<ExitCode, Error> {
+    if take_flag(&mut args, "--help") || take_flag(&mut args, "-h") {
+        println!("{USAGE}");
+        return Ok(ExitCode::SUCCESS);
+    }
+    let mut toc = match take_option(&mut args, "--headings")? {
+        Some(headings) => Toc::headings(&headings.split(',').collect::<Vec<_>>()),
+        None => Toc::default(),
+    };
+    reject_unknown_options(&args)?;
+    let path = match args.as_slice() {
+        [] => None,
+        [path] => Some(path.as_str()),
+        _ => return Err(Error::Usage(USAGE.to_string())),
+    };
+
+    let (path, source) = read_source(path)?;
+    let mut ast = Parser::new()
+        .parse(source.as_bytes())
+        .map_err(|err| Error::Parse {
+            path,
+            message: err.to_string(),
+        })?;
+    toc.run(&mut ast);
+    println!(
+        "{}",
+        serde_json::to_string_pretty(toc.outline()).expect("the outline is always serializable")
+    );
+    Ok(ExitCode::SUCCESS)
+}
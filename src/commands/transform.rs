@@ -0,0 +1,80 @@
+use super::{read_source, reject_unknown_options, take_flag, take_option, Error};
+use markup::{parser::Parser, pass::Pipeline, printer, semantic::has_comments};
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: markup transform [FILE] --pass <PASS>... [OPTIONS]
+
+Runs the given passes in order over FILE (or stdin), prints the result and reports what each
+pass changed on stderr. Comments aren't kept, a warning says so when FILE has any.
+
+Passes:
+    dedent                    Removes the indentation of nested strings
+    footnotes                 Numbers `footnote` tags and lists them at the end of each section
+    footnotes:sections=A,B    Same but also lists them at the end of A and B
+    inline                    Expands `*em*`, `**strong**`, `code` and [links](url) in strings
+    inline:skip=A,B           Same but leaves strings inside A and B alone, code-block always is
+    inline:only=A,B           Same but only inside A and B
+    number-figures            Numbers `figure` tags with a `number` attribute
+    rename:FROM=TO            Renames FROM tags to TO
+    toc                       Gives h1..h6 ids and fills `toc` tags with a list of links to them
+    toc:headings=A,B          Same but A and B are the headings, from the top level down
+    whitespace                Collapses whitespace in strings, see the README for the rules
+    whitespace:inline=A,B     Same but A and B are inline tags too
+    whitespace:preserve=A,B   Same but leaves strings inside A and B alone
+
+Options:
+    --pass <PASS>        Adds a pass to the pipeline, can be repeated
+    --format <FORMAT>    Output format: `markup` (default) or `json`
+    --quiet              Don't report the changes";
+
+pub fn run(mut args: Vec<String>) -> Result<ExitCode, Error> {
+    if take_flag(&mut args, "--help") || take_flag(&mut args, "-h") {
+        println!("{USAGE}");
+        return Ok(ExitCode::SUCCESS);
+    }
+    let mut specs = vec![];
+    while let Some(spec) = take_option(&mut args, "--pass")? {
+        specs.push(spec);
+    }
+    let json = match take_option(&mut args, "--format")?.as_deref() {
+        None | Some("markup") => false,
+        Some("json") => true,
+        Some(format) => return Err(Error::Usage(format!("unknown format `{format}`"))),
+    };
+    let quiet = take_flag(&mut args, "--quiet");
+    reject_unknown_options(&args)?;
+    let path = match args.as_slice() {
+        [] => None,
+        [path] => Some(path.as_str()),
+        _ => return Err(Error::Usage(USAGE.to_string())),
+    };
+    let mut pipeline = Pipeline::from_specs(&specs).map_err(|err| Error::Usage(err.to_string()))?;
+
+    let (path, source) = read_source(path)?;
+    let mut ast = Parser::new()
+        .parse(source.as_bytes())
+        .map_err(|err| Error::Parse {
+            path: path.clone(),
+            message: err.to_string(),
+        })?;
+    if has_comments(&source) {
+        eprintln!("warning: {path}: comments aren't kept in the output");
+    }
+    let reports = pipeline.run(&mut ast);
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&ast).expect("the ast is always serializable")
+        );
+    } else {
+        print!("{}", printer::print(&ast));
+    }
+    if !quiet {
+        for report in reports {
+            eprintln!("{}: {}", report.pass, report.changes);
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,426 @@
+use super::{list, Error, LiteralDeserializer};
+use crate::{
+    ast::{Attribute, Node, Tag},
+    parser::Parser,
+    project::{line_column, offset_of},
+};
+use serde::{
+    de::{
+        self, value::SeqDeserializer, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess,
+        VariantAccess, Visitor,
+    },
+    forward_to_deserialize_any, Deserialize, Deserializer,
+};
+
+/// Deserializes `T` from a document holding a single tag, the inverse of
+/// [`to_string`](crate::to_string) which documents how rust values map to markup.
+///
+/// Errors point at the line and column of the tag or attribute they are about.
+///
+/// ```
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize, Debug, PartialEq)]
+/// struct Server {
+///     host: String,
+///     port: u16,
+/// }
+///
+/// let server: Server = markup::from_str(r#"server(host: "localhost", port: 8080) {}"#).unwrap();
+/// assert_eq!(server.port, 8080);
+/// assert_eq!(
+///     markup::from_str::<Server>("server(host: \"localhost\") {}")
+///         .unwrap_err()
+///         .to_string(),
+///     "Missing attribute `port` at line 1, column 1"
+/// );
+/// ```
+pub fn from_str<'de, T: Deserialize<'de>>(source: &'de str) -> Result<T, Error> {
+    let nodes = Parser::new()
+        .parse(source.as_bytes())
+        .map_err(|error| locate(source, error.position(), Error::Parse(error.to_string())))?;
+    let mut tags = vec![];
+    for node in &nodes {
+        match node {
+            Node::Tag(tag) => tags.push(tag),
+            Node::String(s) => return Err(locate_str(source, s, Error::UnexpectedText)),
+        }
+    }
+    match tags.as_slice() {
+        [tag] => T::deserialize(TagDeserializer { tag, source }),
+        [] => Err(locate(source, 0, Error::ExpectedSingleTag(0))),
+        [_, second, ..] => Err(locate_str(
+            source,
+            &second.name,
+            Error::ExpectedSingleTag(tags.len()),
+        )),
+    }
+}
+
+/// Adds the line and column of the byte `offset` to `error` unless it already has a location.
+fn locate(source: &str, offset: usize, error: Error) -> Error {
+    if let Error::Located { .. } = error {
+        return error;
+    }
+    let (line, column) = line_column(source, offset);
+    Error::Located {
+        line,
+        column,
+        error: Box::new(error),
+    }
+}
+
+/// Locates `error` at `s` if it's borrowed from `source`, strings a pass created have no location.
+fn locate_str(source: &str, s: &str, error: Error) -> Error {
+    match offset_of(source, s) {
+        Some(offset) => locate(source, offset, error),
+        None => error,
+    }
+}
+
+enum Entry<'a, 'de> {
+    Attribute(&'a Attribute<'de>),
+    Tags(&'a str, Vec<&'a Tag<'de>>),
+}
+
+impl<'a, 'de> Entry<'a, 'de> {
+    fn name(&self) -> &'a str {
+        match self {
+            Entry::Attribute(attr) => attr.name(),
+            Entry::Tags(name, _) => name,
+        }
+    }
+}
+
+struct TagDeserializer<'a, 'de> {
+    tag: &'a Tag<'de>,
+    source: &'de str,
+}
+
+impl<'a, 'de> TagDeserializer<'a, 'de> {
+    /// The attributes followed by the children grouped by name, in the order they first appear.
+    fn entries(&self) -> Result<Vec<Entry<'a, 'de>>, Error> {
+        let mut entries: Vec<Entry> = vec![];
+        for attr in &self.tag.attributes {
+            if entries.iter().any(|entry| entry.name() == attr.name()) {
+                return Err(self.locate(attr.name(), Error::DuplicateAttribute(attr.name().into())));
+            }
+            entries.push(Entry::Attribute(attr));
+        }
+        for child in &self.tag.children {
+            let child = match child {
+                Node::Tag(child) => child,
+                Node::String(s) => return Err(self.locate(s, Error::UnexpectedText)),
+            };
+            match entries.iter_mut().find(|entry| entry.name() == child.name) {
+                Some(Entry::Tags(_, tags)) => tags.push(child),
+                Some(Entry::Attribute(_)) => {
+                    return Err(
+                        self.locate(&child.name, Error::DuplicateTag(child.name.to_string()))
+                    )
+                }
+                None => entries.push(Entry::Tags(&child.name, vec![child])),
+            }
+        }
+        Ok(entries)
+    }
+
+    fn locate(&self, s: &str, error: Error) -> Error {
+        locate_str(self.source, s, error)
+    }
+}
+
+impl<'a, 'de> IntoDeserializer<'de, Error> for TagDeserializer<'a, 'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'a, 'de> Deserializer<'de> for TagDeserializer<'a, 'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let access = TagAccess {
+            entries: self.entries()?.into_iter(),
+            value: None,
+            source: self.source,
+        };
+        visitor
+            .visit_map(access)
+            .map_err(|error| self.locate(&self.tag.name, error))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        if let Some(attr) = self
+            .tag
+            .attributes
+            .iter()
+            .find(|attr| !fields.contains(&attr.name()))
+        {
+            let error = de::Error::unknown_field(attr.name(), fields);
+            return Err(self.locate(attr.name(), error));
+        }
+        let unknown = self.tag.children.iter().find_map(|child| match child {
+            Node::Tag(child) if !fields.contains(&child.name.as_ref()) => Some(child),
+            _ => None,
+        });
+        if let Some(child) = unknown {
+            let error = Error::UnknownTag {
+                name: child.name.to_string(),
+                expected: list(fields),
+            };
+            return Err(self.locate(&child.name, error));
+        }
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match (self.tag.attributes.as_slice(), self.tag.children.as_slice()) {
+            ([], [Node::Tag(variant)]) => visitor
+                .visit_enum(VariantDeserializer {
+                    tag: variant,
+                    source: self.source,
+                })
+                .map_err(|error| self.locate(&variant.name, error)),
+            _ => Err(self.locate(&self.tag.name, Error::ExpectedVariant)),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct identifier ignored_any
+    }
+}
+
+struct TagAccess<'a, 'de> {
+    entries: std::vec::IntoIter<Entry<'a, 'de>>,
+    value: Option<Entry<'a, 'de>>,
+    source: &'de str,
+}
+
+impl<'a, 'de> MapAccess<'de> for TagAccess<'a, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        match self.entries.next() {
+            Some(entry) => {
+                let name = entry.name();
+                self.value = Some(entry);
+                seed.deserialize(name.into_deserializer()).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self
+            .value
+            .take()
+            .expect("next_value_seed is called after next_key_seed")
+        {
+            Entry::Attribute(attr) => {
+                seed.deserialize(LiteralDeserializer(attr.value()))
+                    .map_err(|error| {
+                        locate_str(self.source, attr.name(), error.in_attribute(attr.name()))
+                    })
+            }
+            Entry::Tags(_, tags) => seed.deserialize(ChildrenDeserializer {
+                tags,
+                source: self.source,
+            }),
+        }
+    }
+}
+
+/// The children of a tag that have the same name, a sequence or a single value.
+struct ChildrenDeserializer<'a, 'de> {
+    tags: Vec<&'a Tag<'de>>,
+    source: &'de str,
+}
+
+impl<'a, 'de> ChildrenDeserializer<'a, 'de> {
+    fn single(self) -> Result<TagDeserializer<'a, 'de>, Error> {
+        match self.tags.as_slice() {
+            [tag] => Ok(TagDeserializer {
+                tag,
+                source: self.source,
+            }),
+            [_, second, ..] => Err(locate_str(
+                self.source,
+                &second.name,
+                Error::DuplicateTag(second.name.to_string()),
+            )),
+            [] => unreachable!("children are grouped by the name of at least one tag"),
+        }
+    }
+}
+
+impl<'a, 'de> Deserializer<'de> for ChildrenDeserializer<'a, 'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        if self.tags.len() == 1 {
+            self.single()?.deserialize_any(visitor)
+        } else {
+            self.deserialize_seq(visitor)
+        }
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let source = self.source;
+        let mut seq = SeqDeserializer::new(
+            self.tags
+                .into_iter()
+                .map(|tag| TagDeserializer { tag, source }),
+        );
+        let value = visitor.visit_seq(&mut seq)?;
+        seq.end()?;
+        Ok(value)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.single()?.deserialize_map(visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.single()?.deserialize_struct(name, fields, visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.single()?.deserialize_newtype_struct(name, visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.single()?.deserialize_enum(name, variants, visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct tuple_struct identifier ignored_any
+    }
+}
+
+/// A tag named after the variant of an enum, its attributes and children are the variant's fields.
+struct VariantDeserializer<'a, 'de> {
+    tag: &'a Tag<'de>,
+    source: &'de str,
+}
+
+impl<'a, 'de> EnumAccess<'de> for VariantDeserializer<'a, 'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self::Variant), Self::Error> {
+        let name: &str = &self.tag.name;
+        let variant = seed.deserialize(name.into_deserializer())?;
+        Ok((variant, self))
+    }
+}
+
+impl<'a, 'de> VariantAccess<'de> for VariantDeserializer<'a, 'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        if self.tag.attributes.is_empty() && self.tag.children.is_empty() {
+            Ok(())
+        } else {
+            Err(de::Error::invalid_type(
+                de::Unexpected::Map,
+                &"a unit variant",
+            ))
+        }
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, Self::Error> {
+        seed.deserialize(TagDeserializer {
+            tag: self.tag,
+            source: self.source,
+        })
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        Err(de::Error::invalid_type(de::Unexpected::Map, &visitor))
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        TagDeserializer {
+            tag: self.tag,
+            source: self.source,
+        }
+        .deserialize_struct("", fields, visitor)
+    }
+}
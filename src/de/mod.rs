@@ -0,0 +1,517 @@
+use crate::ast::{Attribute, Literal, Tag};
+use serde::{
+    de::{
+        self,
+        value::{MapDeserializer, SeqDeserializer},
+        DeserializeSeed, IntoDeserializer, MapAccess, Visitor,
+    },
+    forward_to_deserialize_any, Deserialize, Deserializer,
+};
+use std::{borrow::Cow, fmt::Display};
+use thiserror::Error;
+
+mod document;
+
+pub use document::from_str;
+
+#[derive(Debug, Error, PartialEq, Eq, Clone)]
+pub enum Error {
+    #[error("Missing attribute `{}`", .0)]
+    MissingAttribute(String),
+    #[error("Unknown attribute `{}`, expected one of {}", .name, .expected)]
+    UnknownAttribute { name: String, expected: String },
+    #[error("Attribute `{}` is given more than once", .0)]
+    DuplicateAttribute(String),
+    #[error("Invalid attribute `{}`: {}", .name, .message)]
+    InvalidAttribute { name: String, message: String },
+    #[error("Unknown tag `{}`, expected one of {}", .name, .expected)]
+    UnknownTag { name: String, expected: String },
+    #[error("Tag `{}` is given more than once", .0)]
+    DuplicateTag(String),
+    #[error("Expected a single tag naming the variant")]
+    ExpectedVariant,
+    #[error("Unexpected text, only tags are allowed here")]
+    UnexpectedText,
+    #[error("Expected a single tag at the top of the document, got {}", .0)]
+    ExpectedSingleTag(usize),
+    #[error("{}", .0)]
+    Parse(String),
+    #[error("{} at line {}, column {}", .error, .line, .column)]
+    Located {
+        line: usize,
+        column: usize,
+        error: Box<Error>,
+    },
+    #[error("{}", .0)]
+    Custom(String),
+}
+
+impl de::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Self::Custom(msg.to_string())
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        Self::MissingAttribute(field.to_string())
+    }
+
+    fn unknown_field(field: &str, expected: &'static [&'static str]) -> Self {
+        Self::UnknownAttribute {
+            name: field.to_string(),
+            expected: list(expected),
+        }
+    }
+
+    fn duplicate_field(field: &'static str) -> Self {
+        Self::DuplicateAttribute(field.to_string())
+    }
+}
+
+impl Error {
+    /// Names the attribute an error happened in, errors that already name one are kept.
+    pub(crate) fn in_attribute(self, name: &str) -> Self {
+        match self {
+            Self::Custom(message) => Self::InvalidAttribute {
+                name: name.to_string(),
+                message,
+            },
+            error => error,
+        }
+    }
+}
+
+pub(crate) fn list(names: &[&str]) -> String {
+    names
+        .iter()
+        .map(|name| format!("`{name}`"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Deserializes the attributes of `tag` into `T`, usually a struct with one field per attribute.
+///
+/// Numbers, strings, booleans, lists and maps map to the rust types serde would expect, flags are
+/// `true` and identifiers deserialize like strings. Both identifiers and strings deserialize unit
+/// enum variants. Closed ranges deserialize into [`std::ops::Range`] and
+/// [`std::ops::RangeInclusive`], any range into [`RangeOrNumber`](crate::range::RangeOrNumber).
+/// Missing attributes are only allowed for `Option` and `#[serde(default)]` fields and attributes
+/// the struct doesn't have are an error. `&str` fields borrow from the parsed source, so they
+/// fail on strings a pass replaced, `String` and `Cow<str>` fields work for both.
+///
+/// ```
+/// use markup::{ast::Node, parser::Parser};
+/// use serde::Deserialize;
+/// use std::ops::Range;
+///
+/// #[derive(Deserialize, Debug, PartialEq)]
+/// #[serde(rename_all = "lowercase")]
+/// enum Lang {
+///     Rust,
+///     Ts,
+/// }
+///
+/// #[derive(Deserialize, Debug, PartialEq)]
+/// struct CodeBlockOptions {
+///     lang: Lang,
+///     highlights: Vec<Range<u32>>,
+///     width: Option<u32>,
+/// }
+///
+/// let ast = Parser::new()
+///     .parse(b"code-block(lang: ts, highlights: [1..3, 5..=6]) {}")
+///     .unwrap();
+/// let Node::Tag(tag) = &ast[0] else { unreachable!() };
+/// assert_eq!(
+///     markup::from_attributes::<CodeBlockOptions>(tag).unwrap(),
+///     CodeBlockOptions {
+///         lang: Lang::Ts,
+///         highlights: vec![1..3, 5..7],
+///         width: None,
+///     }
+/// );
+/// ```
+pub fn from_attributes<'de, T: Deserialize<'de>>(tag: &Tag<'de>) -> Result<T, Error> {
+    T::deserialize(AttributesDeserializer {
+        attributes: &tag.attributes,
+    })
+}
+
+struct AttributesDeserializer<'a, 'de> {
+    attributes: &'a [Attribute<'de>],
+}
+
+impl<'a, 'de> Deserializer<'de> for AttributesDeserializer<'a, 'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_map(Attributes {
+            attributes: self.attributes.iter(),
+            value: None,
+        })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        if let Some(attr) = self
+            .attributes
+            .iter()
+            .find(|attr| !fields.contains(&attr.name()))
+        {
+            return Err(de::Error::unknown_field(attr.name(), fields));
+        }
+        self.deserialize_any(visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map enum identifier ignored_any
+    }
+}
+
+struct Attributes<'a, 'de> {
+    attributes: std::slice::Iter<'a, Attribute<'de>>,
+    value: Option<&'a Attribute<'de>>,
+}
+
+impl<'a, 'de> MapAccess<'de> for Attributes<'a, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        match self.attributes.next() {
+            Some(attr) => {
+                self.value = Some(attr);
+                seed.deserialize(attr.name().into_deserializer()).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        let attr = self
+            .value
+            .take()
+            .expect("next_value_seed is called after next_key_seed");
+        seed.deserialize(LiteralDeserializer(attr.value()))
+            .map_err(|error| error.in_attribute(attr.name()))
+    }
+}
+
+/// The entries of a map literal, visited like the attributes of a tag.
+struct Entries<'a, 'de> {
+    entries: std::slice::Iter<'a, (Cow<'de, str>, Literal<'de>)>,
+    value: Option<&'a (Cow<'de, str>, Literal<'de>)>,
+}
+
+impl<'a, 'de> MapAccess<'de> for Entries<'a, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        match self.entries.next() {
+            Some(entry) => {
+                self.value = Some(entry);
+                seed.deserialize(entry.0.as_ref().into_deserializer())
+                    .map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        let (_, value) = self
+            .value
+            .take()
+            .expect("next_value_seed is called after next_key_seed");
+        seed.deserialize(LiteralDeserializer(value))
+    }
+}
+
+/// Deserializes a single attribute value, strings can be borrowed if they borrow from the source.
+pub(crate) struct LiteralDeserializer<'a, 'de>(pub(crate) &'a Literal<'de>);
+
+impl<'a, 'de> IntoDeserializer<'de, Error> for LiteralDeserializer<'a, 'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'a, 'de> LiteralDeserializer<'a, 'de> {
+    /// Ranges are visited as a `start`, `end`, `step` map with an exclusive `end` unless
+    /// `inclusive` is set, which is what `RangeInclusive` expects. Open ranges have no `end`.
+    fn visit_range<V: Visitor<'de>>(self, visitor: V, inclusive: bool) -> Result<V::Value, Error> {
+        let Literal::Range {
+            start,
+            end,
+            inclusive: literal_inclusive,
+            step,
+        } = *self.0
+        else {
+            return self.deserialize_any(visitor);
+        };
+        let mut entries = vec![("start", start as u64)];
+        if let Some(end) = end {
+            let end = match (literal_inclusive, inclusive) {
+                (true, false) => end as u64 + 1,
+                (false, true) if end == start => {
+                    return Err(de::Error::invalid_value(
+                        de::Unexpected::Other("an empty range"),
+                        &visitor,
+                    ))
+                }
+                (false, true) => end as u64 - 1,
+                _ => end as u64,
+            };
+            entries.push(("end", end));
+        }
+        if let Some(step) = step {
+            entries.push(("step", step as u64));
+        }
+        let mut map = MapDeserializer::new(entries.into_iter());
+        let value = visitor.visit_map(&mut map)?;
+        map.end()?;
+        Ok(value)
+    }
+}
+
+impl<'a, 'de> Deserializer<'de> for LiteralDeserializer<'a, 'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            Literal::Number(n) => visitor.visit_u32(*n),
+            Literal::String(Cow::Borrowed(s)) | Literal::Identifier(Cow::Borrowed(s)) => {
+                visitor.visit_borrowed_str(s)
+            }
+            Literal::String(Cow::Owned(s)) | Literal::Identifier(Cow::Owned(s)) => {
+                visitor.visit_str(s)
+            }
+            Literal::Bool(b) => visitor.visit_bool(*b),
+            Literal::Flag => visitor.visit_bool(true),
+            Literal::List(items) => {
+                let mut seq = SeqDeserializer::new(items.iter().map(LiteralDeserializer));
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            Literal::Map(entries) => visitor.visit_map(Entries {
+                entries: entries.iter(),
+                value: None,
+            }),
+            Literal::Range { .. } => self.visit_range(visitor, false),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match name {
+            "RangeInclusive" => self.visit_range(visitor, true),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            Literal::String(s) | Literal::Identifier(s) => {
+                visitor.visit_enum(s.as_ref().into_deserializer())
+            }
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_attributes, Error};
+    use crate::{
+        ast::{Node, Tag},
+        parser::Parser,
+        range::RangeOrNumber,
+    };
+    use pretty_assertions::assert_eq;
+    use serde::Deserialize;
+    use std::{
+        collections::BTreeMap,
+        ops::{Range, RangeInclusive},
+    };
+
+    fn tag(source: &str) -> Tag<'_> {
+        match Parser::new().parse(source.as_bytes()).unwrap().remove(0) {
+            Node::Tag(tag) => tag,
+            Node::String(_) => unreachable!(),
+        }
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(rename_all = "kebab-case")]
+    enum Lang {
+        Rust,
+        TypeScript,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct CodeBlockOptions<'a> {
+        lang: Lang,
+        title: Option<&'a str>,
+        width: Option<u64>,
+        #[serde(default)]
+        highlights: Vec<RangeOrNumber>,
+    }
+
+    #[test]
+    fn structs() {
+        assert_eq!(
+            from_attributes::<CodeBlockOptions>(&tag(
+                r#"code-block(lang: "type-script", title: "main.ts", highlights: [1, 3..=5 by 2]) {}"#
+            )),
+            Ok(CodeBlockOptions {
+                lang: Lang::TypeScript,
+                title: Some("main.ts"),
+                width: None,
+                highlights: vec![
+                    RangeOrNumber::Number(1),
+                    RangeOrNumber::Range {
+                        start: 3,
+                        end: Some(6),
+                        inclusive: false,
+                        step: Some(2)
+                    }
+                ],
+            })
+        );
+        assert_eq!(
+            from_attributes::<CodeBlockOptions>(&tag(r#"code-block(lang: "rust") {}"#)),
+            Ok(CodeBlockOptions {
+                lang: Lang::Rust,
+                title: None,
+                width: None,
+                highlights: vec![],
+            })
+        );
+    }
+
+    #[test]
+    fn ranges() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Ranges {
+            a: Range<u32>,
+            b: RangeInclusive<u32>,
+            c: RangeOrNumber,
+        }
+        assert_eq!(
+            from_attributes::<Ranges>(&tag("t(a: 1..=3, b: 1..3, c: 4..) {}")),
+            Ok(Ranges {
+                a: 1..4,
+                b: 1..=2,
+                c: RangeOrNumber::Range {
+                    start: 4,
+                    end: None,
+                    inclusive: false,
+                    step: None
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn maps() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Size {
+            w: u32,
+            h: u32,
+        }
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Image {
+            size: Size,
+            langs: BTreeMap<String, Lang>,
+        }
+        assert_eq!(
+            from_attributes::<Image>(&tag(
+                "image(size: {h: 480, w: 640}, langs: {a: rust, b: type-script}) {}"
+            )),
+            Ok(Image {
+                size: Size { w: 640, h: 480 },
+                langs: BTreeMap::from([
+                    ("a".to_string(), Lang::Rust),
+                    ("b".to_string(), Lang::TypeScript)
+                ]),
+            })
+        );
+    }
+
+    #[test]
+    fn errors_name_the_attribute() {
+        assert_eq!(
+            from_attributes::<CodeBlockOptions>(&tag(r#"code-block(title: "x") {}"#)),
+            Err(Error::MissingAttribute("lang".to_string()))
+        );
+        assert_eq!(
+            from_attributes::<CodeBlockOptions>(&tag(r#"code-block(lang: "rust", lines: 1) {}"#))
+                .unwrap_err()
+                .to_string(),
+            "Unknown attribute `lines`, expected one of `lang`, `title`, `width`, `highlights`"
+        );
+        assert_eq!(
+            from_attributes::<CodeBlockOptions>(&tag(r#"code-block(lang: "go") {}"#))
+                .unwrap_err()
+                .to_string(),
+            "Invalid attribute `lang`: unknown variant `go`, expected `rust` or `type-script`"
+        );
+        assert_eq!(
+            from_attributes::<CodeBlockOptions>(&tag(r#"code-block(lang: "rust", width: "1") {}"#))
+                .unwrap_err()
+                .to_string(),
+            "Invalid attribute `width`: invalid type: string \"1\", expected u64"
+        );
+        assert_eq!(
+            from_attributes::<CodeBlockOptions>(&tag(
+                r#"code-block(lang: "rust", lang: "rust") {}"#
+            )),
+            Err(Error::DuplicateAttribute("lang".to_string()))
+        );
+    }
+}
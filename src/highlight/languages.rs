@@ -0,0 +1,295 @@
+use super::{
+    scanner::{scan, Grammar},
+    Class, Language, Span,
+};
+use crate::semantic::{self, TokenKind};
+
+const JAVASCRIPT: Grammar = Grammar {
+    keywords: &[
+        "async",
+        "await",
+        "break",
+        "case",
+        "catch",
+        "class",
+        "const",
+        "continue",
+        "debugger",
+        "default",
+        "delete",
+        "do",
+        "else",
+        "export",
+        "extends",
+        "finally",
+        "for",
+        "from",
+        "function",
+        "if",
+        "import",
+        "in",
+        "instanceof",
+        "let",
+        "new",
+        "of",
+        "return",
+        "static",
+        "super",
+        "switch",
+        "this",
+        "throw",
+        "try",
+        "typeof",
+        "var",
+        "void",
+        "while",
+        "with",
+        "yield",
+    ],
+    constants: &["true", "false", "null", "undefined", "NaN", "Infinity"],
+    types: &[],
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    quotes: &['"', '\'', '`'],
+    word_comments: false,
+    lifetimes: false,
+    raw_strings: false,
+    variables: false,
+    keys: false,
+    capitalized_types: true,
+    identifier_chars: &['$'],
+};
+
+const TYPESCRIPT: Grammar = Grammar {
+    keywords: &[
+        "abstract",
+        "as",
+        "async",
+        "await",
+        "break",
+        "case",
+        "catch",
+        "class",
+        "const",
+        "continue",
+        "debugger",
+        "declare",
+        "default",
+        "delete",
+        "do",
+        "else",
+        "enum",
+        "export",
+        "extends",
+        "finally",
+        "for",
+        "from",
+        "function",
+        "if",
+        "implements",
+        "import",
+        "in",
+        "instanceof",
+        "interface",
+        "is",
+        "keyof",
+        "let",
+        "namespace",
+        "new",
+        "of",
+        "private",
+        "protected",
+        "public",
+        "readonly",
+        "return",
+        "satisfies",
+        "static",
+        "super",
+        "switch",
+        "this",
+        "throw",
+        "try",
+        "type",
+        "typeof",
+        "var",
+        "void",
+        "while",
+        "with",
+        "yield",
+    ],
+    types: &[
+        "any", "bigint", "boolean", "never", "number", "object", "string", "symbol", "unknown",
+    ],
+    ..JAVASCRIPT
+};
+
+pub struct JavaScript;
+
+impl Language for JavaScript {
+    fn name(&self) -> &str {
+        "javascript"
+    }
+
+    fn aliases(&self) -> &[&str] {
+        &["js", "jsx", "mjs", "cjs"]
+    }
+
+    fn highlight(&self, code: &str) -> Vec<Span> {
+        scan(&JAVASCRIPT, code)
+    }
+}
+
+pub struct TypeScript;
+
+impl Language for TypeScript {
+    fn name(&self) -> &str {
+        "typescript"
+    }
+
+    fn aliases(&self) -> &[&str] {
+        &["ts", "tsx", "mts", "cts"]
+    }
+
+    fn highlight(&self, code: &str) -> Vec<Span> {
+        scan(&TYPESCRIPT, code)
+    }
+}
+
+const RUST: Grammar = Grammar {
+    keywords: &[
+        "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+        "extern", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut",
+        "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait", "type",
+        "unsafe", "use", "where", "while",
+    ],
+    constants: &["true", "false", "None", "Some", "Ok", "Err"],
+    types: &[
+        "bool", "char", "str", "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32",
+        "i64", "i128", "isize", "f32", "f64",
+    ],
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    quotes: &['"', '\''],
+    word_comments: false,
+    lifetimes: true,
+    raw_strings: true,
+    variables: false,
+    keys: false,
+    capitalized_types: true,
+    identifier_chars: &[],
+};
+
+pub struct Rust;
+
+impl Language for Rust {
+    fn name(&self) -> &str {
+        "rust"
+    }
+
+    fn aliases(&self) -> &[&str] {
+        &["rs"]
+    }
+
+    fn highlight(&self, code: &str) -> Vec<Span> {
+        scan(&RUST, code)
+    }
+}
+
+const JSON: Grammar = Grammar {
+    keywords: &[],
+    constants: &["true", "false", "null"],
+    types: &[],
+    line_comments: &[],
+    block_comment: None,
+    quotes: &['"'],
+    word_comments: false,
+    lifetimes: false,
+    raw_strings: false,
+    variables: false,
+    keys: true,
+    capitalized_types: false,
+    identifier_chars: &[],
+};
+
+pub struct Json;
+
+impl Language for Json {
+    fn name(&self) -> &str {
+        "json"
+    }
+
+    fn highlight(&self, code: &str) -> Vec<Span> {
+        scan(&JSON, code)
+    }
+}
+
+const SHELL: Grammar = Grammar {
+    keywords: &[
+        "if", "then", "else", "elif", "fi", "for", "while", "until", "do", "done", "case", "esac",
+        "in", "function", "return", "local", "export", "select", "time",
+    ],
+    constants: &["true", "false"],
+    types: &[],
+    line_comments: &["#"],
+    block_comment: None,
+    quotes: &['"', '\''],
+    word_comments: true,
+    lifetimes: false,
+    raw_strings: false,
+    variables: true,
+    keys: false,
+    capitalized_types: false,
+    identifier_chars: &[],
+};
+
+pub struct Shell;
+
+impl Language for Shell {
+    fn name(&self) -> &str {
+        "shell"
+    }
+
+    fn aliases(&self) -> &[&str] {
+        &["sh", "bash", "zsh", "console"]
+    }
+
+    fn highlight(&self, code: &str) -> Vec<Span> {
+        scan(&SHELL, code)
+    }
+}
+
+/// Highlights markup itself.
+pub struct Markup;
+
+impl Language for Markup {
+    fn name(&self) -> &str {
+        "markup"
+    }
+
+    fn aliases(&self) -> &[&str] {
+        &["mu"]
+    }
+
+    fn highlight(&self, code: &str) -> Vec<Span> {
+        semantic::classify(code)
+            .into_iter()
+            .filter_map(|token| {
+                let class = match token.kind {
+                    TokenKind::TagName => Class::Tag,
+                    TokenKind::AttributeName => Class::Property,
+                    TokenKind::String => Class::String,
+                    TokenKind::Number => Class::Number,
+                    TokenKind::Boolean | TokenKind::EnumMember => Class::Constant,
+                    TokenKind::RangeOperator => Class::Operator,
+                    TokenKind::Punctuation => Class::Punctuation,
+                    TokenKind::Comment => Class::Comment,
+                    TokenKind::Invalid => return None,
+                };
+                Some(Span {
+                    class,
+                    range: token.range,
+                })
+            })
+            .collect()
+    }
+}
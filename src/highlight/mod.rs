@@ -0,0 +1,390 @@
+use crate::ast::{Literal, Node, Tag};
+use serde::Serialize;
+use std::{fmt::Write, ops::Range};
+
+mod languages;
+mod scanner;
+
+pub use languages::{JavaScript, Json, Markup, Rust, Shell, TypeScript};
+pub use scanner::{Grammar, Scanner};
+
+/// What a piece of highlighted code is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Class {
+    Keyword,
+    String,
+    Comment,
+    Number,
+    /// Built-in values like `true` or `null`.
+    Constant,
+    Type,
+    Function,
+    Variable,
+    /// Object keys in json and attribute names in markup.
+    Property,
+    /// Tag names in markup.
+    Tag,
+    Operator,
+    Punctuation,
+}
+
+impl Class {
+    /// The name used for this class in css classes and serialised spans.
+    pub fn name(self) -> &'static str {
+        match self {
+            Class::Keyword => "keyword",
+            Class::String => "string",
+            Class::Comment => "comment",
+            Class::Number => "number",
+            Class::Constant => "constant",
+            Class::Type => "type",
+            Class::Function => "function",
+            Class::Variable => "variable",
+            Class::Property => "property",
+            Class::Tag => "tag",
+            Class::Operator => "operator",
+            Class::Punctuation => "punctuation",
+        }
+    }
+
+    fn ansi(self) -> &'static str {
+        match self {
+            Class::Keyword => "\x1b[35m",
+            Class::String => "\x1b[32m",
+            Class::Comment => "\x1b[90m",
+            Class::Number | Class::Constant => "\x1b[33m",
+            Class::Type => "\x1b[36m",
+            Class::Function => "\x1b[34m",
+            Class::Variable | Class::Property => "\x1b[31m",
+            Class::Tag => "\x1b[1;34m",
+            Class::Operator | Class::Punctuation => "",
+        }
+    }
+}
+
+/// A classified byte range of the highlighted code. Code that isn't covered by a span is plain
+/// text.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Span {
+    pub class: Class,
+    pub range: Range<usize>,
+}
+
+/// A tokenizer for a language that can be registered in a [`Highlighter`].
+pub trait Language {
+    /// The canonical name of the language, e.g. `typescript`.
+    fn name(&self) -> &str;
+    /// Other values of the `lang` attribute that select this language, e.g. `ts`.
+    fn aliases(&self) -> &[&str] {
+        &[]
+    }
+    /// Classifies `code` into sorted, non-overlapping spans. This must not fail on invalid code.
+    fn highlight(&self, code: &str) -> Vec<Span>;
+}
+
+/// The registry of languages that `code-block`s can be highlighted with.
+pub struct Highlighter {
+    languages: Vec<Box<dyn Language>>,
+}
+
+impl Default for Highlighter {
+    fn default() -> Self {
+        let mut highlighter = Self::empty();
+        highlighter.register(TypeScript);
+        highlighter.register(JavaScript);
+        highlighter.register(Rust);
+        highlighter.register(Json);
+        highlighter.register(Shell);
+        highlighter.register(Markup);
+        highlighter
+    }
+}
+
+impl Highlighter {
+    /// A highlighter with the built-in languages.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A highlighter without any languages.
+    pub fn empty() -> Self {
+        Self { languages: vec![] }
+    }
+
+    /// Registers a language, languages registered later win over earlier ones with the same name
+    /// or alias so the built-in ones can be replaced.
+    pub fn register(&mut self, language: impl Language + 'static) {
+        self.languages.push(Box::new(language));
+    }
+
+    /// Finds a language by its name or one of its aliases, ignoring case.
+    pub fn language(&self, name: &str) -> Option<&dyn Language> {
+        self.languages
+            .iter()
+            .rev()
+            .find(|language| {
+                language.name().eq_ignore_ascii_case(name)
+                    || language
+                        .aliases()
+                        .iter()
+                        .any(|alias| alias.eq_ignore_ascii_case(name))
+            })
+            .map(|language| language.as_ref())
+    }
+
+    /// Highlights `code` as `lang`, returns `None` if the language isn't registered.
+    pub fn highlight(&self, lang: &str, code: &str) -> Option<Vec<Span>> {
+        self.language(lang).map(|language| language.highlight(code))
+    }
+
+    /// Highlights the string content of a `code-block` using its `lang` attribute, a string or an
+    /// identifier.
+    pub fn highlight_tag(&self, tag: &Tag) -> Option<(String, Vec<Span>)> {
+        let (Some(Literal::String(lang)) | Some(Literal::Identifier(lang))) = tag.attr("lang")
+        else {
+            return None;
+        };
+        let code: String = tag
+            .children
+            .iter()
+            .filter_map(|child| match child {
+                Node::String(s) => Some(s.as_ref()),
+                Node::Tag(_) => None,
+            })
+            .collect();
+        let spans = self.highlight(lang, &code)?;
+        Some((code, spans))
+    }
+}
+
+/// Renders highlighted code as html, each span becomes a `<span class="hl-{class}">`.
+pub fn to_html(code: &str, spans: &[Span]) -> String {
+    let mut out = String::new();
+    for (class, text) in pieces(code, spans) {
+        match class {
+            Some(class) => {
+                write!(out, "<span class=\"hl-{}\">", class.name())
+                    .expect("writing to a string can't fail");
+                escape_html(&mut out, text);
+                out.push_str("</span>");
+            }
+            None => escape_html(&mut out, text),
+        }
+    }
+    out
+}
+
+/// Renders highlighted code with ansi colors for terminals.
+pub fn to_ansi(code: &str, spans: &[Span]) -> String {
+    let mut out = String::new();
+    for (class, text) in pieces(code, spans) {
+        match class.map(Class::ansi) {
+            Some(color) if !color.is_empty() => {
+                out.push_str(color);
+                out.push_str(text);
+                out.push_str("\x1b[0m");
+            }
+            _ => out.push_str(text),
+        }
+    }
+    out
+}
+
+/// Splits `code` into the classified spans and the plain text between them.
+fn pieces<'c>(code: &'c str, spans: &[Span]) -> Vec<(Option<Class>, &'c str)> {
+    let mut pieces = vec![];
+    let mut position = 0;
+    for span in spans {
+        if span.range.start > position {
+            pieces.push((None, &code[position..span.range.start]));
+        }
+        pieces.push((Some(span.class), &code[span.range.clone()]));
+        position = span.range.end;
+    }
+    if position < code.len() {
+        pieces.push((None, &code[position..]));
+    }
+    pieces
+}
+
+pub(crate) fn escape_html(out: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '&' => out.push_str("&amp;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{to_html, Class, Highlighter, Language, Span};
+    use crate::{ast::Node, parser::Parser};
+    use pretty_assertions::assert_eq;
+
+    fn classes(lang: &str, code: &str) -> Vec<(Class, String)> {
+        Highlighter::new()
+            .highlight(lang, code)
+            .unwrap()
+            .into_iter()
+            .map(|span| (span.class, code[span.range].to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn typescript() {
+        assert_eq!(
+            classes("ts", "const a: number = add(1, \"x\"); // done"),
+            vec![
+                (Class::Keyword, "const".to_string()),
+                (Class::Operator, ":".to_string()),
+                (Class::Type, "number".to_string()),
+                (Class::Operator, "=".to_string()),
+                (Class::Function, "add".to_string()),
+                (Class::Punctuation, "(".to_string()),
+                (Class::Number, "1".to_string()),
+                (Class::Punctuation, ",".to_string()),
+                (Class::String, "\"x\"".to_string()),
+                (Class::Punctuation, ")".to_string()),
+                (Class::Punctuation, ";".to_string()),
+                (Class::Comment, "// done".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn rust_lifetimes_and_chars() {
+        assert_eq!(
+            classes("rs", "fn f(s: &'a str) -> char { 'x' }"),
+            vec![
+                (Class::Keyword, "fn".to_string()),
+                (Class::Function, "f".to_string()),
+                (Class::Punctuation, "(".to_string()),
+                (Class::Operator, ":".to_string()),
+                (Class::Operator, "&".to_string()),
+                (Class::Type, "'a".to_string()),
+                (Class::Type, "str".to_string()),
+                (Class::Punctuation, ")".to_string()),
+                (Class::Operator, "->".to_string()),
+                (Class::Type, "char".to_string()),
+                (Class::Punctuation, "{".to_string()),
+                (Class::String, "'x'".to_string()),
+                (Class::Punctuation, "}".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn json_keys() {
+        assert_eq!(
+            classes("json", r#"{"a": [1, true, "b"]}"#),
+            vec![
+                (Class::Punctuation, "{".to_string()),
+                (Class::Property, "\"a\"".to_string()),
+                (Class::Operator, ":".to_string()),
+                (Class::Punctuation, "[".to_string()),
+                (Class::Number, "1".to_string()),
+                (Class::Punctuation, ",".to_string()),
+                (Class::Constant, "true".to_string()),
+                (Class::Punctuation, ",".to_string()),
+                (Class::String, "\"b\"".to_string()),
+                (Class::Punctuation, "]".to_string()),
+                (Class::Punctuation, "}".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn shell() {
+        assert_eq!(
+            classes("sh", "if [ -f $FILE ]; then echo \"a#b\" # comment\nfi"),
+            vec![
+                (Class::Keyword, "if".to_string()),
+                (Class::Punctuation, "[".to_string()),
+                (Class::Operator, "-".to_string()),
+                (Class::Variable, "$FILE".to_string()),
+                (Class::Punctuation, "]".to_string()),
+                (Class::Punctuation, ";".to_string()),
+                (Class::Keyword, "then".to_string()),
+                (Class::String, "\"a#b\"".to_string()),
+                (Class::Comment, "# comment".to_string()),
+                (Class::Keyword, "fi".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn markup() {
+        assert_eq!(
+            classes("mu", "p(width: 1..3) { \"text\" }"),
+            vec![
+                (Class::Tag, "p".to_string()),
+                (Class::Punctuation, "(".to_string()),
+                (Class::Property, "width".to_string()),
+                (Class::Punctuation, ":".to_string()),
+                (Class::Number, "1".to_string()),
+                (Class::Operator, "..".to_string()),
+                (Class::Number, "3".to_string()),
+                (Class::Punctuation, ")".to_string()),
+                (Class::Punctuation, "{".to_string()),
+                (Class::String, "\"text\"".to_string()),
+                (Class::Punctuation, "}".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn broken_code_does_not_fail() {
+        for lang in ["ts", "rust", "json", "sh", "markup"] {
+            for code in ["\"unclosed", "/* unclosed", "'", "${", "a(: \"", "\u{e9}x"] {
+                Highlighter::new().highlight(lang, code).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn registered_languages_win() {
+        struct Upper;
+        impl Language for Upper {
+            fn name(&self) -> &str {
+                "upper"
+            }
+            fn aliases(&self) -> &[&str] {
+                &["ts"]
+            }
+            fn highlight(&self, code: &str) -> Vec<Span> {
+                vec![Span {
+                    class: Class::Keyword,
+                    range: 0..code.len(),
+                }]
+            }
+        }
+        let mut highlighter = Highlighter::new();
+        highlighter.register(Upper);
+        assert_eq!(highlighter.language("TS").unwrap().name(), "upper");
+        assert_eq!(
+            highlighter.language("typescript").unwrap().name(),
+            "typescript"
+        );
+        assert!(highlighter.language("cobol").is_none());
+    }
+
+    #[test]
+    fn code_block_to_html() {
+        let ast = Parser::new()
+            .parse(br#"code-block(lang: "js") { "a < b" }"#)
+            .unwrap();
+        let Node::Tag(tag) = &ast[0] else {
+            unreachable!()
+        };
+        let (code, spans) = Highlighter::new().highlight_tag(tag).unwrap();
+        assert_eq!(
+            to_html(&code, &spans),
+            r#"a <span class="hl-operator">&lt;</span> b"#
+        );
+    }
+}
@@ -0,0 +1,306 @@
+use super::{Class, Language, Span};
+
+/// The lexical rules of a language, which is enough to highlight most C-like languages.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Grammar {
+    pub keywords: &'static [&'static str],
+    /// Built-in values like `true`, highlighted as [`Class::Constant`].
+    pub constants: &'static [&'static str],
+    /// Built-in types, highlighted as [`Class::Type`].
+    pub types: &'static [&'static str],
+    pub line_comments: &'static [&'static str],
+    pub block_comment: Option<(&'static str, &'static str)>,
+    /// Characters that start and end a string, strings can contain `\` escapes.
+    pub quotes: &'static [char],
+    /// Line comments only start at the beginning of a word, as `#` in shell.
+    pub word_comments: bool,
+    /// `'a` is a lifetime unless it is a character literal like `'a'`.
+    pub lifetimes: bool,
+    /// `r"..."` and `r#"..."#` are raw strings.
+    pub raw_strings: bool,
+    /// `$name`, `${...}` and `$?` are variables.
+    pub variables: bool,
+    /// Strings followed by `:` are object keys.
+    pub keys: bool,
+    /// Identifiers starting with an uppercase letter are types.
+    pub capitalized_types: bool,
+    /// Characters other than alphanumerics and `_` allowed in identifiers.
+    pub identifier_chars: &'static [char],
+}
+
+/// A [`Language`] highlighted by [`Grammar`] rules, the easiest way to register a new language.
+///
+/// ```
+/// use markup::highlight::{Grammar, Highlighter, Scanner};
+///
+/// let mut highlighter = Highlighter::new();
+/// highlighter.register(Scanner::new(
+///     "lua",
+///     &["luau"],
+///     Grammar {
+///         keywords: &["local", "function", "end", "if", "then", "return"],
+///         constants: &["nil", "true", "false"],
+///         line_comments: &["--"],
+///         quotes: &['"', '\''],
+///         ..Grammar::default()
+///     },
+/// ));
+/// assert!(highlighter.highlight("luau", "local a = nil -- nothing").is_some());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Scanner {
+    name: String,
+    aliases: Vec<&'static str>,
+    grammar: Grammar,
+}
+
+impl Scanner {
+    pub fn new(name: &str, aliases: &[&'static str], grammar: Grammar) -> Self {
+        Self {
+            name: name.to_string(),
+            aliases: aliases.to_vec(),
+            grammar,
+        }
+    }
+}
+
+impl Language for Scanner {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn aliases(&self) -> &[&str] {
+        &self.aliases
+    }
+
+    fn highlight(&self, code: &str) -> Vec<Span> {
+        scan(&self.grammar, code)
+    }
+}
+
+const PUNCTUATION: &[char] = &['(', ')', '{', '}', '[', ']', ',', ';', '.'];
+const OPERATORS: &[char] = &[
+    '+', '-', '*', '/', '%', '=', '<', '>', '!', '&', '|', '^', '~', '?', ':', '@', '#',
+];
+
+/// Highlights `code` using the rules of `grammar`.
+pub fn scan(grammar: &Grammar, code: &str) -> Vec<Span> {
+    let mut scanner = State {
+        grammar,
+        code,
+        current: 0,
+        spans: vec![],
+    };
+    scanner.run();
+    scanner.spans
+}
+
+struct State<'g, 'c> {
+    grammar: &'g Grammar,
+    code: &'c str,
+    current: usize,
+    spans: Vec<Span>,
+}
+
+impl<'g, 'c> State<'g, 'c> {
+    fn run(&mut self) {
+        while let Some(c) = self.peek() {
+            let start = self.current;
+            if c.is_whitespace() {
+                self.advance();
+            } else if self.line_comment() {
+                while self.peek().is_some_and(|c| c != '\n') {
+                    self.advance();
+                }
+                self.push(Class::Comment, start);
+            } else if let Some((open, close)) = self
+                .grammar
+                .block_comment
+                .filter(|(open, _)| self.rest().starts_with(open))
+            {
+                self.current += open.len();
+                match self.rest().find(close) {
+                    Some(end) => self.current += end + close.len(),
+                    None => self.current = self.code.len(),
+                }
+                self.push(Class::Comment, start);
+            } else if self.grammar.quotes.contains(&c) && !(c == '\'' && self.grammar.lifetimes) {
+                self.string(c, start);
+            } else if c == '\'' && self.grammar.lifetimes {
+                self.lifetime_or_char(start);
+            } else if c == '$' && self.grammar.variables {
+                self.variable(start);
+            } else if c.is_ascii_digit() {
+                self.number(start);
+            } else if self.is_identifier_start(c) {
+                self.word(start);
+            } else if PUNCTUATION.contains(&c) {
+                self.advance();
+                self.push(Class::Punctuation, start);
+            } else if OPERATORS.contains(&c) {
+                while self.peek().is_some_and(|c| OPERATORS.contains(&c)) && !self.line_comment() {
+                    self.advance();
+                }
+                self.push(Class::Operator, start);
+            } else {
+                self.advance();
+            }
+        }
+    }
+
+    fn line_comment(&self) -> bool {
+        let at_word_start = || {
+            self.code[..self.current]
+                .chars()
+                .next_back()
+                .is_none_or(|c| c.is_whitespace() || c == ';')
+        };
+        self.grammar
+            .line_comments
+            .iter()
+            .any(|prefix| self.rest().starts_with(prefix))
+            && (!self.grammar.word_comments || at_word_start())
+    }
+
+    fn string(&mut self, quote: char, start: usize) {
+        self.advance();
+        while let Some(c) = self.advance() {
+            if c == '\\' {
+                self.advance();
+            } else if c == quote {
+                break;
+            }
+        }
+        let class = if self.grammar.keys && self.rest().trim_start().starts_with(':') {
+            Class::Property
+        } else {
+            Class::String
+        };
+        self.push(class, start);
+    }
+
+    fn raw_string(&mut self, start: usize) {
+        let hashes = self.rest().chars().take_while(|c| *c == '#').count();
+        self.current += hashes;
+        if self.peek() != Some('"') {
+            //Just an `r` followed by `#`s, not a raw string.
+            self.current = start + 1;
+            return;
+        }
+        self.advance();
+        let terminator = format!("\"{}", "#".repeat(hashes));
+        match self.rest().find(&terminator) {
+            Some(end) => self.current += end + terminator.len(),
+            None => self.current = self.code.len(),
+        }
+        self.push(Class::String, start);
+    }
+
+    fn lifetime_or_char(&mut self, start: usize) {
+        self.advance();
+        let mut chars = self.rest().chars();
+        match (chars.next(), chars.next()) {
+            (Some('\\'), _) | (Some(_), Some('\'')) => {
+                self.current = start;
+                self.string('\'', start);
+            }
+            (Some(c), _) if self.is_identifier_start(c) => {
+                while self.peek().is_some_and(|c| self.is_identifier_char(c)) {
+                    self.advance();
+                }
+                self.push(Class::Type, start);
+            }
+            _ => self.push(Class::Operator, start),
+        }
+    }
+
+    fn variable(&mut self, start: usize) {
+        self.advance();
+        match self.peek() {
+            Some('{') => {
+                while let Some(c) = self.advance() {
+                    if c == '}' {
+                        break;
+                    }
+                }
+            }
+            Some(c) if self.is_identifier_start(c) => {
+                while self.peek().is_some_and(|c| self.is_identifier_char(c)) {
+                    self.advance();
+                }
+            }
+            Some(c) if c.is_ascii_digit() || "#?@*!$-".contains(c) => {
+                self.advance();
+            }
+            _ => return self.push(Class::Operator, start),
+        }
+        self.push(Class::Variable, start);
+    }
+
+    fn number(&mut self, start: usize) {
+        while let Some(c) = self.peek() {
+            let next_is_digit = || self.rest()[1..].starts_with(|c: char| c.is_ascii_digit());
+            if c.is_alphanumeric() || c == '_' || (c == '.' && next_is_digit()) {
+                self.advance();
+            } else {
+                break;
+            }
+        }
+        self.push(Class::Number, start);
+    }
+
+    fn word(&mut self, start: usize) {
+        while self.peek().is_some_and(|c| self.is_identifier_char(c)) {
+            self.advance();
+        }
+        let word = &self.code[start..self.current];
+        if self.grammar.raw_strings && (word == "r" || word == "br") {
+            if let Some('"' | '#') = self.peek() {
+                return self.raw_string(start);
+            }
+        }
+        let class = if self.grammar.keywords.contains(&word) {
+            Class::Keyword
+        } else if self.grammar.constants.contains(&word) {
+            Class::Constant
+        } else if self.grammar.types.contains(&word)
+            || (self.grammar.capitalized_types && word.starts_with(char::is_uppercase))
+        {
+            Class::Type
+        } else if self.rest().starts_with('(') || self.rest().starts_with("!(") {
+            Class::Function
+        } else {
+            return;
+        };
+        self.push(class, start);
+    }
+
+    fn is_identifier_start(&self, c: char) -> bool {
+        c.is_alphabetic() || c == '_' || self.grammar.identifier_chars.contains(&c)
+    }
+
+    fn is_identifier_char(&self, c: char) -> bool {
+        self.is_identifier_start(c) || c.is_numeric()
+    }
+
+    fn push(&mut self, class: Class, start: usize) {
+        self.spans.push(Span {
+            class,
+            range: start..self.current,
+        });
+    }
+
+    fn rest(&self) -> &'c str {
+        &self.code[self.current..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.current += c.len_utf8();
+        Some(c)
+    }
+}
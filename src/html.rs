@@ -0,0 +1,228 @@
+use crate::{
+    ast::{Literal, Node, Tag},
+    highlight::{self, escape_html, Highlighter, Span},
+    range::LineSet,
+};
+use std::fmt::Write;
+
+/// Elements html doesn't close.
+const VOID: [&str; 13] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// Renders a document as html.
+///
+/// Tags become elements of the same name and their attributes become html attributes: strings,
+/// numbers, identifiers, ranges and maps are written out, a list is joined with spaces so
+/// `class: [a, b]` works, a flag or `true` is a bare attribute and `false` is left out. Strings
+/// are escaped as they are, run [`Whitespace`](crate::pass::Whitespace) first for the spaces to
+/// render the way they were meant.
+///
+/// Some tags are rendered specially:
+///
+/// | tag                                | html                                                |
+/// |------------------------------------|-----------------------------------------------------|
+/// | `code-block(lang: ts, highlights)` | `<pre><code class="language-ts">` with a `<span class="line">` per line, highlighted with the [`Highlighter`] and `highlighted` on the lines in `highlights` |
+/// | `ref(to: "id", path: "a.html")`    | `<a href="a.html#id">`, as resolved by [`CrossReferences`](crate::pass::CrossReferences) |
+/// | `toc`                              | `<nav class="toc">`                                 |
+/// | `footnotes`                        | `<section class="footnotes">`                       |
+/// | `meta`                             | nothing, it belongs in the `<head>`                 |
+#[derive(Default)]
+pub struct Html {
+    highlighter: Highlighter,
+}
+
+impl Html {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Highlights code blocks with `highlighter` instead of the built-in languages.
+    pub fn with_highlighter(highlighter: Highlighter) -> Self {
+        Self { highlighter }
+    }
+
+    pub fn render(&self, nodes: &[Node]) -> String {
+        let mut out = String::new();
+        for node in nodes {
+            self.node(&mut out, node);
+        }
+        out
+    }
+
+    fn node(&self, out: &mut String, node: &Node) {
+        match node {
+            Node::String(s) => escape_html(out, s),
+            Node::Tag(tag) => self.tag(out, tag),
+        }
+    }
+
+    fn tag(&self, out: &mut String, tag: &Tag) {
+        match tag.name.as_ref() {
+            "meta" => {}
+            "code-block" => self.code_block(out, tag),
+            "ref" => {
+                let href = match (tag.attr("path"), tag.attr("to")) {
+                    (Some(Literal::String(path)), Some(Literal::String(to))) => {
+                        format!("{path}#{to}")
+                    }
+                    (_, Some(Literal::String(to))) => format!("#{to}"),
+                    _ => String::new(),
+                };
+                out.push_str("<a href=\"");
+                escape_html(out, &href);
+                out.push_str("\">");
+                self.children(out, tag, "a");
+            }
+            "toc" => self.element(out, tag, "nav", Some("toc")),
+            "footnotes" => self.element(out, tag, "section", Some("footnotes")),
+            name => self.element(out, tag, name, None),
+        }
+    }
+
+    /// `tag` as the element `name`, with `class` added to the classes it already has.
+    fn element(&self, out: &mut String, tag: &Tag, name: &str, class: Option<&str>) {
+        write!(out, "<{name}").expect("writing to a string can't fail");
+        let mut class = class.map(str::to_string);
+        for attribute in &tag.attributes {
+            if let (Some(class), "class") = (&mut class, attribute.name()) {
+                class.push(' ');
+                class.push_str(&value(attribute.value()));
+                continue;
+            }
+            match attribute.value() {
+                Literal::Bool(false) => {}
+                Literal::Bool(true) | Literal::Flag => {
+                    write!(out, " {}", attribute.name()).expect("writing to a string can't fail")
+                }
+                literal => {
+                    write!(out, " {}=\"", attribute.name())
+                        .expect("writing to a string can't fail");
+                    escape_html(out, &value(literal));
+                    out.push('"');
+                }
+            }
+        }
+        if let Some(class) = class {
+            out.push_str(" class=\"");
+            escape_html(out, &class);
+            out.push('"');
+        }
+        out.push('>');
+        if !VOID.contains(&name) {
+            self.children(out, tag, name);
+        }
+    }
+
+    fn children(&self, out: &mut String, tag: &Tag, name: &str) {
+        for child in &tag.children {
+            self.node(out, child);
+        }
+        write!(out, "</{name}>").expect("writing to a string can't fail");
+    }
+
+    fn code_block(&self, out: &mut String, tag: &Tag) {
+        let (code, spans) = self.highlighter.highlight_tag(tag).unwrap_or_else(|| {
+            let code = tag
+                .children
+                .iter()
+                .filter_map(|child| match child {
+                    Node::String(s) => Some(s.as_ref()),
+                    Node::Tag(_) => None,
+                })
+                .collect();
+            (code, vec![])
+        });
+        let lines = code.split_inclusive('\n').count() as u32;
+        let highlights = tag
+            .attr("highlights")
+            .and_then(|literal| LineSet::resolve(literal, lines).ok())
+            .unwrap_or_default();
+        out.push_str("<pre><code");
+        if let Some(lang) = tag.attr("lang") {
+            out.push_str(" class=\"language-");
+            escape_html(out, &value(lang));
+            out.push('"');
+        }
+        out.push('>');
+        let mut start = 0;
+        for (i, line) in code.split_inclusive('\n').enumerate() {
+            let range = start..start + line.len();
+            start = range.end;
+            let line_spans: Vec<Span> = spans
+                .iter()
+                .filter(|span| span.range.start < range.end && span.range.end > range.start)
+                .map(|span| Span {
+                    class: span.class,
+                    range: span.range.start.max(range.start) - range.start
+                        ..span.range.end.min(range.end) - range.start,
+                })
+                .collect();
+            if highlights.contains(i as u32 + 1) {
+                out.push_str("<span class=\"line highlighted\">");
+            } else {
+                out.push_str("<span class=\"line\">");
+            }
+            out.push_str(&highlight::to_html(line, &line_spans));
+            out.push_str("</span>");
+        }
+        out.push_str("</code></pre>");
+    }
+}
+
+/// The text of an attribute value.
+fn value(literal: &Literal) -> String {
+    match literal {
+        Literal::String(s) | Literal::Identifier(s) => s.to_string(),
+        Literal::List(items) => items.iter().map(value).collect::<Vec<_>>().join(" "),
+        literal => literal.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Html;
+    use crate::parser::Parser;
+    use pretty_assertions::assert_eq;
+
+    fn render(source: &str) -> String {
+        let ast = Parser::new().parse(source.as_bytes()).unwrap();
+        Html::new().render(&ast)
+    }
+
+    #[test]
+    fn tags_and_attributes() {
+        assert_eq!(
+            render(
+                r#"meta(title: "x"); p(class: [a, b], hidden, draggable: false) { "a < b " em "c" br; }"#
+            ),
+            r#"<p class="a b" hidden>a &lt; b <em>c</em><br></p>"#
+        );
+        assert_eq!(
+            render(
+                r#"toc(class: "side") { ul {} } ref(to: "x", path: "a.html") "A" ref(to: "y");"#
+            ),
+            r##"<nav class="toc side"><ul></ul></nav><a href="a.html#x">A</a><a href="#y"></a>"##
+        );
+    }
+
+    #[test]
+    fn code_blocks() {
+        assert_eq!(
+            render(
+                r#"code-block(lang: json, highlights: [2]) { "{\n  \"a\": 1\n}" } code-block "<x>""#
+            ),
+            concat!(
+                r#"<pre><code class="language-json">"#,
+                r#"<span class="line"><span class="hl-punctuation">{</span>"#,
+                "\n</span>",
+                r#"<span class="line highlighted">  <span class="hl-property">&quot;a&quot;</span><span class="hl-operator">:</span> <span class="hl-number">1</span>"#,
+                "\n</span>",
+                r#"<span class="line"><span class="hl-punctuation">}</span></span>"#,
+                "</code></pre>",
+                r#"<pre><code><span class="line">&lt;x&gt;</span></code></pre>"#
+            )
+        );
+    }
+}
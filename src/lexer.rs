@@ -1,10 +1,13 @@
-use std::fmt::Display;
+use std::{borrow::Cow, fmt::Display, ops::Range};
 use thiserror::Error;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Token<'a> {
     Identifier(&'a str),
+    /// The content of a string as written, escapes are processed by [`unescape`].
     String(&'a str),
+    /// The content of a `r#"..."#` string, taken verbatim.
+    RawString(&'a str),
     Number(u32),
     LeftParen,
     RightParen,
@@ -13,9 +16,11 @@ pub enum Token<'a> {
     LeftBrace,
     RightBrace,
     DoubleDot,
+    DoubleDotEqual,
     Dot,
     Comma,
     Colon,
+    Semicolon,
     EOF,
 }
 
@@ -23,7 +28,7 @@ impl<'a> Display for Token<'a> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Token::Identifier(i) => write!(f, "{i}"),
-            Token::String(s) => write!(f, "{s}"),
+            Token::String(s) | Token::RawString(s) => write!(f, "{s}"),
             Token::Number(n) => write!(f, "{n}"),
             Token::LeftParen => write!(f, "("),
             Token::RightParen => write!(f, ")"),
@@ -32,9 +37,11 @@ impl<'a> Display for Token<'a> {
             Token::LeftBrace => write!(f, "`{{`"),
             Token::RightBrace => write!(f, "`}}`"),
             Token::DoubleDot => write!(f, ".."),
+            Token::DoubleDotEqual => write!(f, "..="),
             Token::Dot => write!(f, "."),
             Token::Comma => write!(f, ","),
             Token::Colon => write!(f, ":"),
+            Token::Semicolon => write!(f, ";"),
             Token::EOF => write!(f, "\0"),
         }
     }
@@ -46,11 +53,77 @@ pub enum LexingError {
     UnrecognizedCharacter { character: char, position: usize },
     #[error("Unclosed string literal at start: {} end: {}", .start, .end)]
     UnclosedStringLiteral { start: usize, end: usize },
+    #[error("Unclosed raw string literal at start: {} end: {}, it must end with `\"{}`", .start, .end, "#".repeat(*.hashes))]
+    UnclosedRawStringLiteral {
+        start: usize,
+        end: usize,
+        hashes: usize,
+    },
+    #[error("Unknown escape `\\{}` at position {}, strings can only escape `\\`, `\"`, `n`, `r` and `t`", .character, .position)]
+    UnknownEscape { character: char, position: usize },
+    #[error("Number at position {} is too large, numbers can be at most {}", .position, u32::MAX)]
+    NumberTooLarge { position: usize },
+    #[error("Invalid UTF-8 at position {}", .position)]
+    InvalidUtf8 { position: usize },
+}
+
+impl LexingError {
+    /// The byte offset in the source where the error starts.
+    pub fn position(&self) -> usize {
+        match self {
+            LexingError::UnrecognizedCharacter { position, .. }
+            | LexingError::UnknownEscape { position, .. }
+            | LexingError::NumberTooLarge { position }
+            | LexingError::InvalidUtf8 { position } => *position,
+            LexingError::UnclosedStringLiteral { start, .. }
+            | LexingError::UnclosedRawStringLiteral { start, .. } => *start,
+        }
+    }
+}
+
+/// Processes the escapes of a string the lexer accepted, borrowing it if it has none.
+pub fn unescape(s: &str) -> Cow<'_, str> {
+    if !s.contains('\\') {
+        return Cow::Borrowed(s);
+    }
+    let mut unescaped = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some('t') => unescaped.push('\t'),
+            Some(c) => unescaped.push(c),
+            None => {}
+        }
+    }
+    Cow::Owned(unescaped)
+}
+
+/// Writes a string so the lexer reads it back the same: as a raw string with as few `#` as
+/// needed if it contains a `"` or a `\\`, as it is otherwise.
+pub struct Quoted<'a>(pub &'a str);
+
+impl Display for Quoted<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.0.contains(['"', '\\']) {
+            return write!(f, "\"{}\"", self.0);
+        }
+        let mut hashes = String::new();
+        while self.0.contains(&format!("\"{hashes}")) {
+            hashes.push('#');
+        }
+        write!(f, "r{hashes}\"{}\"{hashes}", self.0)
+    }
 }
 
 type LexingResult<'source> = Result<Token<'source>, LexingError>;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Lexer<'source> {
     source: &'source [u8],
     current: usize,
@@ -62,21 +135,43 @@ impl<'source> Lexer<'source> {
     }
 
     pub fn scan_tokens(&mut self) -> Result<Vec<Token<'source>>, LexingError> {
+        Ok(self
+            .scan_spanned_tokens()?
+            .into_iter()
+            .map(|(token, _)| token)
+            .collect())
+    }
+
+    /// Scans the tokens together with the byte range each of them covers in the source.
+    pub fn scan_spanned_tokens(
+        &mut self,
+    ) -> Result<Vec<(Token<'source>, Range<usize>)>, LexingError> {
         let mut tokens = vec![];
-        while !self.is_at_end() {
-            self.skip_whitespace();
-            let token = self.scan_token()?;
-            tokens.push(token);
+        loop {
+            let (token, span) = self.next_spanned_token()?;
+            tokens.push((token, span));
+            if token == Token::EOF {
+                return Ok(tokens);
+            }
         }
-        if tokens.last() != Some(&Token::EOF) {
-            //if there is no whitespace after the last scanned token than we never call the scan token again so
-            //we don't call the `scan_token` and get `Token::EOF`
-            tokens.push(Token::EOF);
+    }
+
+    /// Scans the next token and the byte range it covers, [`Token::EOF`] once the source is done.
+    pub fn next_spanned_token(&mut self) -> Result<(Token<'source>, Range<usize>), LexingError> {
+        self.skip_whitespace();
+        let start = self.current;
+        if self.is_at_end() {
+            return Ok((Token::EOF, start..start));
         }
-        Ok(tokens)
+        let token = self.scan_token()?;
+        Ok((token, start..self.current))
     }
 
     fn scan_token(&mut self) -> LexingResult<'source> {
+        let start = self.current;
+        if !self.is_at_end() && self.char_at(start).is_none() {
+            return Err(LexingError::InvalidUtf8 { position: start });
+        }
         let c = self.advance();
         match c {
             '(' => Ok(Token::LeftParen),
@@ -88,19 +183,25 @@ impl<'source> Lexer<'source> {
             ',' => Ok(Token::Comma),
             '.' => {
                 if self.match_char('.') {
-                    Ok(Token::DoubleDot)
+                    if self.match_char('=') {
+                        Ok(Token::DoubleDotEqual)
+                    } else {
+                        Ok(Token::DoubleDot)
+                    }
                 } else {
                     Ok(Token::Dot)
                 }
             }
             ':' => Ok(Token::Colon),
+            ';' => Ok(Token::Semicolon),
             '\0' => Ok(Token::EOF),
             '"' => Ok(self.string()?),
-            _ if c.is_numeric() => Ok(self.number()?),
-            _ if c.is_alphabetic() => Ok(self.identifier()?),
+            'r' if matches!(self.peek(), '"' | '#') => Ok(self.raw_string()?),
+            _ if c.is_ascii_digit() => Ok(self.number(start)?),
+            _ if c.is_alphabetic() => Ok(self.identifier(start)?),
             _ => Err(LexingError::UnrecognizedCharacter {
                 character: c,
-                position: self.current,
+                position: start,
             }),
         }
     }
@@ -108,7 +209,20 @@ impl<'source> Lexer<'source> {
     fn string(&mut self) -> LexingResult<'source> {
         let start = self.current;
         while !self.is_at_end() && self.peek() != '"' {
-            self.advance();
+            let escape = self.current;
+            if self.advance() == '\\' {
+                match self.advance() {
+                    '\\' | '"' | 'n' | 'r' | 't' => {}
+                    //Reported as unclosed below.
+                    _ if self.is_at_end() => {}
+                    character => {
+                        return Err(LexingError::UnknownEscape {
+                            character,
+                            position: escape,
+                        })
+                    }
+                }
+            }
         }
         if self.is_at_end() {
             return Err(LexingError::UnclosedStringLiteral {
@@ -116,50 +230,104 @@ impl<'source> Lexer<'source> {
                 end: self.current,
             });
         }
-        let inner = std::str::from_utf8(&self.source[start..self.current]).expect("should be utf8");
+        let inner = self.lexeme(start..self.current)?;
         //eat closing quote
         self.advance();
         Ok(Token::String(inner))
     }
 
-    fn identifier(&mut self) -> LexingResult<'source> {
+    /// `r"..."`, `r#"..."#` and so on, the string ends at a `"` followed by as many `#` as it
+    /// started with.
+    fn raw_string(&mut self) -> LexingResult<'source> {
         let start = self.current - 1;
+        let mut hashes = 0;
+        while self.match_char('#') {
+            hashes += 1;
+        }
+        if !self.match_char('"') {
+            return Err(LexingError::UnrecognizedCharacter {
+                character: '#',
+                position: self.current - 1,
+            });
+        }
+        let terminator = format!("\"{}", "#".repeat(hashes));
+        let content_start = self.current;
+        let Some(length) = self.source[content_start..]
+            .windows(terminator.len())
+            .position(|window| window == terminator.as_bytes())
+        else {
+            self.current = self.source.len();
+            return Err(LexingError::UnclosedRawStringLiteral {
+                start,
+                end: self.current,
+                hashes,
+            });
+        };
+        self.current = content_start + length + terminator.len();
+        let inner = self.lexeme(content_start..content_start + length)?;
+        Ok(Token::RawString(inner))
+    }
+
+    fn identifier(&mut self, start: usize) -> LexingResult<'source> {
         while is_alphabetic(self.peek()) {
             self.advance();
         }
-        let lexeme =
-            std::str::from_utf8(&self.source[start..self.current]).expect("should be utf8");
-        Ok(Token::Identifier(lexeme))
+        Ok(Token::Identifier(self.lexeme(start..self.current)?))
     }
 
-    fn number(&mut self) -> LexingResult<'source> {
-        let start = self.current - 1;
-        while self.peek().is_numeric() {
+    fn number(&mut self, start: usize) -> LexingResult<'source> {
+        while self.peek().is_ascii_digit() {
             self.advance();
         }
-        let lexeme =
-            std::str::from_utf8(&self.source[start..self.current]).expect("Should be utf8");
-        let num: u32 = lexeme
+        //Only ascii digits were read so the number can only fail to parse by overflowing.
+        let num: u32 = self
+            .lexeme(start..self.current)?
             .parse()
-            .unwrap_or_else(|_| panic!("'{lexeme}' should be valid number"));
+            .map_err(|_| LexingError::NumberTooLarge { position: start })?;
         Ok(Token::Number(num))
     }
 
+    fn lexeme(&self, range: Range<usize>) -> Result<&'source str, LexingError> {
+        let start = range.start;
+        std::str::from_utf8(&self.source[range]).map_err(|error| LexingError::InvalidUtf8 {
+            position: start + error.valid_up_to(),
+        })
+    }
+
+    /// The char starting at the byte `at` and its length in bytes, `None` if the source isn't
+    /// valid UTF-8 there.
+    fn char_at(&self, at: usize) -> Option<(char, usize)> {
+        let width = match self.source[at] {
+            0x00..=0x7F => 1,
+            0xC0..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            0xF0..=0xF7 => 4,
+            _ => return None,
+        };
+        let bytes = self.source.get(at..at + width)?;
+        let c = std::str::from_utf8(bytes).ok()?.chars().next()?;
+        Some((c, width))
+    }
+
+    /// The next char, `\0` at the end and `U+FFFD` for a byte that isn't valid UTF-8.
     fn peek(&self) -> char {
         if self.is_at_end() {
             '\0'
         } else {
-            self.source[self.current].into()
+            self.char_at(self.current)
+                .map_or(char::REPLACEMENT_CHARACTER, |(c, _)| c)
         }
     }
 
     fn advance(&mut self) -> char {
         if self.is_at_end() {
-            '\0'
-        } else {
-            self.current += 1;
-            self.source[self.current - 1].into()
+            return '\0';
         }
+        let (c, width) = self
+            .char_at(self.current)
+            .unwrap_or((char::REPLACEMENT_CHARACTER, 1));
+        self.current += width;
+        c
     }
 
     fn is_at_end(&self) -> bool {
@@ -170,14 +338,23 @@ impl<'source> Lexer<'source> {
         if self.is_at_end() || self.peek() != c {
             false
         } else {
-            self.current += 1;
+            self.advance();
             true
         }
     }
 
+    /// Skips whitespace and `//` comments, which run until the end of the line.
     fn skip_whitespace(&mut self) {
-        while !self.is_at_end() && self.peek().is_whitespace() {
-            self.advance();
+        while !self.is_at_end() {
+            if self.peek().is_whitespace() {
+                self.advance();
+            } else if self.source[self.current..].starts_with(b"//") {
+                while !self.is_at_end() && self.peek() != '\n' {
+                    self.advance();
+                }
+            } else {
+                break;
+            }
         }
     }
 }
@@ -190,8 +367,9 @@ fn is_alphabetic(c: char) -> bool {
 mod tests {
     use std::vec;
 
-    use super::{Lexer, LexingError, Token};
+    use super::{unescape, Lexer, LexingError, Quoted, Token};
     use pretty_assertions::assert_eq;
+    use std::borrow::Cow;
 
     #[test]
     fn number_literal() {
@@ -252,9 +430,167 @@ string literal""#;
         );
     }
 
+    #[test]
+    fn non_ascii() {
+        let source = "p { } café { \"naïve\" } 😀";
+        let tokens = Lexer::new(source.as_bytes()).scan_tokens();
+        assert_eq!(
+            tokens,
+            Err(LexingError::UnrecognizedCharacter {
+                character: '😀',
+                position: 25
+            })
+        );
+        let tokens = Lexer::new("café { \"naïve\" }".as_bytes()).scan_tokens();
+        assert_eq!(
+            tokens,
+            Ok(vec![
+                Token::Identifier("café"),
+                Token::LeftBrace,
+                Token::String("naïve"),
+                Token::RightBrace,
+                Token::EOF
+            ])
+        );
+        assert_eq!(
+            Lexer::new(b"p \"a\xff\"").scan_tokens(),
+            Err(LexingError::InvalidUtf8 { position: 4 })
+        );
+        assert_eq!(
+            Lexer::new(b"p { \xe9 }").scan_tokens(),
+            Err(LexingError::InvalidUtf8 { position: 4 })
+        );
+    }
+
+    #[test]
+    fn number_too_large() {
+        let source = "p(a: 4294967295, b: 99999999999) {}";
+        let tokens = Lexer::new(source.as_bytes()).scan_tokens();
+        assert_eq!(tokens, Err(LexingError::NumberTooLarge { position: 20 }));
+        assert_eq!(
+            tokens.unwrap_err().to_string(),
+            "Number at position 20 is too large, numbers can be at most 4294967295"
+        );
+    }
+
+    #[test]
+    fn unrecognized_character() {
+        let source = "p { % }";
+        let mut lexer = Lexer::new(source.as_bytes());
+        let tokens = lexer.scan_tokens();
+        assert_eq!(
+            tokens,
+            Err(LexingError::UnrecognizedCharacter {
+                character: '%',
+                position: 4
+            })
+        )
+    }
+
+    #[test]
+    fn escapes() {
+        let source = r#""say \"hi\"\n\\""#;
+        let tokens = Lexer::new(source.as_bytes()).scan_tokens().unwrap();
+        assert_eq!(tokens, vec![Token::String(r#"say \"hi\"\n\\"#), Token::EOF]);
+        assert_eq!(unescape(r#"say \"hi\"\n\\"#), "say \"hi\"\n\\");
+        assert_eq!(unescape(r"\\*not em\\*"), r"\*not em\*");
+        assert!(matches!(unescape("no escapes"), Cow::Borrowed(_)));
+        assert_eq!(
+            Lexer::new(br#""a \d""#).scan_tokens(),
+            Err(LexingError::UnknownEscape {
+                character: 'd',
+                position: 3
+            })
+        );
+        assert_eq!(
+            Lexer::new(br#""a \""#).scan_tokens(),
+            Err(LexingError::UnclosedStringLiteral { start: 0, end: 5 })
+        );
+    }
+
+    #[test]
+    fn raw_strings() {
+        let source = r###"r"C:\path" r#"{"a": "b"}"# r##"a "# b"## r "x""###;
+        let tokens = Lexer::new(source.as_bytes()).scan_tokens().unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::RawString(r"C:\path"),
+                Token::RawString(r#"{"a": "b"}"#),
+                Token::RawString(r##"a "# b"##),
+                Token::Identifier("r"),
+                Token::String("x"),
+                Token::EOF
+            ]
+        );
+        let error = Lexer::new(br##"p { r#"a "b" }"##)
+            .scan_tokens()
+            .unwrap_err();
+        assert_eq!(
+            error,
+            LexingError::UnclosedRawStringLiteral {
+                start: 4,
+                end: 14,
+                hashes: 1
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "Unclosed raw string literal at start: 4 end: 14, it must end with `\"#`"
+        );
+    }
+
+    #[test]
+    fn quoting() {
+        for s in ["plain", "C:\\path", r#"{"a": "b"}"#, r##"a "# b"##] {
+            let quoted = Quoted(s).to_string();
+            let tokens = Lexer::new(quoted.as_bytes()).scan_tokens().unwrap();
+            let value = match tokens[0] {
+                Token::String(s) => unescape(s),
+                Token::RawString(s) => s.into(),
+                _ => unreachable!(),
+            };
+            assert_eq!(value, s);
+        }
+        assert_eq!(Quoted(r#"say "hi""#).to_string(), r##"r#"say "hi""#"##);
+    }
+
+    #[test]
+    fn spans() {
+        let source = r#"p(a: 1..2) { "x" }"#;
+        let mut lexer = Lexer::new(source.as_bytes());
+        let spans: Vec<_> = lexer
+            .scan_spanned_tokens()
+            .unwrap()
+            .into_iter()
+            .map(|(_, span)| &source[span])
+            .collect();
+        assert_eq!(
+            spans,
+            vec!["p", "(", "a", ":", "1", "..", "2", ")", "{", "\"x\"", "}", ""]
+        )
+    }
+
+    #[test]
+    fn comments() {
+        let source = "// heading\nh1 { \"a // b\" } // trailing";
+        let mut lexer = Lexer::new(source.as_bytes());
+        let tokens = lexer.scan_tokens();
+        assert_eq!(
+            tokens,
+            Ok(vec![
+                Token::Identifier("h1"),
+                Token::LeftBrace,
+                Token::String("a // b"),
+                Token::RightBrace,
+                Token::EOF
+            ])
+        )
+    }
+
     #[test]
     fn punctuation() {
-        let source = " ( ){}[ ] , ..: ";
+        let source = " ( ){}[ ] , ..: ..=";
         let mut lexer = Lexer::new(source.as_bytes());
         let tokens = lexer.scan_tokens();
         assert_eq!(
@@ -269,6 +605,7 @@ string literal""#;
                 Token::Comma,
                 Token::DoubleDot,
                 Token::Colon,
+                Token::DoubleDotEqual,
                 Token::EOF
             ])
         )
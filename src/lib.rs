@@ -1,3 +1,21 @@
+pub mod arena;
 pub mod ast;
+pub mod attributes;
+pub mod de;
+pub mod highlight;
+pub mod html;
 pub mod lexer;
+pub mod lsp;
 pub mod parser;
+pub mod pass;
+pub mod printer;
+pub mod project;
+pub mod range;
+pub mod selector;
+pub mod semantic;
+pub mod ser;
+pub mod serve;
+pub mod site;
+
+pub use de::{from_attributes, from_str};
+pub use ser::to_string;
@@ -0,0 +1,65 @@
+use serde_json::{json, Value};
+
+/// Converts byte offsets to the line and utf-16 column positions the protocol uses.
+#[derive(Debug, Clone)]
+pub struct LineIndex<'a> {
+    source: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(source: &'a str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self {
+            source,
+            line_starts,
+        }
+    }
+
+    /// The zero based line and utf-16 column of `offset`.
+    pub fn position(&self, offset: usize) -> (usize, usize) {
+        let offset = offset.min(self.source.len());
+        let line = self.line(offset);
+        let start = self.line_starts[line];
+        let column = self.source[start..offset]
+            .chars()
+            .map(char::len_utf16)
+            .sum();
+        (line, column)
+    }
+
+    pub fn line(&self, offset: usize) -> usize {
+        match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next) => next - 1,
+        }
+    }
+
+    /// A protocol `Range` object for the byte range `start..end`.
+    pub fn range(&self, start: usize, end: usize) -> Value {
+        let (start_line, start_column) = self.position(start);
+        let (end_line, end_column) = self.position(end);
+        json!({
+            "start": { "line": start_line, "character": start_column },
+            "end": { "line": end_line, "character": end_column },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LineIndex;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn positions() {
+        let source = "p {\n  \"h\u{e9}llo\u{1F600}x\"\n}";
+        let index = LineIndex::new(source);
+        assert_eq!(index.position(0), (0, 0));
+        assert_eq!(index.position(4), (1, 0));
+        assert_eq!(index.position(source.find('x').unwrap()), (1, 10));
+        assert_eq!(index.position(source.len()), (2, 1));
+    }
+}
@@ -0,0 +1,446 @@
+use crate::{
+    lexer::LexingError,
+    parser::{Parser, ParsingError},
+    printer,
+    semantic::{classify, has_comments, TokenKind},
+};
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Write},
+};
+
+mod line_index;
+mod outline;
+
+pub use line_index::LineIndex;
+pub use outline::{outline, Outline, Symbol};
+
+const SYMBOL_KIND_OBJECT: u32 = 19;
+const SEVERITY_ERROR: u32 = 1;
+const METHOD_NOT_FOUND: i32 = -32601;
+const INVALID_REQUEST: i32 = -32600;
+/// The legend of the semantic tokens, indexed by [`semantic_token_type`].
+const SEMANTIC_TOKEN_TYPES: [&str; 8] = [
+    "class",
+    "property",
+    "string",
+    "number",
+    "operator",
+    "comment",
+    "keyword",
+    "enumMember",
+];
+
+/// A language server for markup documents.
+///
+/// The server only keeps the text of the open documents and parses them on demand, it answers
+/// with parse diagnostics, document symbols, folding ranges and whole-document formatting.
+#[derive(Debug, Default)]
+pub struct Server {
+    documents: HashMap<String, String>,
+    shutdown: bool,
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handles a single message from the client and returns the messages to send back.
+    pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+        let Some(id) = message.get("id").cloned() else {
+            return self.notification(method, params);
+        };
+        if self.shutdown {
+            return vec![error(id, INVALID_REQUEST, "the server is shutting down")];
+        }
+        let result = match method {
+            "initialize" => json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "documentSymbolProvider": true,
+                    "foldingRangeProvider": true,
+                    "documentFormattingProvider": true,
+                    "semanticTokensProvider": {
+                        "legend": { "tokenTypes": SEMANTIC_TOKEN_TYPES, "tokenModifiers": [] },
+                        "full": true,
+                    },
+                },
+                "serverInfo": { "name": "markup-lsp", "version": env!("CARGO_PKG_VERSION") },
+            }),
+            "shutdown" => {
+                self.shutdown = true;
+                Value::Null
+            }
+            "textDocument/documentSymbol" => self.with_document(params, document_symbols),
+            "textDocument/foldingRange" => self.with_document(params, folding_ranges),
+            "textDocument/formatting" => self.with_document(params, formatting),
+            "textDocument/semanticTokens/full" => self.with_document(params, semantic_tokens),
+            _ => {
+                return vec![error(
+                    id,
+                    METHOD_NOT_FOUND,
+                    &format!("unsupported method `{method}`"),
+                )]
+            }
+        };
+        vec![json!({ "jsonrpc": "2.0", "id": id, "result": result })]
+    }
+
+    fn notification(&mut self, method: &str, params: &Value) -> Vec<Value> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let text = match method {
+            "textDocument/didOpen" => params["textDocument"]["text"].as_str(),
+            //The server asks for full document sync, so the last change is the whole document.
+            "textDocument/didChange" => params["contentChanges"]
+                .as_array()
+                .and_then(|changes| changes.last())
+                .and_then(|change| change["text"].as_str()),
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+                return vec![publish_diagnostics(uri, vec![])];
+            }
+            _ => return vec![],
+        };
+        let Some(text) = text else {
+            return vec![];
+        };
+        self.documents.insert(uri.to_string(), text.to_string());
+        vec![publish_diagnostics(uri, diagnostics(text))]
+    }
+
+    fn with_document(&self, params: &Value, f: fn(&str) -> Value) -> Value {
+        params["textDocument"]["uri"]
+            .as_str()
+            .and_then(|uri| self.documents.get(uri))
+            .map_or(Value::Null, |text| f(text))
+    }
+}
+
+/// Serves the protocol over `input` and `output` until the client sends `exit`.
+pub fn run(mut input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+    let mut server = Server::new();
+    while let Some(message) = read_message(&mut input)? {
+        if message["method"] == "exit" {
+            break;
+        }
+        for response in server.handle(&message) {
+            write_message(&mut output, &response)?;
+        }
+    }
+    Ok(())
+}
+
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let Some(length) = length else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "missing Content-Length header",
+        ));
+    };
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+fn write_message(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    output.flush()
+}
+
+fn error(id: Value, code: i32, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: Vec<Value>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": { "uri": uri, "diagnostics": diagnostics },
+    })
+}
+
+fn diagnostics(text: &str) -> Vec<Value> {
+    let Err(err) = Parser::new().parse(text.as_bytes()) else {
+        return vec![];
+    };
+    let range = match err {
+        ParsingError::UnclosedStringLiteral { start, end }
+        | ParsingError::LexingError(LexingError::UnclosedStringLiteral { start, end }) => {
+            start..end
+        }
+        _ => {
+            //Underline the word the error points at.
+            let start = err.position().min(text.len());
+            let length = text[start..]
+                .find(char::is_whitespace)
+                .unwrap_or(text.len() - start);
+            start..start + length.max(text[start..].chars().next().map_or(0, char::len_utf8))
+        }
+    };
+    vec![json!({
+        "range": LineIndex::new(text).range(range.start, range.end),
+        "severity": SEVERITY_ERROR,
+        "source": "markup",
+        "message": err.to_string(),
+    })]
+}
+
+fn document_symbols(text: &str) -> Value {
+    fn convert(index: &LineIndex, symbol: &Symbol) -> Value {
+        let mut value = json!({
+            "name": symbol.name,
+            "kind": SYMBOL_KIND_OBJECT,
+            "range": index.range(symbol.range.start, symbol.range.end),
+            "selectionRange": index.range(symbol.selection.start, symbol.selection.end),
+            "children": symbol.children.iter().map(|child| convert(index, child)).collect::<Vec<_>>(),
+        });
+        if let Some(detail) = &symbol.detail {
+            value["detail"] = json!(detail);
+        }
+        value
+    }
+    let index = LineIndex::new(text);
+    outline(text)
+        .symbols
+        .iter()
+        .map(|symbol| convert(&index, symbol))
+        .collect()
+}
+
+fn folding_ranges(text: &str) -> Value {
+    let index = LineIndex::new(text);
+    outline(text)
+        .blocks
+        .iter()
+        .filter_map(|block| {
+            //Keep the line of the closing brace visible.
+            let start = index.line(block.start);
+            let end = index.line(block.end - 1).checked_sub(1)?;
+            (end > start).then(|| json!({ "startLine": start, "endLine": end }))
+        })
+        .collect()
+}
+
+fn formatting(text: &str) -> Value {
+    let Ok(ast) = Parser::new().parse(text.as_bytes()) else {
+        return Value::Null;
+    };
+    //Printing the ast would remove the comments.
+    if has_comments(text) {
+        return Value::Null;
+    }
+    let formatted = printer::print(&ast);
+    if formatted == text {
+        return json!([]);
+    }
+    json!([{
+        "range": LineIndex::new(text).range(0, text.len()),
+        "newText": formatted,
+    }])
+}
+
+fn semantic_token_type(kind: TokenKind) -> Option<usize> {
+    match kind {
+        TokenKind::TagName => Some(0),
+        TokenKind::AttributeName => Some(1),
+        TokenKind::String => Some(2),
+        TokenKind::Number => Some(3),
+        TokenKind::RangeOperator => Some(4),
+        TokenKind::Comment => Some(5),
+        TokenKind::Boolean => Some(6),
+        TokenKind::EnumMember => Some(7),
+        TokenKind::Punctuation | TokenKind::Invalid => None,
+    }
+}
+
+fn semantic_tokens(text: &str) -> Value {
+    let index = LineIndex::new(text);
+    let mut data = vec![];
+    let (mut previous_line, mut previous_column) = (0, 0);
+    for token in classify(text) {
+        let Some(token_type) = semantic_token_type(token.kind) else {
+            continue;
+        };
+        //Clients don't have to support tokens spanning several lines, so strings are split.
+        let mut start = token.range.start;
+        for piece in text[token.range.clone()].split_inclusive('\n') {
+            let (line, column) = index.position(start);
+            let length: usize = piece
+                .trim_end_matches('\n')
+                .chars()
+                .map(char::len_utf16)
+                .sum();
+            if length > 0 {
+                let delta_column = if line == previous_line {
+                    column - previous_column
+                } else {
+                    column
+                };
+                data.extend([line - previous_line, delta_column, length, token_type, 0]);
+                (previous_line, previous_column) = (line, column);
+            }
+            start += piece.len();
+        }
+    }
+    json!({ "data": data })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{run, Server};
+    use pretty_assertions::assert_eq;
+    use serde_json::{json, Value};
+
+    fn open(server: &mut Server, text: &str) -> Vec<Value> {
+        server.handle(&json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": { "textDocument": { "uri": "file:///a.mu", "languageId": "markup", "version": 1, "text": text } },
+        }))
+    }
+
+    fn request(server: &mut Server, method: &str) -> Value {
+        let mut responses = server.handle(&json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": { "textDocument": { "uri": "file:///a.mu" } },
+        }));
+        assert_eq!(responses.len(), 1);
+        responses.remove(0)["result"].take()
+    }
+
+    #[test]
+    fn diagnostics() {
+        let mut server = Server::new();
+        let messages = open(&mut server, "p {\n  div(a: 1 { }\n}");
+        assert_eq!(
+            messages[0]["params"]["diagnostics"][0]["range"],
+            json!({ "start": { "line": 1, "character": 11 }, "end": { "line": 1, "character": 12 } })
+        );
+        let messages = open(&mut server, "p { \"ok\" }");
+        assert_eq!(messages[0]["params"]["diagnostics"], json!([]));
+    }
+
+    #[test]
+    fn non_ascii_and_large_numbers() {
+        let mut server = Server::new();
+        let messages = open(&mut server, "p { } café {}");
+        assert_eq!(messages[0]["params"]["diagnostics"], json!([]));
+        assert_eq!(
+            request(&mut server, "textDocument/documentSymbol")[1]["name"],
+            "café"
+        );
+        request(&mut server, "textDocument/semanticTokens/full");
+        let messages = open(&mut server, "p(a: 99999999999) {}");
+        let diagnostic = &messages[0]["params"]["diagnostics"][0];
+        assert_eq!(
+            diagnostic["message"],
+            "Lexing error: Number at position 5 is too large, numbers can be at most 4294967295"
+        );
+        assert_eq!(
+            diagnostic["range"],
+            json!({ "start": { "line": 0, "character": 5 }, "end": { "line": 0, "character": 17 } })
+        );
+    }
+
+    #[test]
+    fn symbols_and_folding() {
+        let mut server = Server::new();
+        open(
+            &mut server,
+            "section {\n  h1(id: \"a\") { \"x\" }\n  p {\n    \"y\"\n  }\n}\n",
+        );
+        let symbols = request(&mut server, "textDocument/documentSymbol");
+        assert_eq!(symbols[0]["name"], "section");
+        assert_eq!(symbols[0]["children"][0]["name"], "h1");
+        assert_eq!(symbols[0]["children"][0]["detail"], "id: \"a\"");
+        assert_eq!(symbols[0]["children"][1]["name"], "p");
+        assert_eq!(
+            request(&mut server, "textDocument/foldingRange"),
+            json!([{ "startLine": 0, "endLine": 4 }, { "startLine": 2, "endLine": 3 }])
+        );
+    }
+
+    #[test]
+    fn formatting() {
+        let mut server = Server::new();
+        open(&mut server, "p{\"x\"}");
+        assert_eq!(
+            request(&mut server, "textDocument/formatting"),
+            json!([{
+                "range": { "start": { "line": 0, "character": 0 }, "end": { "line": 0, "character": 6 } },
+                "newText": "p { \"x\" }\n",
+            }])
+        );
+        open(&mut server, "p{");
+        assert_eq!(request(&mut server, "textDocument/formatting"), Value::Null);
+    }
+
+    #[test]
+    fn semantic_tokens() {
+        let mut server = Server::new();
+        open(&mut server, "p(a: 1) {\n  \"x\ny\"\n}");
+        assert_eq!(
+            request(&mut server, "textDocument/semanticTokens/full"),
+            json!({ "data": [
+                0, 0, 1, 0, 0,
+                0, 2, 1, 1, 0,
+                0, 3, 1, 3, 0,
+                1, 2, 2, 2, 0,
+                1, 0, 2, 2, 0,
+            ] })
+        );
+    }
+
+    #[test]
+    fn comments_are_not_formatted_away() {
+        let mut server = Server::new();
+        open(&mut server, "p{\"x\"} // note");
+        assert_eq!(request(&mut server, "textDocument/formatting"), Value::Null);
+    }
+
+    #[test]
+    fn stdio_transport() {
+        let messages = [
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} }),
+            json!({ "jsonrpc": "2.0", "id": 2, "method": "shutdown" }),
+            json!({ "jsonrpc": "2.0", "method": "exit" }),
+        ];
+        let input: String = messages
+            .iter()
+            .map(|message| {
+                let body = message.to_string();
+                format!("Content-Length: {}\r\n\r\n{body}", body.len())
+            })
+            .collect();
+        let mut output = vec![];
+        run(input.as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output.matches("Content-Length").count(), 2);
+        assert!(output.contains("\"documentFormattingProvider\":true"));
+    }
+}
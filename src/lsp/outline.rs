@@ -0,0 +1,166 @@
+use crate::semantic::{classify, SemanticToken, TokenKind};
+use std::ops::Range;
+
+/// A tag in the outline of a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    /// The attributes as they were written, without the parentheses.
+    pub detail: Option<String>,
+    /// From the tag name up to its closing brace.
+    pub range: Range<usize>,
+    /// The tag name.
+    pub selection: Range<usize>,
+    pub children: Vec<Symbol>,
+}
+
+/// The tags and `{ }` blocks of a document, recovered from its [`classify`]d tokens so an outline
+/// is still available while the document doesn't parse or even lex.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Outline {
+    pub symbols: Vec<Symbol>,
+    /// From each opening brace to the end of its closing brace.
+    pub blocks: Vec<Range<usize>>,
+}
+
+pub fn outline(source: &str) -> Outline {
+    let tokens: Vec<SemanticToken> = classify(source)
+        .into_iter()
+        .filter(|token| token.kind != TokenKind::Comment)
+        .collect();
+    let punctuation = |i: usize| {
+        tokens
+            .get(i)
+            .filter(|token| token.kind == TokenKind::Punctuation)
+            .map(|token| &source[token.range.clone()])
+    };
+    let is_string = |i: usize| {
+        tokens
+            .get(i)
+            .is_some_and(|token| token.kind == TokenKind::String)
+    };
+    let mut outline = Outline::default();
+    //Tags whose body is still open together with where their body starts.
+    let mut open: Vec<(Symbol, usize)> = vec![];
+    let mut i = 0;
+    while i < tokens.len() {
+        let span = tokens[i].range.clone();
+        if tokens[i].kind == TokenKind::TagName
+            && (matches!(punctuation(i + 1), Some("(" | "{" | ";")) || is_string(i + 1))
+        {
+            let mut symbol = Symbol {
+                name: source[span.clone()].to_string(),
+                detail: None,
+                range: span.clone(),
+                selection: span,
+                children: vec![],
+            };
+            i += 1;
+            if punctuation(i) == Some("(") {
+                let attributes_start = tokens[i].range.end;
+                let close = (i..tokens.len()).find(|&j| punctuation(j) == Some(")"));
+                //An unclosed attribute list runs until the end of the document.
+                let end = close.map_or(source.len()..source.len(), |j| tokens[j].range.clone());
+                symbol.detail = Some(source[attributes_start..end.start].trim().to_string());
+                symbol.range.end = end.end;
+                i = close.map_or(tokens.len(), |j| j + 1);
+            }
+            match punctuation(i) {
+                Some("{") => {
+                    open.push((symbol, tokens[i].range.start));
+                    i += 1;
+                }
+                //`hr;` and `p "text"` end with their last token.
+                Some(";") => {
+                    symbol.range.end = tokens[i].range.end;
+                    attach(&mut outline.symbols, &mut open, symbol);
+                    i += 1;
+                }
+                _ if is_string(i) => {
+                    symbol.range.end = tokens[i].range.end;
+                    attach(&mut outline.symbols, &mut open, symbol);
+                    i += 1;
+                }
+                _ => attach(&mut outline.symbols, &mut open, symbol),
+            }
+            continue;
+        }
+        if punctuation(i) == Some("}") {
+            if let Some((mut symbol, start)) = open.pop() {
+                symbol.range.end = span.end;
+                outline.blocks.push(start..span.end);
+                attach(&mut outline.symbols, &mut open, symbol);
+            }
+        }
+        i += 1;
+    }
+    //Unclosed tags extend to the end of the document.
+    while let Some((mut symbol, _)) = open.pop() {
+        symbol.range.end = source.len();
+        attach(&mut outline.symbols, &mut open, symbol);
+    }
+    outline.blocks.sort_by_key(|block| block.start);
+    outline
+}
+
+fn attach(roots: &mut Vec<Symbol>, open: &mut [(Symbol, usize)], symbol: Symbol) {
+    match open.last_mut() {
+        Some((parent, _)) => parent.children.push(symbol),
+        None => roots.push(symbol),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::outline;
+    use pretty_assertions::assert_eq;
+
+    fn names(symbols: &[super::Symbol]) -> Vec<String> {
+        symbols
+            .iter()
+            .map(|symbol| match symbol.children.as_slice() {
+                [] => symbol.name.clone(),
+                children => format!("{}[{}]", symbol.name, names(children).join(" ")),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn nested_tags() {
+        let source = r#"section(id: "a") { h1 { "x" } p { b { "y" } } } hr {}"#;
+        let outline = outline(source);
+        assert_eq!(names(&outline.symbols), vec!["section[h1 p[b]]", "hr"]);
+        assert_eq!(outline.symbols[0].detail.as_deref(), Some(r#"id: "a""#));
+        assert_eq!(outline.symbols[0].range, 0..source.find(" hr").unwrap());
+        assert_eq!(outline.blocks.len(), 5);
+    }
+
+    #[test]
+    fn bodyless_tags() {
+        let source = r#"section { hr; p "x" image(src: "a.png"); }"#;
+        let outline = outline(source);
+        assert_eq!(names(&outline.symbols), vec!["section[hr p image]"]);
+        let children = &outline.symbols[0].children;
+        assert_eq!(&source[children[1].range.clone()], r#"p "x""#);
+        assert_eq!(
+            &source[children[2].range.clone()],
+            r#"image(src: "a.png");"#
+        );
+        assert_eq!(outline.blocks.len(), 1);
+    }
+
+    #[test]
+    fn broken_documents() {
+        let source = "section { p { \"x\" } div(a: 1";
+        let outline = outline(source);
+        assert_eq!(names(&outline.symbols), vec!["section[p div]"]);
+        assert_eq!(outline.symbols[0].range, 0..source.len());
+
+        //An unclosed string runs until the end, what comes before it is still there.
+        let source = "// notes\nsection { p { \"x\" } div { % } p \"unclosed }";
+        let unclosed = super::outline(source);
+        assert_eq!(names(&unclosed.symbols), vec!["section[p div p]"]);
+        assert_eq!(unclosed.blocks.len(), 2);
+        assert_eq!(unclosed.symbols[0].range, 9..source.len());
+    }
+}
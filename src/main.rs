@@ -1,3 +1,46 @@
-fn main() {
-    println!("hello, world!");
+use std::process::ExitCode;
+
+mod commands;
+
+const USAGE: &str = "\
+Usage: markup <COMMAND> [OPTIONS]
+
+Commands:
+    build <SOURCE> <OUTPUT>    Render a directory of documents as a static html site
+    check <FILE>...            Report broken references and duplicate ids across files
+    query <SELECTOR> [FILE]    Print the tags matching SELECTOR
+    serve <SOURCE> [OUTPUT]    Preview a site on localhost, reloading pages when files change
+    toc [FILE]                 Print the outline of a document's headings as json
+    transform [FILE]           Run transformation passes over a document
+
+Run `markup <COMMAND> --help` for the options of a command.";
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    let result = match args.next().as_deref() {
+        Some("build") => commands::build::run(args.collect()),
+        Some("check") => commands::check::run(args.collect()),
+        Some("query") => commands::query::run(args.collect()),
+        Some("serve") => commands::serve::run(args.collect()),
+        Some("toc") => commands::toc::run(args.collect()),
+        Some("transform") => commands::transform::run(args.collect()),
+        Some("-h" | "--help") => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Some(command) => Err(commands::Error::Usage(format!(
+            "unknown command `{command}`\n\n{USAGE}"
+        ))),
+        None => Err(commands::Error::Usage(USAGE.to_string())),
+    };
+    match result {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("error: {err}");
+            match err {
+                commands::Error::Usage(_) => ExitCode::from(2),
+                _ => ExitCode::FAILURE,
+            }
+        }
+    }
 }
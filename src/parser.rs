@@ -1,7 +1,8 @@
 use crate::{
-    ast::{Attribute, Literal, Node, Tag},
-    lexer::{Lexer, LexingError, Token},
+    ast::{Attribute, Body, Document, Literal, Node, Tag},
+    lexer::{unescape, Lexer, LexingError, Token},
 };
+use std::{borrow::Cow, ops::Range};
 use thiserror::Error;
 
 #[derive(Debug, Error, PartialEq, Eq, Clone, Copy)]
@@ -18,14 +19,38 @@ pub enum ParsingError<'a> {
         expected: Token<'a>,
         got: Token<'a>,
     },
-    #[error("Lexing error")]
+    #[error("Reversed range {}..{} at {}, the start of a range can't be after its end", .start, .end, .at)]
+    ReversedRange { at: usize, start: u32, end: u32 },
+    #[error("Range step at {} must be greater than zero", .at)]
+    ZeroStep { at: usize },
+    #[error("Duplicate key `{}` at {}", .key, .at)]
+    DuplicateKey { at: usize, key: &'a str },
+    #[error("Lexing error: {0}")]
     LexingError(#[from] LexingError),
 }
 
+impl<'a> ParsingError<'a> {
+    /// The byte offset in the source where the error starts.
+    pub fn position(&self) -> usize {
+        match self {
+            ParsingError::UnrecognizedCharacter { position, .. } => *position,
+            ParsingError::UnclosedStringLiteral { start, .. } => *start,
+            ParsingError::LexingError(error) => error.position(),
+            ParsingError::UnexpectedToken { at }
+            | ParsingError::ExpectedToken { at, .. }
+            | ParsingError::ReversedRange { at, .. }
+            | ParsingError::ZeroStep { at }
+            | ParsingError::DuplicateKey { at, .. } => *at,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Parser<'source> {
-    tokens: Vec<Token<'source>>,
-    current: usize,
+    lexer: Lexer<'source>,
+    /// The current token and the next one with the byte ranges they cover, the rest of the source
+    /// is scanned as the parser gets to it.
+    lookahead: [(Token<'source>, Range<usize>); 2],
 }
 
 impl<'a> Default for Parser<'a> {
@@ -37,14 +62,13 @@ impl<'a> Default for Parser<'a> {
 impl<'a> Parser<'a> {
     pub fn new() -> Self {
         Self {
-            tokens: vec![],
-            current: 0,
+            lexer: Lexer::new(&[]),
+            lookahead: [(Token::EOF, 0..0), (Token::EOF, 0..0)],
         }
     }
 
     pub fn parse(&mut self, source: &'a [u8]) -> Result<Vec<Node<'a>>, ParsingError<'a>> {
-        let mut lexer = Lexer::new(source);
-        self.tokens = lexer.scan_tokens()?;
+        self.start(source)?;
         let mut nodes: Vec<Node<'a>> = vec![];
         while !self.is_at_end() {
             nodes.push(self.node()?);
@@ -52,37 +76,85 @@ impl<'a> Parser<'a> {
         Ok(nodes)
     }
 
+    /// Parses a whole document, a `meta` tag at its start becomes [`Document::meta`] and any
+    /// other `meta` tag is an ordinary node.
+    pub fn parse_document(&mut self, source: &'a [u8]) -> Result<Document<'a>, ParsingError<'a>> {
+        let mut nodes = self.parse(source)?;
+        let meta = match nodes.first() {
+            Some(Node::Tag(tag)) if tag.name == "meta" => match nodes.remove(0) {
+                Node::Tag(tag) => Some(tag),
+                Node::String(_) => unreachable!("matched a tag"),
+            },
+            _ => None,
+        };
+        Ok(Document { meta, nodes })
+    }
+
+    /// Starts over at the first token of `source`.
+    pub(crate) fn start(&mut self, source: &'a [u8]) -> Result<(), ParsingError<'a>> {
+        self.lexer = Lexer::new(source);
+        self.lookahead[0] = self.lexer.next_spanned_token()?;
+        self.lookahead[1] = self.lexer.next_spanned_token()?;
+        Ok(())
+    }
+
     fn node(&mut self) -> Result<Node<'a>, ParsingError<'a>> {
-        match self.tokens[self.current] {
-            Token::String(s) => {
-                self.current += 1;
-                Ok(Node::String(s))
+        let mut node = self.open_node()?;
+        if let Node::Tag(tag) = &mut node {
+            if tag.body == Body::Block {
+                while !self.close_block()? {
+                    tag.children.push(self.node()?);
+                }
             }
-            Token::Identifier(_) => self.tag(),
-            _ => Err(ParsingError::UnexpectedToken { at: self.current }),
         }
+        Ok(node)
     }
 
-    fn tag(&mut self) -> Result<Node<'a>, ParsingError<'a>> {
-        let Token::Identifier(name) = self.tokens[self.current] else {
-            return Err(ParsingError::UnexpectedToken { at: self.current });
-        };
-        self.current += 1;
-        let mut node = Tag {
-            name,
-            attributes: vec![],
-            children: vec![],
+    /// Parses a string or a tag without the children of its block, which come next until
+    /// [`close_block`](Self::close_block) is true. A string body is the only child of its tag.
+    pub(crate) fn open_node(&mut self) -> Result<Node<'a>, ParsingError<'a>> {
+        match self.token() {
+            Token::String(_) | Token::RawString(_) => Ok(Node::String(self.string()?)),
+            Token::Identifier(_) => self.open_tag(),
+            _ => Err(ParsingError::UnexpectedToken {
+                at: self.position(),
+            }),
+        }
+    }
+
+    /// Consumes the `}` ending a block if it's the current token.
+    pub(crate) fn close_block(&mut self) -> Result<bool, ParsingError<'a>> {
+        let closes = self.token() == Token::RightBrace;
+        if closes {
+            self.advance()?;
+        }
+        Ok(closes)
+    }
+
+    fn open_tag(&mut self) -> Result<Node<'a>, ParsingError<'a>> {
+        let Token::Identifier(name) = self.token() else {
+            return Err(ParsingError::UnexpectedToken {
+                at: self.position(),
+            });
         };
+        self.advance()?;
+        let mut node = Tag::new(name);
 
-        if let Token::LeftParen = self.tokens[self.current] {
+        if let Token::LeftParen = self.token() {
             node.attributes = self.attributes()?;
         }
 
-        self.consume(Token::LeftBrace)?;
-        while self.tokens[self.current] != Token::RightBrace {
-            node.children.push(self.node()?);
+        match self.token() {
+            Token::Semicolon => {
+                self.advance()?;
+                node.body = Body::None;
+            }
+            Token::String(_) | Token::RawString(_) => {
+                node.children.push(Node::String(self.string()?));
+                node.body = Body::String;
+            }
+            _ => self.consume(Token::LeftBrace)?,
         }
-        self.consume(Token::RightBrace)?;
         Ok(Node::Tag(node))
     }
 
@@ -91,11 +163,11 @@ impl<'a> Parser<'a> {
         let mut attrs: Vec<Attribute<'a>> = vec![];
         //This is similar to parsing lists except we call `self.attribute()` instead of `self.literal()`
         //Maybe i can extract the logic of surrounded and delimited grammars.
-        if let Ok(attr) = self.attribute() {
-            attrs.push(attr);
+        if self.token() != Token::RightParen {
+            attrs.push(self.attribute()?);
         }
-        while self.tokens[self.current] == Token::Comma {
-            self.current += 1;
+        while self.token() == Token::Comma {
+            self.advance()?;
             attrs.push(self.attribute()?);
         }
 
@@ -104,39 +176,24 @@ impl<'a> Parser<'a> {
     }
 
     fn attribute(&mut self) -> Result<Attribute<'a>, ParsingError<'a>> {
-        let Token::Identifier(name) = self.tokens[self.current] else {
-            return Err(ParsingError::UnexpectedToken { at: self.current });
+        let Token::Identifier(name) = self.token() else {
+            return Err(ParsingError::UnexpectedToken {
+                at: self.position(),
+            });
         };
-        self.current += 1;
-        self.consume(Token::Colon)?;
-        match self.tokens[self.current] {
-            Token::Number(n) => {
-                if self.peek_next() == Token::DoubleDot {
-                    Ok(Attribute {
-                        name,
-                        value: self.range()?,
-                    })
-                } else {
-                    self.current += 1;
-                    Ok(Attribute {
-                        name,
-                        value: Literal::Number(n),
-                    })
-                }
-            }
-            Token::String(s) => {
-                self.current += 1;
-                Ok(Attribute {
-                    name,
-                    value: Literal::String(s),
-                })
-            }
-            Token::LeftBracket => Ok(Attribute {
-                name,
-                value: self.list()?,
-            }),
-            _ => Err(ParsingError::UnexpectedToken { at: self.current }),
+        self.advance()?;
+        //An attribute without a value is a flag like `details(open) {}`.
+        if matches!(self.token(), Token::Comma | Token::RightParen) {
+            return Ok(Attribute {
+                name: name.into(),
+                value: Literal::Flag,
+            });
         }
+        self.consume(Token::Colon)?;
+        Ok(Attribute {
+            name: name.into(),
+            value: self.literal()?,
+        })
     }
 
     fn list(&mut self) -> Result<Literal<'a>, ParsingError<'a>> {
@@ -145,11 +202,11 @@ impl<'a> Parser<'a> {
         //Because the non-terminal is `list ::= '[' (literal (',' literal))? ']'` like this
         //we first take the first literal and as long as we have a ',' we parse the others in
         //the while loop.
-        if let Ok(l) = self.literal() {
-            items.push(l);
+        if self.token() != Token::RightBracket {
+            items.push(self.literal()?);
         }
-        while self.tokens[self.current] == Token::Comma {
-            self.current += 1;
+        while self.token() == Token::Comma {
+            self.advance()?;
             items.push(self.literal()?);
         }
 
@@ -157,78 +214,179 @@ impl<'a> Parser<'a> {
         Ok(Literal::List(items))
     }
 
+    fn map(&mut self) -> Result<Literal<'a>, ParsingError<'a>> {
+        let mut entries: Vec<(Cow<'a, str>, Literal<'a>)> = vec![];
+        self.consume(Token::LeftBrace)?;
+        //Same as lists and attributes, `map ::= '{' (entry (',' entry)*)? '}'`.
+        if self.token() != Token::RightBrace {
+            entries.push(self.entry(&entries)?);
+        }
+        while self.token() == Token::Comma {
+            self.advance()?;
+            entries.push(self.entry(&entries)?);
+        }
+
+        self.consume(Token::RightBrace)?;
+        Ok(Literal::Map(entries))
+    }
+
+    fn entry(
+        &mut self,
+        entries: &[(Cow<'a, str>, Literal<'a>)],
+    ) -> Result<(Cow<'a, str>, Literal<'a>), ParsingError<'a>> {
+        let at = self.position();
+        let Token::Identifier(key) = self.token() else {
+            return Err(ParsingError::UnexpectedToken { at });
+        };
+        if entries.iter().any(|(other, _)| other == key) {
+            return Err(ParsingError::DuplicateKey { at, key });
+        }
+        self.advance()?;
+        self.consume(Token::Colon)?;
+        Ok((key.into(), self.literal()?))
+    }
+
     fn range(&mut self) -> Result<Literal<'a>, ParsingError<'a>> {
-        let Token::Number(start) = self.tokens[self.current] else {
-            return Err(ParsingError::UnexpectedToken { at: self.current });
+        let at = self.position();
+        let Token::Number(start) = self.token() else {
+            return Err(ParsingError::UnexpectedToken { at });
         };
-        self.current += 1;
-        self.consume(Token::DoubleDot)?;
-        if let Token::Number(end) = self.tokens[self.current] {
-            self.current += 1;
-            Ok(Literal::Range {
-                start,
-                end: Some(end),
-            })
+        self.advance()?;
+        let inclusive = if self.token() == Token::DoubleDotEqual {
+            self.advance()?;
+            true
         } else {
-            Ok(Literal::Range { start, end: None })
+            self.consume(Token::DoubleDot)?;
+            false
+        };
+        let end = match self.token() {
+            Token::Number(end) => {
+                self.advance()?;
+                Some(end)
+            }
+            //`start..=` would be an inclusive range without an end.
+            _ if inclusive => {
+                return Err(ParsingError::UnexpectedToken {
+                    at: self.position(),
+                })
+            }
+            _ => None,
+        };
+        if let Some(end) = end.filter(|end| start > *end) {
+            return Err(ParsingError::ReversedRange { at, start, end });
         }
+        let step = if self.token() == Token::Identifier("by") {
+            self.advance()?;
+            let Token::Number(step) = self.token() else {
+                return Err(ParsingError::UnexpectedToken {
+                    at: self.position(),
+                });
+            };
+            if step == 0 {
+                return Err(ParsingError::ZeroStep {
+                    at: self.position(),
+                });
+            }
+            self.advance()?;
+            Some(step)
+        } else {
+            None
+        };
+        Ok(Literal::Range {
+            start,
+            end,
+            inclusive,
+            step,
+        })
     }
 
     fn literal(&mut self) -> Result<Literal<'a>, ParsingError<'a>> {
-        match self.tokens[self.current] {
+        match self.token() {
             Token::Number(n) => {
-                if self.peek_next() == Token::DoubleDot {
+                if matches!(self.peek_next(), Token::DoubleDot | Token::DoubleDotEqual) {
                     Ok(self.range()?)
                 } else {
-                    self.current += 1;
+                    self.advance()?;
                     Ok(Literal::Number(n))
                 }
             }
-            Token::String(s) => {
-                self.current += 1;
-                Ok(Literal::String(s))
-            }
+            Token::String(_) | Token::RawString(_) => Ok(Literal::String(self.string()?)),
             Token::LeftBracket => Ok(self.list()?),
-            _ => Err(ParsingError::UnexpectedToken { at: self.current }),
+            Token::LeftBrace => Ok(self.map()?),
+            Token::Identifier(name) => {
+                self.advance()?;
+                Ok(match name {
+                    "true" => Literal::Bool(true),
+                    "false" => Literal::Bool(false),
+                    name => Literal::Identifier(name.into()),
+                })
+            }
+            _ => Err(ParsingError::UnexpectedToken {
+                at: self.position(),
+            }),
         }
     }
 
-    fn is_at_end(&self) -> bool {
-        self.tokens[self.current] == Token::EOF
+    /// Consumes the current string token, escapes are only processed outside raw strings.
+    fn string(&mut self) -> Result<Cow<'a, str>, ParsingError<'a>> {
+        let value = match self.token() {
+            Token::String(s) => unescape(s),
+            Token::RawString(s) => Cow::Borrowed(s),
+            _ => unreachable!("only called on string tokens"),
+        };
+        self.advance()?;
+        Ok(value)
+    }
+
+    pub(crate) fn is_at_end(&self) -> bool {
+        self.token() == Token::EOF
     }
     fn consume(&mut self, token: Token<'a>) -> Result<(), ParsingError<'a>> {
-        if self.tokens[self.current] == token {
-            self.current += 1;
+        if self.token() == token {
+            self.advance()?;
             Ok(())
         } else {
             Err(ParsingError::ExpectedToken {
-                at: self.current,
+                at: self.position(),
                 expected: token,
-                got: self.tokens[self.current],
+                got: self.token(),
             })
         }
     }
 
+    fn token(&self) -> Token<'a> {
+        self.lookahead[0].0
+    }
+
+    fn advance(&mut self) -> Result<(), ParsingError<'a>> {
+        self.lookahead.swap(0, 1);
+        self.lookahead[1] = self.lexer.next_spanned_token()?;
+        Ok(())
+    }
+
+    /// The byte offset of the current token, errors are reported with it.
+    fn position(&self) -> usize {
+        self.lookahead[0].1.start
+    }
+
     fn peek_next(&self) -> Token<'a> {
-        self.tokens[self.current + 1]
+        self.lookahead[1].0
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::{
-        ast::{Attribute, Literal, Node, Tag},
-        lexer::Lexer,
+        ast::{Attribute, Body, Literal, Node, Tag},
+        lexer::Token,
     };
     use pretty_assertions::assert_eq;
 
-    use super::Parser;
+    use super::{Parser, ParsingError};
 
-    fn init_parser(source: &str) -> Parser {
-        let mut lexer = Lexer::new(source.as_bytes());
-        let tokens = lexer.scan_tokens().unwrap();
+    fn init_parser(source: &str) -> Parser<'_> {
         let mut parser = Parser::new();
-        parser.tokens = tokens;
+        parser.start(source.as_bytes()).unwrap();
         parser
     }
 
@@ -241,7 +399,9 @@ mod tests {
             ast,
             Ok(Literal::Range {
                 start: 1,
-                end: Some(10)
+                end: Some(10),
+                inclusive: false,
+                step: None
             })
         )
     }
@@ -255,11 +415,51 @@ mod tests {
             ast,
             Ok(Literal::Range {
                 start: 1,
-                end: None
+                end: None,
+                inclusive: false,
+                step: None
             })
         )
     }
 
+    #[test]
+    fn inclusive_range_with_step() {
+        let source = "1..=10 by 3";
+        let mut parser = init_parser(source);
+        let ast = parser.range();
+        assert_eq!(
+            ast,
+            Ok(Literal::Range {
+                start: 1,
+                end: Some(10),
+                inclusive: true,
+                step: Some(3)
+            })
+        );
+        assert_eq!(ast.unwrap().to_string(), source);
+    }
+
+    #[test]
+    fn invalid_ranges() {
+        let mut parser = Parser::new();
+        assert_eq!(
+            parser.parse(b"p(a: 5..3) {}"),
+            Err(ParsingError::ReversedRange {
+                at: 5,
+                start: 5,
+                end: 3
+            })
+        );
+        assert_eq!(
+            parser.parse(b"p(a: 1.. by 0) {}"),
+            Err(ParsingError::ZeroStep { at: 12 })
+        );
+        assert_eq!(
+            parser.parse(b"p(a: 1..=) {}"),
+            Err(ParsingError::UnexpectedToken { at: 9 })
+        );
+    }
+
     #[test]
     fn list() {
         let source = r#"[1, 1..3, "string"]"#;
@@ -271,9 +471,11 @@ mod tests {
                 Literal::Number(1),
                 Literal::Range {
                     start: 1,
-                    end: Some(3)
+                    end: Some(3),
+                    inclusive: false,
+                    step: None
                 },
-                Literal::String("string")
+                Literal::String("string".into())
             ],))
         )
     }
@@ -289,13 +491,17 @@ mod tests {
                 Literal::Number(1),
                 Literal::Range {
                     start: 1,
-                    end: Some(3)
+                    end: Some(3),
+                    inclusive: false,
+                    step: None
                 },
                 Literal::List(vec![
                     Literal::Number(1),
                     Literal::Range {
                         start: 1,
-                        end: Some(3)
+                        end: Some(3),
+                        inclusive: false,
+                        step: None
                     },
                 ])
             ],))
@@ -310,12 +516,14 @@ mod tests {
         assert_eq!(
             ast,
             Ok(Attribute {
-                name: "name",
+                name: "name".into(),
                 value: Literal::List(vec![
                     Literal::Number(1),
                     Literal::Range {
                         start: 1,
-                        end: Some(3)
+                        end: Some(3),
+                        inclusive: false,
+                        step: None
                     }
                 ])
             })
@@ -331,27 +539,80 @@ mod tests {
             ast,
             Ok(vec![
                 Attribute {
-                    name: "name",
+                    name: "name".into(),
                     value: Literal::List(vec![
                         Literal::Number(1),
                         Literal::Range {
                             start: 1,
-                            end: Some(3)
+                            end: Some(3),
+                            inclusive: false,
+                            step: None
                         }
                     ])
                 },
                 Attribute {
-                    name: "num",
+                    name: "num".into(),
                     value: Literal::Number(123)
                 }
             ])
         )
     }
 
+    #[test]
+    fn flags_booleans_and_identifiers() {
+        let source = "(open, lang: ts, hidden: false, langs: [rs, true], last)";
+        let mut parser = init_parser(source);
+        assert_eq!(
+            parser.attributes(),
+            Ok(vec![
+                Attribute::new("open", Literal::Flag),
+                Attribute::new("lang", Literal::Identifier("ts".into())),
+                Attribute::new("hidden", false),
+                Attribute::new(
+                    "langs",
+                    Literal::List(vec![Literal::Identifier("rs".into()), Literal::Bool(true)])
+                ),
+                Attribute::new("last", Literal::Flag),
+            ])
+        );
+    }
+
+    #[test]
+    fn map() {
+        let source = "(size: {w: 640, h: 480}, langs: [{name: rs, lines: 1..3}, {}])";
+        let mut parser = init_parser(source);
+        assert_eq!(
+            parser.attributes(),
+            Ok(vec![
+                Attribute::new(
+                    "size",
+                    Literal::Map(vec![
+                        ("w".into(), Literal::Number(640)),
+                        ("h".into(), Literal::Number(480)),
+                    ])
+                ),
+                Attribute::new(
+                    "langs",
+                    Literal::List(vec![
+                        Literal::Map(vec![
+                            ("name".into(), Literal::Identifier("rs".into())),
+                            ("lines".into(), (1..3).into()),
+                        ]),
+                        Literal::Map(vec![]),
+                    ])
+                ),
+            ])
+        );
+        assert_eq!(
+            Parser::new().parse(b"image(size: {w: 1, h: 2, w: 3}) {}"),
+            Err(ParsingError::DuplicateKey { at: 25, key: "w" })
+        );
+    }
+
     #[test]
     fn node() {
         let source = r#"
-row(reversed: "true") {
+row(reversed: true) {
     p { "first" }
     "second"
 }"#;
@@ -360,23 +621,68 @@ row(reversed: "true") {
         assert_eq!(
             ast,
             Ok(Node::Tag(Tag {
-                name: "row",
+                name: "row".into(),
                 attributes: vec![Attribute {
-                    name: "reversed",
-                    value: Literal::String("true")
+                    name: "reversed".into(),
+                    value: Literal::Bool(true)
                 }],
                 children: vec![
                     Node::Tag(Tag {
-                        name: "p",
+                        name: "p".into(),
                         attributes: vec![],
-                        children: vec![Node::String("first")]
+                        children: vec![Node::String("first".into())],
+                        body: Body::Block
                     }),
-                    Node::String("second")
-                ]
+                    Node::String("second".into())
+                ],
+                body: Body::Block
             }))
         )
     }
 
+    #[test]
+    fn bodies() {
+        let source = r#"hr; image(src: "a.png"); p "text" p {} div { br; }"#;
+        let ast = Parser::new().parse(source.as_bytes()).unwrap();
+        let body = |tag: Tag<'static>, body| Node::Tag(Tag { body, ..tag });
+        assert_eq!(
+            ast,
+            vec![
+                body(Tag::new("hr"), Body::None),
+                body(Tag::new("image").with_attr("src", "a.png"), Body::None),
+                body(Tag::new("p").with_child("text"), Body::String),
+                body(Tag::new("p"), Body::Block),
+                body(
+                    Tag::new("div").with_child(body(Tag::new("br"), Body::None)),
+                    Body::Block
+                ),
+            ]
+        );
+        assert_eq!(
+            Parser::new().parse(b"p 1"),
+            Err(ParsingError::ExpectedToken {
+                at: 2,
+                expected: Token::LeftBrace,
+                got: Token::Number(1)
+            })
+        );
+    }
+
+    #[test]
+    fn errors_point_into_the_source() {
+        let source = "p { \"a\" }\ndiv(a: 1 { }";
+        let err = Parser::new().parse(source.as_bytes()).unwrap_err();
+        assert_eq!(
+            err,
+            ParsingError::ExpectedToken {
+                at: 19,
+                expected: Token::RightParen,
+                got: Token::LeftBrace
+            }
+        );
+        assert_eq!(&source[err.position()..], "{ }");
+    }
+
     #[test]
     fn markup() {
         let source = r#"
@@ -390,17 +696,34 @@ div {"second"}
             ast,
             vec![
                 Node::Tag(Tag {
-                    name: "p",
+                    name: "p".into(),
                     attributes: vec![],
-                    children: vec![Node::String("first")]
+                    children: vec![Node::String("first".into())],
+                    body: Body::Block
                 }),
                 Node::Tag(Tag {
-                    name: "div",
+                    name: "div".into(),
                     attributes: vec![],
-                    children: vec![Node::String("second")]
+                    children: vec![Node::String("second".into())],
+                    body: Body::Block
                 }),
-                Node::String("third")
+                Node::String("third".into())
             ]
         )
     }
+
+    #[test]
+    fn document() {
+        let source = br#"meta(title: "a") {} p { "b" } meta(title: "c");"#;
+        let document = Parser::new().parse_document(source).unwrap();
+        assert_eq!(
+            document.meta,
+            Some(Tag::new("meta").with_attr("title", "a"))
+        );
+        assert_eq!(document.nodes.len(), 2);
+        assert_eq!(document.into_nodes(), Parser::new().parse(source).unwrap());
+
+        let document = Parser::new().parse_document(br#"p { "b" } meta;"#).unwrap();
+        assert_eq!(document.meta, None);
+    }
 }
@@ -0,0 +1,67 @@
+use super::{rewrite, Changes, Edit, Pass};
+use crate::ast::Node;
+
+/// Removes the indentation strings get from being nested in the source.
+///
+/// The common leading whitespace of the lines is removed together with the line break right after
+/// the opening quote and the whitespace before the closing quote, so
+///
+/// ```text
+/// code-block {
+///     "
+///         let a = 1;
+///     "
+/// }
+/// ```
+///
+/// ends up with `let a = 1;` as its content.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Dedent;
+
+impl Pass for Dedent {
+    fn name(&self) -> &str {
+        "dedent"
+    }
+
+    fn run<'a>(&mut self, nodes: &mut Vec<Node<'a>>) -> Changes {
+        rewrite(nodes, &mut |node, _| {
+            let Node::String(s) = node else {
+                return Edit::Keep;
+            };
+            let dedented = dedent(s);
+            if dedented == *s {
+                Edit::Keep
+            } else {
+                Edit::Replace(Node::String(dedented.into()))
+            }
+        })
+    }
+}
+
+pub(crate) fn dedent(s: &str) -> String {
+    let s = s.strip_prefix('\n').unwrap_or(s);
+    let s = match s.rfind('\n') {
+        Some(i) if s[i..].trim().is_empty() => &s[..i],
+        _ => s,
+    };
+    textwrap::dedent(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::dedent;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn nested_code() {
+        assert_eq!(
+            dedent("\n        fn a() {\n            1\n        }\n    "),
+            "fn a() {\n    1\n}"
+        );
+    }
+
+    #[test]
+    fn single_line() {
+        assert_eq!(dedent("text"), "text");
+    }
+}
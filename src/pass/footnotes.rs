@@ -0,0 +1,330 @@
+use super::{Changes, Pass};
+use crate::ast::{Literal, Node, Tag};
+use std::collections::HashMap;
+use thiserror::Error;
+
+/// Numbers `footnote` tags and moves their content to the end of the enclosing section.
+///
+/// Every `footnote { ... }` is replaced with a marker linking to its note and the notes are
+/// collected, numbered from 1 in document order, into a list appended to the innermost enclosing
+/// `section`, or to the document, with a link back to every marker:
+///
+/// ```text
+/// p {
+///     "Markup"
+///     sup {
+///         a(id: "fnref-1", href: "#fn-1") { "1" }
+///     }
+/// }
+/// footnotes {
+///     ol {
+///         li(id: "fn-1") {
+///             "Not markdown."
+///             a(href: "#fnref-1") { "↩" }
+///         }
+///     }
+/// }
+/// ```
+///
+/// `footnote(name: "src") { ... }` defines a named footnote that `footnote(ref: "src");` refers
+/// to again, before or after the definition, every use gets the same number and its own
+/// back-link. References to undefined names and names defined twice are left as they are and
+/// reported by [`Footnotes::errors`].
+#[derive(Debug, Clone)]
+pub struct Footnotes {
+    sections: Vec<String>,
+    errors: Vec<FootnoteError>,
+}
+
+#[derive(Debug, Error, PartialEq, Eq, Clone)]
+pub enum FootnoteError {
+    #[error("Reference to undefined footnote `{}`", .0)]
+    Undefined(String),
+    #[error("Footnote `{}` is defined more than once", .0)]
+    Duplicate(String),
+}
+
+impl Default for Footnotes {
+    fn default() -> Self {
+        Self {
+            sections: vec!["section".to_string()],
+            errors: vec![],
+        }
+    }
+}
+
+/// A named footnote, found before the notes are numbered.
+struct Named<'a> {
+    children: Option<Vec<Node<'a>>>,
+    uses: usize,
+    /// The markers already put in place.
+    markers: usize,
+    number: Option<u32>,
+}
+
+struct State<'a> {
+    number: u32,
+    named: HashMap<String, Named<'a>>,
+    changes: Changes,
+}
+
+impl Footnotes {
+    /// Also emits the notes at the end of `tag`s.
+    pub fn section(mut self, tag: &str) -> Self {
+        self.sections.push(tag.to_string());
+        self
+    }
+
+    /// The problems found the last time the pass ran.
+    pub fn errors(&self) -> &[FootnoteError] {
+        &self.errors
+    }
+
+    fn is_section(&self, tag: &Tag) -> bool {
+        self.sections.iter().any(|name| *name == tag.name)
+    }
+
+    /// Takes the content of the named footnotes and counts their uses.
+    fn named<'a>(&mut self, nodes: &mut [Node<'a>], named: &mut HashMap<String, Named<'a>>) {
+        for node in nodes {
+            let Node::Tag(tag) = node else {
+                continue;
+            };
+            if tag.name != "footnote" {
+                self.named(&mut tag.children, named);
+                continue;
+            }
+            let name = match (tag.attr("name"), tag.attr("ref")) {
+                (Some(Literal::String(name)), _) | (None, Some(Literal::String(name))) => {
+                    name.to_string()
+                }
+                _ => continue,
+            };
+            let note = named.entry(name.clone()).or_insert(Named {
+                children: None,
+                uses: 0,
+                markers: 0,
+                number: None,
+            });
+            if tag.attr("name").is_none() {
+                note.uses += 1;
+            } else if note.children.is_none() {
+                note.children = Some(std::mem::take(&mut tag.children));
+                note.uses += 1;
+            } else {
+                self.errors.push(FootnoteError::Duplicate(name));
+            }
+        }
+    }
+
+    fn scope<'a>(&mut self, nodes: &mut Vec<Node<'a>>, state: &mut State<'a>) {
+        let mut notes = vec![];
+        self.walk(nodes, state, &mut notes);
+        if !notes.is_empty() {
+            nodes.push(Node::Tag(
+                Tag::new("footnotes").with_child(Tag::new("ol").with_children(notes)),
+            ));
+        }
+    }
+
+    fn walk<'a>(
+        &mut self,
+        nodes: &mut [Node<'a>],
+        state: &mut State<'a>,
+        notes: &mut Vec<Tag<'a>>,
+    ) {
+        for node in nodes {
+            let Node::Tag(tag) = node else {
+                continue;
+            };
+            if self.is_section(tag) {
+                self.scope(&mut tag.children, state);
+                continue;
+            }
+            if tag.name != "footnote" {
+                self.walk(&mut tag.children, state, notes);
+                continue;
+            }
+            let name = match (tag.attr("name"), tag.attr("ref")) {
+                (Some(Literal::String(name)), _) | (None, Some(Literal::String(name))) => {
+                    Some(name.to_string())
+                }
+                _ => None,
+            };
+            let marker = match name {
+                None => {
+                    state.number += 1;
+                    let number = state.number;
+                    notes.push(note(number, std::mem::take(&mut tag.children), 1));
+                    marker(number, 1)
+                }
+                //A second definition, which kept its children.
+                Some(_) if tag.attr("name").is_some() && !tag.children.is_empty() => continue,
+                Some(name) => {
+                    let named = state.named.get_mut(&name).expect("counted by `named`");
+                    let Some(children) = &named.children else {
+                        if !self
+                            .errors
+                            .contains(&FootnoteError::Undefined(name.clone()))
+                        {
+                            self.errors.push(FootnoteError::Undefined(name));
+                        }
+                        continue;
+                    };
+                    named.markers += 1;
+                    let number = match named.number {
+                        Some(number) => number,
+                        None => {
+                            state.number += 1;
+                            named.number = Some(state.number);
+                            notes.push(note(state.number, children.clone(), named.uses));
+                            state.number
+                        }
+                    };
+                    marker(number, named.markers)
+                }
+            };
+            *node = Node::Tag(marker);
+            state.changes.replaced += 1;
+        }
+    }
+}
+
+impl Pass for Footnotes {
+    fn name(&self) -> &str {
+        "footnotes"
+    }
+
+    fn run<'a>(&mut self, nodes: &mut Vec<Node<'a>>) -> Changes {
+        self.errors.clear();
+        let mut state = State {
+            number: 0,
+            named: HashMap::new(),
+            changes: Changes::default(),
+        };
+        self.named(nodes, &mut state.named);
+        self.scope(nodes, &mut state);
+        state.changes
+    }
+}
+
+/// The `nth` marker, from 1, of footnote `number`.
+fn marker(number: u32, nth: usize) -> Tag<'static> {
+    Tag::new("sup").with_child(
+        Tag::new("a")
+            .with_attr("id", marker_id(number, nth))
+            .with_attr("href", format!("#fn-{number}"))
+            .with_child(number.to_string()),
+    )
+}
+
+fn marker_id(number: u32, nth: usize) -> String {
+    match nth {
+        1 => format!("fnref-{number}"),
+        _ => format!("fnref-{number}-{nth}"),
+    }
+}
+
+/// The list item of footnote `number`, linking back to its `uses` markers.
+fn note(number: u32, children: Vec<Node>, uses: usize) -> Tag {
+    let back_links = (1..=uses).map(|nth| {
+        Tag::new("a")
+            .with_attr("href", format!("#{}", marker_id(number, nth)))
+            .with_child("↩")
+    });
+    Tag::new("li")
+        .with_attr("id", format!("fn-{number}"))
+        .with_children(children)
+        .with_children(back_links)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FootnoteError, Footnotes};
+    use crate::{parser::Parser, pass::Pass, printer::print};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn collects_notes_per_section() {
+        let source = r#"
+p { "a" footnote { "first" } }
+section {
+    p { "b" footnote(ref: "src"); footnote { "third" } }
+}
+p { "c" footnote(name: "src") { "second" } }
+"#;
+        let mut ast = Parser::new().parse(source.as_bytes()).unwrap();
+        let mut footnotes = Footnotes::default();
+        let changes = footnotes.run(&mut ast);
+        assert_eq!(changes.replaced, 4);
+        assert_eq!(footnotes.errors(), []);
+        assert_eq!(
+            print(&ast),
+            r##"p {
+    "a"
+    sup {
+        a(id: "fnref-1", href: "#fn-1") { "1" }
+    }
+}
+section {
+    p {
+        "b"
+        sup {
+            a(id: "fnref-2", href: "#fn-2") { "2" }
+        }
+        sup {
+            a(id: "fnref-3", href: "#fn-3") { "3" }
+        }
+    }
+    footnotes {
+        ol {
+            li(id: "fn-2") {
+                "second"
+                a(href: "#fnref-2") { "↩" }
+                a(href: "#fnref-2-2") { "↩" }
+            }
+            li(id: "fn-3") {
+                "third"
+                a(href: "#fnref-3") { "↩" }
+            }
+        }
+    }
+}
+p {
+    "c"
+    sup {
+        a(id: "fnref-2-2", href: "#fn-2") { "2" }
+    }
+}
+footnotes {
+    ol {
+        li(id: "fn-1") {
+            "first"
+            a(href: "#fnref-1") { "↩" }
+        }
+    }
+}
+"##
+        );
+    }
+
+    #[test]
+    fn reports_undefined_and_duplicate_names() {
+        let source = r#"footnote(ref: "x"); footnote(ref: "x"); footnote(name: "y") { "a" } footnote(name: "y") { "b" }"#;
+        let mut ast = Parser::new().parse(source.as_bytes()).unwrap();
+        let mut footnotes = Footnotes::default();
+        footnotes.run(&mut ast);
+        assert_eq!(
+            footnotes.errors(),
+            [
+                FootnoteError::Duplicate("y".to_string()),
+                FootnoteError::Undefined("x".to_string())
+            ]
+        );
+        assert_eq!(
+            print(&ast[..2]),
+            "footnote(ref: \"x\");\nfootnote(ref: \"x\");\n"
+        );
+        assert_eq!(print(&ast[3..4]), "footnote(name: \"y\") { \"b\" }\n");
+    }
+}
@@ -0,0 +1,404 @@
+use super::{Changes, Pass};
+use crate::ast::{Node, Tag};
+use std::collections::HashSet;
+
+/// Expands inline formatting inside strings into tags:
+///
+/// | written          | becomes                        |
+/// |------------------|--------------------------------|
+/// | `*text*`         | `em { "text" }`                |
+/// | `**text**`       | `strong { "text" }`            |
+/// | `` `text` ``     | `code { "text" }`              |
+/// | `[text](url)`    | `a(href: "url") { "text" }`    |
+///
+/// Emphasis and links can nest, code is taken verbatim. A marker only opens if it isn't followed
+/// by whitespace and only closes if it isn't preceded by whitespace, so `2 * 3 * 4` stays as it
+/// is, and markers that are never closed are kept as text. `\*`, `` \` ``, `\[` and `\]` are the
+/// marker characters themselves, written `"\\*"` or `r"\*"` in a document since the lexer only
+/// knows the usual string escapes.
+///
+/// Strings inside `code-block` are never expanded, [`Inline::skip`] adds more tags to leave alone
+/// and [`Inline::only`] restricts the pass to strings inside the given tags.
+#[derive(Debug, Clone)]
+pub struct Inline {
+    skip: Vec<String>,
+    only: Option<Vec<String>>,
+}
+
+impl Default for Inline {
+    fn default() -> Self {
+        Self {
+            skip: vec!["code-block".to_string()],
+            only: None,
+        }
+    }
+}
+
+impl Inline {
+    /// Leaves the strings inside `tag` alone too.
+    pub fn skip(mut self, tag: &str) -> Self {
+        self.skip.push(tag.to_string());
+        self
+    }
+
+    /// Only expands strings inside one of `tags`, at any depth.
+    pub fn only<S: AsRef<str>>(mut self, tags: &[S]) -> Self {
+        self.only = Some(tags.iter().map(|tag| tag.as_ref().to_string()).collect());
+        self
+    }
+
+    fn expand<'a>(&self, nodes: &mut Vec<Node<'a>>, inside_only: bool, changes: &mut Changes) {
+        let mut i = 0;
+        while i < nodes.len() {
+            match &mut nodes[i] {
+                Node::Tag(tag) => {
+                    if !self.skip.iter().any(|name| *name == tag.name) {
+                        let inside_only = inside_only
+                            || self
+                                .only
+                                .as_ref()
+                                .is_some_and(|only| only.iter().any(|name| *name == tag.name));
+                        self.expand(&mut tag.children, inside_only, changes);
+                    }
+                    i += 1;
+                }
+                Node::String(s) if self.only.is_none() || inside_only => {
+                    let expanded = inline(s);
+                    match expanded.as_slice() {
+                        [Node::String(new)] if new == s => i += 1,
+                        [_] => {
+                            nodes[i] = expanded.into_iter().next().expect("one node");
+                            changes.replaced += 1;
+                            i += 1;
+                        }
+                        _ => {
+                            let len = expanded.len();
+                            nodes.splice(i..=i, expanded);
+                            changes.spliced += 1;
+                            i += len;
+                        }
+                    }
+                }
+                Node::String(_) => i += 1,
+            }
+        }
+    }
+}
+
+impl Pass for Inline {
+    fn name(&self) -> &str {
+        "inline"
+    }
+
+    fn run<'a>(&mut self, nodes: &mut Vec<Node<'a>>) -> Changes {
+        let mut changes = Changes::default();
+        self.expand(nodes, false, &mut changes);
+        changes
+    }
+}
+
+const MARKERS: [char; 4] = ['*', '`', '[', ']'];
+
+/// The nodes `s` expands to.
+pub(crate) fn inline(s: &str) -> Vec<Node<'static>> {
+    InlineParser::new(s).nodes(None)
+}
+
+struct InlineParser<'s> {
+    s: &'s str,
+    pos: usize,
+    /// Where `*`, `**` and `](` could last close, markers opening after that are text without
+    /// looking for their end.
+    last_close: [Option<usize>; 3],
+    /// The markers that were already found not to close, by position.
+    unclosed: HashSet<(usize, &'static str)>,
+    steps: usize,
+}
+
+impl<'s> InlineParser<'s> {
+    fn new(s: &'s str) -> Self {
+        let mut last_close = [None; 3];
+        let mut paren = false;
+        for (i, c) in s.char_indices().rev() {
+            let after_whitespace = s[..i].chars().next_back().is_none_or(char::is_whitespace);
+            match c {
+                ')' => paren = true,
+                '*' if !after_whitespace => {
+                    last_close[0] = last_close[0].or(Some(i));
+                    if s[i..].starts_with("**") {
+                        last_close[1] = last_close[1].or(Some(i));
+                    }
+                }
+                ']' if paren && s[i..].starts_with("](") => {
+                    last_close[2] = last_close[2].or(Some(i));
+                }
+                _ => {}
+            }
+        }
+        Self {
+            s,
+            pos: 0,
+            last_close,
+            unclosed: HashSet::new(),
+            steps: 0,
+        }
+    }
+
+    /// Whether `marker` at `start` can't close, because it was tried before or because nothing
+    /// closes it far enough after `start`.
+    fn never_closes(&self, start: usize, marker: &'static str) -> bool {
+        let last = match marker {
+            "*" => self.last_close[0],
+            "**" => self.last_close[1],
+            _ => self.last_close[2],
+        };
+        //Leaves room for at least one character inside.
+        last.is_none_or(|last| last <= start + marker.len())
+            || self.unclosed.contains(&(start, marker))
+    }
+
+    /// Parses until `close` or the end of the string, `self.pos` is left after `close` if it was
+    /// found.
+    fn nodes(&mut self, close: Option<&str>) -> Vec<Node<'static>> {
+        let mut nodes = vec![];
+        let mut text = String::new();
+        while self.pos < self.s.len() {
+            self.steps += 1;
+            let rest = &self.s[self.pos..];
+            if let Some(close) = close.filter(|close| self.closes(close)) {
+                self.pos += close.len();
+                flush(&mut nodes, &mut text);
+                return nodes;
+            }
+            if let Some(marker) = rest.strip_prefix('\\').and_then(|r| r.chars().next()) {
+                if MARKERS.contains(&marker) {
+                    text.push(marker);
+                    self.pos += 2;
+                    continue;
+                }
+            }
+            let tag = if rest.starts_with("**") {
+                self.delimited("**", "strong")
+            } else if rest.starts_with('*') {
+                self.delimited("*", "em")
+            } else if rest.starts_with('`') {
+                self.code()
+            } else if rest.starts_with('[') {
+                self.link()
+            } else {
+                None
+            };
+            match tag {
+                Some(tag) => {
+                    flush(&mut nodes, &mut text);
+                    nodes.push(Node::Tag(tag));
+                }
+                None => {
+                    let c = rest.chars().next().expect("not at the end");
+                    text.push(c);
+                    self.pos += c.len_utf8();
+                }
+            }
+        }
+        if close.is_some() {
+            //Unclosed, the caller backtracks.
+            return vec![];
+        }
+        flush(&mut nodes, &mut text);
+        nodes
+    }
+
+    /// Whether `close` is at the current position and closes a marker.
+    fn closes(&self, close: &str) -> bool {
+        let rest = &self.s[self.pos..];
+        if !rest.starts_with(close) {
+            return false;
+        }
+        let after_whitespace = self.s[..self.pos]
+            .chars()
+            .next_back()
+            .is_none_or(char::is_whitespace);
+        //`*a **b***` closes the `**` first.
+        let longer_marker = close == "*" && rest.starts_with("**") && !rest.starts_with("***");
+        close == "]" || (!after_whitespace && !longer_marker)
+    }
+
+    fn opens(&self, marker: &str) -> bool {
+        self.s[self.pos + marker.len()..]
+            .chars()
+            .next()
+            .is_some_and(|c| !c.is_whitespace())
+    }
+
+    /// `*text*` and `**text**`, restores the position and returns `None` if they aren't closed.
+    fn delimited(&mut self, marker: &'static str, name: &'static str) -> Option<Tag<'static>> {
+        let start = self.pos;
+        if !self.opens(marker) || self.never_closes(start, marker) {
+            return None;
+        }
+        self.pos += marker.len();
+        let children = self.nodes(Some(marker));
+        if children.is_empty() {
+            self.unclosed.insert((start, marker));
+            self.pos = start;
+            return None;
+        }
+        Some(Tag::new(name).with_children(children))
+    }
+
+    fn code(&mut self) -> Option<Tag<'static>> {
+        let content = &self.s[self.pos + 1..];
+        let end = content.find('`')?;
+        if end == 0 {
+            return None;
+        }
+        self.pos += end + 2;
+        Some(Tag::new("code").with_child(content[..end].to_string()))
+    }
+
+    fn link(&mut self) -> Option<Tag<'static>> {
+        let start = self.pos;
+        if self.never_closes(start, "[") {
+            return None;
+        }
+        self.pos += 1;
+        let text = self.nodes(Some("]"));
+        let url = self.s[self.pos..]
+            .strip_prefix('(')
+            .and_then(|rest| Some(&rest[..rest.find(')')?]));
+        match url {
+            Some(url) if !text.is_empty() => {
+                self.pos += url.len() + 2;
+                Some(
+                    Tag::new("a")
+                        .with_attr("href", url.to_string())
+                        .with_children(text),
+                )
+            }
+            _ => {
+                self.unclosed.insert((start, "["));
+                self.pos = start;
+                None
+            }
+        }
+    }
+}
+
+fn flush(nodes: &mut Vec<Node<'static>>, text: &mut String) {
+    if !text.is_empty() {
+        nodes.push(Node::String(std::mem::take(text).into()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{inline, Inline, InlineParser};
+    use crate::{
+        ast::{Node, Tag},
+        parser::Parser,
+        pass::Pass,
+        printer::print,
+    };
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn markers() {
+        assert_eq!(
+            inline("Use **bold *and* more**, `a*b` or [the *docs*](https://a.b/c)."),
+            vec![
+                Node::from("Use ".to_string()),
+                Tag::new("strong")
+                    .with_child("bold ".to_string())
+                    .with_child(Tag::new("em").with_child("and".to_string()))
+                    .with_child(" more".to_string())
+                    .into(),
+                Node::from(", ".to_string()),
+                Tag::new("code").with_child("a*b".to_string()).into(),
+                Node::from(" or ".to_string()),
+                Tag::new("a")
+                    .with_attr("href", "https://a.b/c".to_string())
+                    .with_child("the ".to_string())
+                    .with_child(Tag::new("em").with_child("docs".to_string()))
+                    .into(),
+                Node::from(".".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn text_that_only_looks_like_markers() {
+        for s in ["2 * 3 * 4", "*open", "a ** b", "[no link]", "[x] (y)", "``"] {
+            assert_eq!(inline(s), vec![Node::from(s.to_string())]);
+        }
+        assert_eq!(
+            inline(r"\*not em\* \[x\](y) \`"),
+            vec![Node::from("*not em* [x](y) `".to_string())]
+        );
+        let mut ast = Parser::new().parse(br#"p { "\\*a\\*" r"\[b\]" }"#).unwrap();
+        Inline::default().run(&mut ast);
+        assert_eq!(print(&ast), "p {\n    \"*a*\"\n    \"[b]\"\n}\n");
+    }
+
+    #[test]
+    fn unclosed_markers_take_linear_time() {
+        for marker in ["*", "**", "[", "[a](", "[a *b "] {
+            let s = format!("{marker}a ").repeat(300);
+            let mut parser = InlineParser::new(&s);
+            let nodes = parser.nodes(None);
+            assert!(
+                parser.steps <= s.len(),
+                "{} steps for {marker:?}",
+                parser.steps
+            );
+            //The second `*` of `**a **a` closes an `em`.
+            if marker != "**" {
+                assert_eq!(nodes, vec![Node::from(s.clone())]);
+            }
+        }
+        //Markers that could close but don't are only tried once.
+        let s = format!("{}b*", "*a ".repeat(300));
+        let mut parser = InlineParser::new(&s);
+        parser.nodes(None);
+        assert!(
+            parser.steps <= s.len() * s.len() / 4,
+            "{} steps",
+            parser.steps
+        );
+    }
+
+    #[test]
+    fn skips_code_blocks() {
+        let source = r#"p { "a *b*" } code-block { "a *b*" } note { div { "`c`" } "d" }"#;
+        let mut ast = Parser::new().parse(source.as_bytes()).unwrap();
+        let changes = Inline::default().skip("note").run(&mut ast);
+        assert_eq!(changes.spliced, 1);
+        assert_eq!(
+            print(&ast),
+            r#"p {
+    "a "
+    em { "b" }
+}
+code-block { "a *b*" }
+note {
+    div { "`c`" }
+    "d"
+}
+"#
+        );
+
+        let mut ast = Parser::new().parse(source.as_bytes()).unwrap();
+        Inline::default().only(&["note"]).run(&mut ast);
+        assert_eq!(
+            print(&ast),
+            r#"p { "a *b*" }
+code-block { "a *b*" }
+note {
+    div {
+        code { "c" }
+    }
+    "d"
+}
+"#
+        );
+    }
+}
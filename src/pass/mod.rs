@@ -0,0 +1,391 @@
+use crate::ast::{Node, Tag};
+use serde::Serialize;
+use std::fmt::Display;
+use thiserror::Error;
+
+mod dedent;
+mod footnotes;
+mod inline;
+mod number_figures;
+mod rename;
+mod toc;
+mod whitespace;
+mod xref;
+
+pub use dedent::Dedent;
+pub use footnotes::{FootnoteError, Footnotes};
+pub use inline::Inline;
+pub use number_figures::NumberFigures;
+pub use rename::Rename;
+pub use toc::{Entry, Toc};
+pub use whitespace::Whitespace;
+pub use xref::{Anchor, Anchors, CrossReferences, ReferenceError};
+
+/// A transformation over a whole document.
+///
+/// Passes are run in order by a [`Pipeline`] and report what they changed so the pipeline can
+/// tell which passes did something.
+pub trait Pass {
+    fn name(&self) -> &str;
+    fn run<'a>(&mut self, nodes: &mut Vec<Node<'a>>) -> Changes;
+}
+
+/// Counts of what a pass did to a document.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Changes {
+    pub replaced: usize,
+    pub removed: usize,
+    pub wrapped: usize,
+    pub spliced: usize,
+    pub renamed: usize,
+    pub attributes: usize,
+}
+
+impl Changes {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl std::ops::AddAssign for Changes {
+    fn add_assign(&mut self, rhs: Self) {
+        self.replaced += rhs.replaced;
+        self.removed += rhs.removed;
+        self.wrapped += rhs.wrapped;
+        self.spliced += rhs.spliced;
+        self.renamed += rhs.renamed;
+        self.attributes += rhs.attributes;
+    }
+}
+
+impl Display for Changes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return write!(f, "no changes");
+        }
+        let counts = [
+            (self.replaced, "replaced"),
+            (self.removed, "removed"),
+            (self.wrapped, "wrapped"),
+            (self.spliced, "spliced"),
+            (self.renamed, "renamed"),
+            (self.attributes, "attributes rewritten"),
+        ];
+        let mut first = true;
+        for (count, what) in counts.into_iter().filter(|(count, _)| *count != 0) {
+            if !first {
+                write!(f, ", ")?;
+            }
+            first = false;
+            write!(f, "{count} {what}")?;
+        }
+        Ok(())
+    }
+}
+
+/// What [`rewrite`] should do with the node it visited.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Edit<'a> {
+    /// Keeps the node and visits its children.
+    Keep,
+    Replace(Node<'a>),
+    Remove,
+    /// Appends the node to the children of the given tag and puts the tag in its place.
+    Wrap(Tag<'a>),
+    /// Puts the given nodes in place of the node.
+    Splice(Vec<Node<'a>>),
+}
+
+/// Visits every node of `nodes` in document order and applies the [`Edit`] returned by `f`.
+///
+/// Nodes put in place by an edit are not visited again so a pass can't loop on its own output.
+/// `f` can also modify the node in place, e.g. to rewrite its attributes, in which case it should
+/// record that in the given [`Changes`] itself.
+pub fn rewrite<'a, F>(nodes: &mut Vec<Node<'a>>, f: &mut F) -> Changes
+where
+    F: FnMut(&mut Node<'a>, &mut Changes) -> Edit<'a>,
+{
+    let mut changes = Changes::default();
+    rewrite_into(nodes, f, &mut changes);
+    changes
+}
+
+fn rewrite_into<'a, F>(nodes: &mut Vec<Node<'a>>, f: &mut F, changes: &mut Changes)
+where
+    F: FnMut(&mut Node<'a>, &mut Changes) -> Edit<'a>,
+{
+    let mut i = 0;
+    while i < nodes.len() {
+        match f(&mut nodes[i], changes) {
+            Edit::Keep => {
+                if let Node::Tag(tag) = &mut nodes[i] {
+                    rewrite_into(&mut tag.children, f, changes);
+                }
+                i += 1;
+            }
+            Edit::Replace(node) => {
+                nodes[i] = node;
+                changes.replaced += 1;
+                i += 1;
+            }
+            Edit::Remove => {
+                nodes.remove(i);
+                changes.removed += 1;
+            }
+            Edit::Wrap(mut wrapper) => {
+                let node = std::mem::replace(&mut nodes[i], Node::String("".into()));
+                wrapper.children.push(node);
+                nodes[i] = Node::Tag(wrapper);
+                changes.wrapped += 1;
+                i += 1;
+            }
+            Edit::Splice(spliced) => {
+                let len = spliced.len();
+                nodes.splice(i..=i, spliced);
+                changes.spliced += 1;
+                i += len;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq, Clone)]
+pub enum PassError {
+    #[error("Unknown pass `{}`", .0)]
+    UnknownPass(String),
+    #[error("Invalid configuration `{}` for pass `{}`: {}", .config, .pass, .reason)]
+    InvalidConfig {
+        pass: String,
+        config: String,
+        reason: &'static str,
+    },
+}
+
+/// Creates a built-in pass from a `name` or `name:config` spec, as passed on the command line.
+///
+/// | spec                      | pass                     |
+/// |---------------------------|--------------------------|
+/// | `dedent`                  | [`Dedent`]               |
+/// | `footnotes`               | [`Footnotes`]            |
+/// | `footnotes:sections=a,b`  | [`Footnotes::section`]   |
+/// | `inline`                  | [`Inline`]               |
+/// | `inline:skip=a,b`         | [`Inline::skip`]         |
+/// | `inline:only=a,b`         | [`Inline::only`]         |
+/// | `number-figures`          | [`NumberFigures`]        |
+/// | `rename:from=to`          | [`Rename`]               |
+/// | `toc`                     | [`Toc`]                  |
+/// | `toc:headings=a,b`        | [`Toc::headings`]        |
+/// | `whitespace`              | [`Whitespace`]           |
+/// | `whitespace:inline=a,b`   | [`Whitespace::inline`]   |
+/// | `whitespace:preserve=a,b` | [`Whitespace::preserve`] |
+pub fn from_spec(spec: &str) -> Result<Box<dyn Pass>, PassError> {
+    let (name, config) = match spec.split_once(':') {
+        Some((name, config)) => (name, Some(config)),
+        None => (spec, None),
+    };
+    let invalid = |reason| PassError::InvalidConfig {
+        pass: name.to_string(),
+        config: config.unwrap_or_default().to_string(),
+        reason,
+    };
+    match (name, config) {
+        ("dedent", None) => Ok(Box::new(Dedent)),
+        ("number-figures", None) => Ok(Box::new(NumberFigures::default())),
+        ("footnotes", None) => Ok(Box::new(Footnotes::default())),
+        ("footnotes", Some(config)) => match config.split_once('=') {
+            Some(("sections", tags)) if !tags.is_empty() => Ok(Box::new(
+                tags.split(',')
+                    .fold(Footnotes::default(), Footnotes::section),
+            )),
+            _ => Err(invalid("expected `sections=a,b`")),
+        },
+        ("inline", None) => Ok(Box::new(Inline::default())),
+        ("inline", Some(config)) => match config.split_once('=') {
+            Some(("skip", tags)) if !tags.is_empty() => Ok(Box::new(
+                tags.split(',').fold(Inline::default(), Inline::skip),
+            )),
+            Some(("only", tags)) if !tags.is_empty() => Ok(Box::new(
+                Inline::default().only(&tags.split(',').collect::<Vec<_>>()),
+            )),
+            _ => Err(invalid("expected `skip=a,b` or `only=a,b`")),
+        },
+        ("toc", None) => Ok(Box::new(Toc::default())),
+        ("toc", Some(config)) => match config.split_once('=') {
+            Some(("headings", tags)) if !tags.is_empty() => Ok(Box::new(Toc::headings(
+                &tags.split(',').collect::<Vec<_>>(),
+            ))),
+            _ => Err(invalid("expected `headings=a,b`")),
+        },
+        ("whitespace", None) => Ok(Box::new(Whitespace::default())),
+        ("whitespace", Some(config)) => match config.split_once('=') {
+            Some(("inline", tags)) if !tags.is_empty() => Ok(Box::new(
+                tags.split(',')
+                    .fold(Whitespace::default(), Whitespace::inline),
+            )),
+            Some(("preserve", tags)) if !tags.is_empty() => Ok(Box::new(
+                tags.split(',')
+                    .fold(Whitespace::default(), Whitespace::preserve),
+            )),
+            _ => Err(invalid("expected `inline=a,b` or `preserve=a,b`")),
+        },
+        ("rename", Some(config)) => match config.split_once('=') {
+            Some((from, to)) if !from.is_empty() && !to.is_empty() => {
+                Ok(Box::new(Rename::new(from, to)))
+            }
+            _ => Err(invalid("expected `from=to`")),
+        },
+        ("rename", None) => Err(invalid("expected `from=to`")),
+        ("dedent" | "number-figures", Some(_)) => Err(invalid("this pass takes no configuration")),
+        _ => Err(PassError::UnknownPass(name.to_string())),
+    }
+}
+
+/// What a single pass of a [`Pipeline`] changed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Report {
+    pub pass: String,
+    pub changes: Changes,
+}
+
+/// Runs passes in the order they were added.
+#[derive(Default)]
+pub struct Pipeline {
+    passes: Vec<Box<dyn Pass>>,
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds a pipeline out of pass specs, see [`from_spec`].
+    pub fn from_specs<S: AsRef<str>>(specs: &[S]) -> Result<Self, PassError> {
+        let passes = specs
+            .iter()
+            .map(|spec| from_spec(spec.as_ref()))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { passes })
+    }
+
+    pub fn with(mut self, pass: impl Pass + 'static) -> Self {
+        self.passes.push(Box::new(pass));
+        self
+    }
+
+    pub fn push(&mut self, pass: Box<dyn Pass>) {
+        self.passes.push(pass);
+    }
+
+    pub fn run(&mut self, nodes: &mut Vec<Node>) -> Vec<Report> {
+        self.passes
+            .iter_mut()
+            .map(|pass| Report {
+                changes: pass.run(nodes),
+                pass: pass.name().to_string(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_spec, rewrite, Changes, Edit, PassError, Pipeline};
+    use crate::{
+        ast::{Node, Tag},
+        parser::Parser,
+        printer::print,
+    };
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn rewrite_edits() {
+        let source = r#"
+remove { "a" }
+replace { "b" }
+wrap { "c" }
+splice { "d" }
+div { remove {} "e" }
+"#;
+        let mut ast = Parser::new().parse(source.as_bytes()).unwrap();
+        let changes = rewrite(&mut ast, &mut |node, _| match node {
+            Node::Tag(tag) => match tag.name.as_ref() {
+                "remove" => Edit::Remove,
+                "replace" => Edit::Replace(Node::String("replaced".into())),
+                "wrap" => Edit::Wrap(Tag::new("wrapper")),
+                "splice" => Edit::Splice(tag.children.drain(..).collect()),
+                _ => Edit::Keep,
+            },
+            Node::String(_) => Edit::Keep,
+        });
+        assert_eq!(
+            changes,
+            Changes {
+                replaced: 1,
+                removed: 2,
+                wrapped: 1,
+                spliced: 1,
+                renamed: 0,
+                attributes: 0
+            }
+        );
+        assert_eq!(
+            print(&ast),
+            r#""replaced"
+wrapper {
+    wrap { "c" }
+}
+"d"
+div { "e" }
+"#
+        );
+    }
+
+    #[test]
+    fn pipeline_reports_each_pass() {
+        let source = r#"cb { "
+    a
+    " }
+figure {}
+"#;
+        let mut ast = Parser::new().parse(source.as_bytes()).unwrap();
+        let reports = Pipeline::from_specs(&["rename:cb=code-block", "dedent", "number-figures"])
+            .unwrap()
+            .run(&mut ast);
+        let summary: Vec<_> = reports
+            .iter()
+            .map(|report| format!("{}: {}", report.pass, report.changes))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                "rename: 1 renamed".to_string(),
+                "dedent: 1 replaced".to_string(),
+                "number-figures: 1 attributes rewritten".to_string()
+            ]
+        );
+        assert_eq!(
+            print(&ast),
+            r#"code-block { "a" }
+figure(number: 1) {}
+"#
+        );
+    }
+
+    #[test]
+    fn invalid_specs() {
+        assert_eq!(
+            from_spec("nope").err(),
+            Some(PassError::UnknownPass("nope".to_string()))
+        );
+        assert!(matches!(
+            from_spec("rename:cb").err(),
+            Some(PassError::InvalidConfig { .. })
+        ));
+        assert!(matches!(
+            from_spec("inline:p").err(),
+            Some(PassError::InvalidConfig { .. })
+        ));
+        assert!(from_spec("inline:only=p,li").is_ok());
+        assert!(from_spec("toc:headings=title,subtitle").is_ok());
+    }
+}
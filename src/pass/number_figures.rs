@@ -0,0 +1,62 @@
+use super::{rewrite, Changes, Edit, Pass};
+use crate::ast::{Attribute, Literal, Node};
+
+/// Gives every `figure` tag a `number` attribute counting from 1 in document order.
+///
+/// An existing `number` attribute is overwritten so running the pass twice is harmless.
+#[derive(Debug, Clone)]
+pub struct NumberFigures {
+    tag: String,
+}
+
+impl Default for NumberFigures {
+    fn default() -> Self {
+        Self {
+            tag: "figure".to_string(),
+        }
+    }
+}
+
+impl NumberFigures {
+    /// Numbers tags named `tag` instead of `figure`.
+    pub fn new(tag: &str) -> Self {
+        Self {
+            tag: tag.to_string(),
+        }
+    }
+}
+
+impl Pass for NumberFigures {
+    fn name(&self) -> &str {
+        "number-figures"
+    }
+
+    fn run<'a>(&mut self, nodes: &mut Vec<Node<'a>>) -> Changes {
+        let mut number = 0;
+        rewrite(nodes, &mut |node, changes| {
+            let Node::Tag(tag) = node else {
+                return Edit::Keep;
+            };
+            if tag.name != self.tag {
+                return Edit::Keep;
+            }
+            number += 1;
+            let value = Literal::Number(number);
+            match tag.attributes.iter_mut().find(|attr| attr.name == "number") {
+                Some(attr) if attr.value == value => {}
+                Some(attr) => {
+                    attr.value = value;
+                    changes.attributes += 1;
+                }
+                None => {
+                    tag.attributes.push(Attribute {
+                        name: "number".into(),
+                        value,
+                    });
+                    changes.attributes += 1;
+                }
+            }
+            Edit::Keep
+        })
+    }
+}
@@ -0,0 +1,37 @@
+use super::{rewrite, Changes, Edit, Pass};
+use crate::ast::Node;
+
+/// Renames every `from` tag to `to`, which is handy to expand shortcuts like `cb` into
+/// `code-block`.
+#[derive(Debug, Clone)]
+pub struct Rename {
+    from: String,
+    to: String,
+}
+
+impl Rename {
+    pub fn new(from: &str, to: &str) -> Self {
+        Self {
+            from: from.to_string(),
+            to: to.to_string(),
+        }
+    }
+}
+
+impl Pass for Rename {
+    fn name(&self) -> &str {
+        "rename"
+    }
+
+    fn run<'a>(&mut self, nodes: &mut Vec<Node<'a>>) -> Changes {
+        rewrite(nodes, &mut |node, changes| {
+            if let Node::Tag(tag) = node {
+                if tag.name == self.from {
+                    tag.name = self.to.clone().into();
+                    changes.renamed += 1;
+                }
+            }
+            Edit::Keep
+        })
+    }
+}
@@ -0,0 +1,361 @@
+use super::{Changes, Pass};
+use crate::ast::{Attribute, Body, Literal, Node, Tag};
+use serde::Serialize;
+use std::collections::HashSet;
+
+/// Gives every heading an `id` and fills `toc` tags with a nested list of links to the headings.
+///
+/// Headings are `h1` to `h6` by default, [`Toc::headings`] sets other tags from the top level
+/// down. The id of a heading is a slug of its text, `h2 { "Getting started" }` gets
+/// `id: "getting-started"`, and slugs that are already taken get a `-2`, `-3`, ... suffix.
+/// Headings that already have an `id` keep it, so the ids of a document don't change when it is
+/// transformed again and links to them keep working.
+///
+/// The children of every `toc` tag are replaced with the outline:
+///
+/// ```text
+/// toc {
+///     ul {
+///         li {
+///             a(href: "#intro") { "Intro" }
+///             ul { ... }
+///         }
+///     }
+/// }
+/// ```
+///
+/// `toc(depth: 2)` only lists the first two levels. Headings inside a `toc` tag are ignored.
+/// After the pass has run [`Toc::outline`] returns the outline, e.g. to export it as json.
+#[derive(Debug, Clone)]
+pub struct Toc {
+    headings: Vec<String>,
+    outline: Vec<Entry>,
+}
+
+/// A heading in the outline built by [`Toc`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Entry {
+    /// Starts at 1 for the first heading tag.
+    pub level: usize,
+    pub id: String,
+    /// The text of the heading with its whitespace collapsed.
+    pub title: String,
+    /// The headings of a lower level up to the next heading of this level or above.
+    pub children: Vec<Entry>,
+}
+
+impl Default for Toc {
+    fn default() -> Self {
+        Self::headings(&["h1", "h2", "h3", "h4", "h5", "h6"])
+    }
+}
+
+impl Toc {
+    /// Uses `tags` as headings, the first one is the top level.
+    pub fn headings<S: AsRef<str>>(tags: &[S]) -> Self {
+        Self {
+            headings: tags.iter().map(|tag| tag.as_ref().to_string()).collect(),
+            outline: vec![],
+        }
+    }
+
+    /// The outline of the document the pass last ran over.
+    pub fn outline(&self) -> &[Entry] {
+        &self.outline
+    }
+
+    fn level(&self, tag: &Tag) -> Option<usize> {
+        self.headings
+            .iter()
+            .position(|name| *name == tag.name)
+            .map(|i| i + 1)
+    }
+
+    fn collect(
+        &self,
+        nodes: &mut [Node],
+        ids: &mut HashSet<String>,
+        entries: &mut Vec<Entry>,
+        changes: &mut Changes,
+    ) {
+        for node in nodes {
+            let Node::Tag(tag) = node else {
+                continue;
+            };
+            if tag.name == "toc" {
+                continue;
+            }
+            let Some(level) = self.level(tag) else {
+                self.collect(&mut tag.children, ids, entries, changes);
+                continue;
+            };
+            let title = title(tag);
+            let id = match tag.attr("id") {
+                Some(Literal::String(id)) => id.to_string(),
+                _ => {
+                    let id = unique(slug(&title), ids);
+                    tag.attributes.retain(|attr| attr.name() != "id");
+                    tag.attributes
+                        .push(Attribute::new("id", Literal::String(id.clone().into())));
+                    changes.attributes += 1;
+                    id
+                }
+            };
+            entries.push(Entry {
+                level,
+                id,
+                title,
+                children: vec![],
+            });
+        }
+    }
+
+    fn fill(&self, nodes: &mut [Node], changes: &mut Changes) {
+        for node in nodes {
+            let Node::Tag(tag) = node else {
+                continue;
+            };
+            if tag.name != "toc" {
+                self.fill(&mut tag.children, changes);
+                continue;
+            }
+            //A malformed depth lists everything rather than failing the whole pass.
+            let depth = tag.get::<Option<u32>>("depth").ok().flatten();
+            let children = match list(&self.outline, depth) {
+                Some(list) => vec![Node::Tag(list)],
+                None => vec![],
+            };
+            if tag.children != children {
+                if !children.is_empty() {
+                    tag.body = Body::Block;
+                }
+                tag.children = children;
+                changes.replaced += 1;
+            }
+        }
+    }
+}
+
+impl Pass for Toc {
+    fn name(&self) -> &str {
+        "toc"
+    }
+
+    fn run<'a>(&mut self, nodes: &mut Vec<Node<'a>>) -> Changes {
+        let mut changes = Changes::default();
+        let mut ids = HashSet::new();
+        taken_ids(nodes, &mut ids);
+        let mut entries = vec![];
+        self.collect(nodes, &mut ids, &mut entries, &mut changes);
+        self.outline = nest(entries);
+        self.fill(nodes, &mut changes);
+        changes
+    }
+}
+
+/// Every string `id` in `nodes` so generated ids don't clash with them.
+fn taken_ids(nodes: &[Node], ids: &mut HashSet<String>) {
+    for node in nodes {
+        if let Node::Tag(tag) = node {
+            if let Some(Literal::String(id)) = tag.attr("id") {
+                ids.insert(id.to_string());
+            }
+            taken_ids(&tag.children, ids);
+        }
+    }
+}
+
+/// The text of `tag` with its whitespace collapsed.
+pub(super) fn title(tag: &Tag) -> String {
+    fn collect(nodes: &[Node], out: &mut String) {
+        for node in nodes {
+            match node {
+                Node::String(s) => out.push_str(s),
+                Node::Tag(tag) => collect(&tag.children, out),
+            }
+        }
+    }
+    let mut text = String::new();
+    collect(&tag.children, &mut text);
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Lowercases `title` and joins its words with `-`, `"What's new?"` becomes `whats-new`.
+fn slug(title: &str) -> String {
+    let mut slug = String::with_capacity(title.len());
+    for c in title.chars() {
+        if c.is_alphanumeric() {
+            slug.extend(c.to_lowercase());
+        } else if (c.is_whitespace() || c == '-' || c == '_') && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_matches('-');
+    if slug.is_empty() {
+        "section".to_string()
+    } else {
+        slug.to_string()
+    }
+}
+
+fn unique(slug: String, ids: &mut HashSet<String>) -> String {
+    let mut id = slug.clone();
+    let mut n = 1;
+    while ids.contains(&id) {
+        n += 1;
+        id = format!("{slug}-{n}");
+    }
+    ids.insert(id.clone());
+    id
+}
+
+/// Nests every entry under the closest previous entry of a higher level.
+fn nest(entries: Vec<Entry>) -> Vec<Entry> {
+    let mut outline: Vec<Entry> = vec![];
+    for entry in entries {
+        let mut siblings = &mut outline;
+        while siblings.last().is_some_and(|last| last.level < entry.level) {
+            siblings = &mut siblings.last_mut().expect("checked above").children;
+        }
+        siblings.push(entry);
+    }
+    outline
+}
+
+fn list(entries: &[Entry], depth: Option<u32>) -> Option<Tag<'static>> {
+    if entries.is_empty() || depth == Some(0) {
+        return None;
+    }
+    let items = entries.iter().map(|entry| {
+        let link = Tag::new("a")
+            .with_attr("href", format!("#{}", entry.id))
+            .with_child(entry.title.clone());
+        Tag::new("li")
+            .with_child(link)
+            .with_children(list(&entry.children, depth.map(|depth| depth - 1)))
+    });
+    Some(Tag::new("ul").with_children(items))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{slug, Entry, Toc};
+    use crate::{parser::Parser, pass::Pass, printer::print};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn slugs() {
+        assert_eq!(slug("Getting started"), "getting-started");
+        assert_eq!(slug("  What's new in 2.0?  "), "whats-new-in-20");
+        assert_eq!(slug("snake_case - and more"), "snake-case-and-more");
+        assert_eq!(slug("Überblick"), "überblick");
+        assert_eq!(slug("???"), "section");
+    }
+
+    #[test]
+    fn fills_toc_tags() {
+        let source = r#"
+toc;
+h1 { "Intro" }
+section(id: "usage") {
+    h2 { "Usage" }
+    h3 { "The " code { "markup" } " command" }
+}
+h2(id: "faq") { "FAQ" }
+h2 { "Usage" }
+"#;
+        let mut ast = Parser::new().parse(source.as_bytes()).unwrap();
+        let mut toc = Toc::default();
+        let changes = toc.run(&mut ast);
+        assert_eq!(changes.attributes, 4);
+        assert_eq!(changes.replaced, 1);
+        let entry = |level, id: &str, title: &str, children| Entry {
+            level,
+            id: id.to_string(),
+            title: title.to_string(),
+            children,
+        };
+        assert_eq!(
+            toc.outline(),
+            [entry(
+                1,
+                "intro",
+                "Intro",
+                vec![
+                    entry(
+                        2,
+                        "usage-2",
+                        "Usage",
+                        vec![entry(3, "the-markup-command", "The markup command", vec![])]
+                    ),
+                    entry(2, "faq", "FAQ", vec![]),
+                    entry(2, "usage-3", "Usage", vec![]),
+                ]
+            )]
+        );
+        let printed = print(&ast);
+        assert_eq!(
+            printed,
+            r##"toc {
+    ul {
+        li {
+            a(href: "#intro") { "Intro" }
+            ul {
+                li {
+                    a(href: "#usage-2") { "Usage" }
+                    ul {
+                        li {
+                            a(href: "#the-markup-command") { "The markup command" }
+                        }
+                    }
+                }
+                li {
+                    a(href: "#faq") { "FAQ" }
+                }
+                li {
+                    a(href: "#usage-3") { "Usage" }
+                }
+            }
+        }
+    }
+}
+h1(id: "intro") { "Intro" }
+section(id: "usage") {
+    h2(id: "usage-2") { "Usage" }
+    h3(id: "the-markup-command") {
+        "The "
+        code { "markup" }
+        " command"
+    }
+}
+h2(id: "faq") { "FAQ" }
+h2(id: "usage-3") { "Usage" }
+"##
+        );
+
+        //The ids are kept so running the pass again changes nothing.
+        let mut ast = Parser::new().parse(printed.as_bytes()).unwrap();
+        assert!(Toc::default().run(&mut ast).is_empty());
+    }
+
+    #[test]
+    fn depth_and_custom_headings() {
+        let source = r#"toc(depth: 1) {} title { "A" } subtitle { "B" } h1 { "C" }"#;
+        let mut ast = Parser::new().parse(source.as_bytes()).unwrap();
+        Toc::headings(&["title", "subtitle"]).run(&mut ast);
+        assert_eq!(
+            print(&ast),
+            r##"toc(depth: 1) {
+    ul {
+        li {
+            a(href: "#a") { "A" }
+        }
+    }
+}
+title(id: "a") { "A" }
+subtitle(id: "b") { "B" }
+h1 { "C" }
+"##
+        );
+    }
+}
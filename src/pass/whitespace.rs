@@ -0,0 +1,238 @@
+use super::{Changes, Pass};
+use crate::ast::{Node, Tag};
+use std::borrow::Cow;
+
+/// Normalises the whitespace of strings so every backend can render them as they are.
+///
+/// Tags are either inline, like `b` or `a`, block, like `p` or `section`, or preserved, like
+/// `code-block`. The strings and inline tags between two block tags form a run of text that is
+/// normalised as a whole:
+///
+/// * every run of spaces, tabs and line breaks becomes a single space,
+/// * a space right after another one is removed, even across inline tags, so
+///   `"a " b { " b" }` becomes `"a " b { "b" }`,
+/// * spaces at the start and the end of a run are removed,
+/// * strings left empty are removed.
+///
+/// Inline tags without text, like `img;`, count as content so the spaces around them are kept.
+/// Strings inside preserved tags are left as they are, they are meant to be rendered verbatim.
+/// After the pass `p { "a " b { "b" } " c" }` reads `p { "a " b { "b" } " c" }` and
+/// `p { "\n    a\n    " b { "b" } "\n" }` reads `p { "a " b { "b" } }`.
+#[derive(Debug, Clone)]
+pub struct Whitespace {
+    inline: Vec<String>,
+    preserved: Vec<String>,
+}
+
+impl Default for Whitespace {
+    fn default() -> Self {
+        let inline = [
+            "a", "abbr", "b", "br", "code", "em", "i", "img", "kbd", "mark", "s", "small", "span",
+            "strong", "sub", "sup", "u",
+        ];
+        Self {
+            inline: inline.map(str::to_string).to_vec(),
+            preserved: vec!["code-block".to_string(), "pre".to_string()],
+        }
+    }
+}
+
+impl Whitespace {
+    /// Treats `tag` as an inline tag.
+    pub fn inline(mut self, tag: &str) -> Self {
+        self.inline.push(tag.to_string());
+        self
+    }
+
+    /// Leaves the strings inside `tag` alone.
+    pub fn preserve(mut self, tag: &str) -> Self {
+        self.preserved.push(tag.to_string());
+        self
+    }
+
+    fn is_inline(&self, tag: &Tag) -> bool {
+        self.inline.iter().any(|name| *name == tag.name) && !self.is_preserved(tag)
+    }
+
+    fn is_preserved(&self, tag: &Tag) -> bool {
+        self.preserved.iter().any(|name| *name == tag.name)
+    }
+
+    fn block(&self, nodes: &mut [Node], changes: &mut Changes) {
+        let is_block = |node: &Node| matches!(node, Node::Tag(tag) if !self.is_inline(tag));
+        for run in nodes.split_mut(is_block) {
+            let mut leaves = vec![];
+            self.leaves(run, &mut leaves);
+            normalize(leaves, changes);
+        }
+        self.nested_blocks(nodes, changes);
+    }
+
+    /// The text of a run in document order.
+    fn leaves<'r, 'a>(&self, nodes: &'r mut [Node<'a>], leaves: &mut Vec<Leaf<'r, 'a>>) {
+        for node in nodes {
+            match node {
+                Node::String(s) => leaves.push(Leaf::Text(s)),
+                Node::Tag(tag) if self.is_inline(tag) && !tag.children.is_empty() => {
+                    self.leaves(&mut tag.children, leaves)
+                }
+                Node::Tag(_) => leaves.push(Leaf::Content),
+            }
+        }
+    }
+
+    /// Normalises the blocks inside `nodes`, which were already normalised as part of a run.
+    fn nested_blocks(&self, nodes: &mut [Node], changes: &mut Changes) {
+        for node in nodes {
+            match node {
+                Node::Tag(tag) if self.is_preserved(tag) => {}
+                Node::Tag(tag) if self.is_inline(tag) => {
+                    self.nested_blocks(&mut tag.children, changes)
+                }
+                Node::Tag(tag) => self.block(&mut tag.children, changes),
+                Node::String(_) => {}
+            }
+        }
+    }
+
+    fn remove_empty(&self, nodes: &mut Vec<Node>, changes: &mut Changes) {
+        let len = nodes.len();
+        nodes.retain(|node| !matches!(node, Node::String(s) if s.is_empty()));
+        changes.removed += len - nodes.len();
+        for node in nodes {
+            match node {
+                Node::Tag(tag) if !self.is_preserved(tag) => {
+                    self.remove_empty(&mut tag.children, changes)
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Pass for Whitespace {
+    fn name(&self) -> &str {
+        "whitespace"
+    }
+
+    fn run<'a>(&mut self, nodes: &mut Vec<Node<'a>>) -> Changes {
+        let mut changes = Changes::default();
+        self.block(nodes, &mut changes);
+        self.remove_empty(nodes, &mut changes);
+        changes
+    }
+}
+
+enum Leaf<'r, 'a> {
+    Text(&'r mut Cow<'a, str>),
+    /// An inline tag without text, or a preserved or block tag inside an inline tag.
+    Content,
+}
+
+fn normalize(leaves: Vec<Leaf>, changes: &mut Changes) {
+    //Whether the text so far ends with a space, the start of a run counts as one.
+    let mut space = true;
+    let mut last_text = None;
+    let mut texts: Vec<Option<String>> = Vec::with_capacity(leaves.len());
+    for leaf in &leaves {
+        match leaf {
+            Leaf::Text(s) => {
+                let mut text = collapse(s);
+                if space && text.starts_with(' ') {
+                    text.remove(0);
+                }
+                if !text.is_empty() {
+                    space = text.ends_with(' ');
+                    last_text = Some(texts.len());
+                }
+                texts.push(Some(text));
+            }
+            Leaf::Content => {
+                space = false;
+                last_text = None;
+                texts.push(None);
+            }
+        }
+    }
+    if let Some(Some(text)) = last_text.map(|i| &mut texts[i]) {
+        if text.ends_with(' ') {
+            text.pop();
+        }
+    }
+    for (leaf, text) in leaves.into_iter().zip(texts) {
+        let (Leaf::Text(s), Some(text)) = (leaf, text) else {
+            continue;
+        };
+        if **s != text {
+            //Empty strings are counted when they are removed.
+            if !text.is_empty() {
+                changes.replaced += 1;
+            }
+            *s = text.into();
+        }
+    }
+}
+
+/// Replaces every run of whitespace with a single space.
+fn collapse(s: &str) -> String {
+    let mut collapsed = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if is_collapsible(c) {
+            while chars.next_if(|c| is_collapsible(*c)).is_some() {}
+            collapsed.push(' ');
+        } else {
+            collapsed.push(c);
+        }
+    }
+    collapsed
+}
+
+/// The whitespace html collapses, a non-breaking space is kept.
+fn is_collapsible(c: char) -> bool {
+    matches!(c, ' ' | '\t' | '\n' | '\r' | '\x0c')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Whitespace;
+    use crate::{parser::Parser, pass::Pass, printer::print};
+    use pretty_assertions::assert_eq;
+
+    fn normalize(source: &str) -> String {
+        let mut ast = Parser::new().parse(source.as_bytes()).unwrap();
+        Whitespace::default().run(&mut ast);
+        print(&ast)
+    }
+
+    #[test]
+    fn joins_inline_tags_with_single_spaces() {
+        let expected = "p {\n    \"a \"\n    b { \"b\" }\n    \" c\"\n}\n";
+        assert_eq!(normalize(r#"p { "a " b { "b" } " c" }"#), expected);
+        assert_eq!(
+            normalize(r#"p { "a " b { " b " } "  c " }"#),
+            "p {\n    \"a \"\n    b { \"b \" }\n    \"c\"\n}\n"
+        );
+        assert_eq!(
+            normalize("p {\n    \"\n        a\n        long line\n    \"\n    b { \"b\" }\n}"),
+            "p {\n    \"a long line \"\n    b { \"b\" }\n}\n"
+        );
+    }
+
+    #[test]
+    fn blocks_end_runs() {
+        assert_eq!(
+            normalize(r#"section { " a " p { " b " img; " " } "  " code-block { "  x  " } " c" }"#),
+            r#"section {
+    "a"
+    p {
+        "b "
+        img;
+    }
+    code-block { "  x  " }
+    "c"
+}
+"#
+        );
+    }
+}
@@ -0,0 +1,352 @@
+use super::{toc::title, Changes};
+use crate::{
+    ast::{Attribute, Body, Literal, Node, Tag},
+    project::{File, Location},
+};
+use std::collections::HashMap;
+use thiserror::Error;
+
+/// Resolves `ref(to: "id")` tags against the `id` attributes of every document of a project.
+///
+/// A `ref` without children gets the text of its target as its link text:
+///
+/// * a heading, `h1` to `h6` by default, is referred to by its text,
+/// * a `figure` is referred to as `Figure 3`, using its `number` attribute or, without one, its
+///   position among the figures of its document like
+///   [`NumberFigures`](super::NumberFigures) would number it, [`CrossReferences::label`] adds
+///   more numbered tags,
+/// * any other tag is referred to by its id.
+///
+/// A `ref` that already has children keeps them. A `ref` to a tag in another document also gets
+/// a `path` attribute naming that document so renderers can link to it.
+///
+/// Ids are shared by the whole project, an id defined twice, even in two different files, is an
+/// error, as is a `ref` to an unknown id. Errors don't stop the resolution, every `ref` that can
+/// be resolved is.
+#[derive(Debug, Clone)]
+pub struct CrossReferences {
+    headings: Vec<String>,
+    labels: Vec<(String, String)>,
+}
+
+#[derive(Debug, Error, PartialEq, Eq, Clone)]
+pub enum ReferenceError {
+    #[error("{}: Reference to unknown id `{}`", .at, .id)]
+    UnknownId { at: Location, id: String },
+    #[error("{}: Duplicate id `{}`, it is already defined at {}", .at, .id, .first)]
+    DuplicateId {
+        at: Location,
+        id: String,
+        first: Location,
+    },
+    #[error("{}: `ref` should have a string `to` attribute naming an id", .at)]
+    MissingTarget { at: Location },
+}
+
+impl ReferenceError {
+    pub fn location(&self) -> &Location {
+        match self {
+            ReferenceError::UnknownId { at, .. }
+            | ReferenceError::DuplicateId { at, .. }
+            | ReferenceError::MissingTarget { at } => at,
+        }
+    }
+}
+
+impl Default for CrossReferences {
+    fn default() -> Self {
+        Self {
+            headings: ["h1", "h2", "h3", "h4", "h5", "h6"]
+                .map(str::to_string)
+                .to_vec(),
+            labels: vec![("figure".to_string(), "Figure".to_string())],
+        }
+    }
+}
+
+/// An id, where it is and what a `ref` to it shows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Anchor {
+    pub id: String,
+    /// The path of the file defining the id.
+    pub path: String,
+    pub text: String,
+    pub at: Location,
+}
+
+/// The anchors of a whole project by id, see [`Anchors::new`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Anchors {
+    by_id: HashMap<String, Anchor>,
+}
+
+impl Anchors {
+    /// Indexes `anchors`, given in document order, and reports the ids defined more than once,
+    /// the first definition wins.
+    pub fn new(anchors: impl IntoIterator<Item = Anchor>) -> (Self, Vec<ReferenceError>) {
+        let mut by_id: HashMap<String, Anchor> = HashMap::new();
+        let mut errors = vec![];
+        for anchor in anchors {
+            match by_id.get(&anchor.id) {
+                Some(first) => errors.push(ReferenceError::DuplicateId {
+                    at: anchor.at,
+                    id: anchor.id,
+                    first: first.at.clone(),
+                }),
+                None => {
+                    by_id.insert(anchor.id.clone(), anchor);
+                }
+            }
+        }
+        (Self { by_id }, errors)
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Anchor> {
+        self.by_id.get(id)
+    }
+}
+
+impl CrossReferences {
+    /// Refers to `tag`s as `prefix` followed by their number, e.g. `label("table", "Table")`.
+    pub fn label(mut self, tag: &str, prefix: &str) -> Self {
+        self.labels.push((tag.to_string(), prefix.to_string()));
+        self
+    }
+
+    /// Fills in every `ref` of `files` and returns what changed along with the duplicate ids
+    /// followed by the broken references, both in document order.
+    ///
+    /// This is [`CrossReferences::anchors`], [`Anchors::new`] and [`CrossReferences::fill`] in
+    /// one go, keeping the anchors of every file around instead lets a change to one file only
+    /// fill in the files referring to it again.
+    pub fn resolve(&self, files: &mut [File]) -> (Changes, Vec<ReferenceError>) {
+        let anchors = files.iter().flat_map(|file| self.anchors(file));
+        let (anchors, mut errors) = Anchors::new(anchors.collect::<Vec<_>>());
+        let mut changes = Changes::default();
+        for file in files {
+            let (file_changes, file_errors) = self.fill(file, &anchors);
+            changes += file_changes;
+            errors.extend(file_errors);
+        }
+        (changes, errors)
+    }
+
+    /// The ids `file` defines, in document order.
+    pub fn anchors(&self, file: &File) -> Vec<Anchor> {
+        let mut anchors = vec![];
+        let mut numbers = vec![0; self.labels.len()];
+        self.collect(file, &file.document.nodes, &mut numbers, &mut anchors);
+        anchors
+    }
+
+    /// The ids the `ref`s of `file` point to.
+    pub fn targets<'a>(&self, file: &'a File) -> Vec<&'a str> {
+        fn collect<'a>(nodes: &'a [Node], targets: &mut Vec<&'a str>) {
+            for node in nodes {
+                if let Node::Tag(tag) = node {
+                    if let (true, Some(Literal::String(id))) = (tag.name == "ref", tag.attr("to")) {
+                        targets.push(id);
+                    }
+                    collect(&tag.children, targets);
+                }
+            }
+        }
+        let mut targets = vec![];
+        collect(&file.document.nodes, &mut targets);
+        targets
+    }
+
+    /// Fills in the `ref`s of `file` and returns what changed and its broken references.
+    pub fn fill(&self, file: &mut File, anchors: &Anchors) -> (Changes, Vec<ReferenceError>) {
+        let mut changes = Changes::default();
+        let mut errors = vec![];
+        let mut nodes = std::mem::take(&mut file.document.nodes);
+        self.fill_nodes(file, &mut nodes, anchors, &mut changes, &mut errors);
+        file.document.nodes = nodes;
+        (changes, errors)
+    }
+
+    fn collect(&self, file: &File, nodes: &[Node], numbers: &mut [u32], anchors: &mut Vec<Anchor>) {
+        for node in nodes {
+            let Node::Tag(tag) = node else {
+                continue;
+            };
+            let label = self.labels.iter().position(|(name, _)| *name == tag.name);
+            let number = label.map(|label| {
+                numbers[label] += 1;
+                numbers[label]
+            });
+            if let Some(Literal::String(id)) = tag.attr("id") {
+                let text = if self.headings.iter().any(|name| *name == tag.name) {
+                    title(tag)
+                } else if let (Some(label), Some(number)) = (label, number) {
+                    let prefix = &self.labels[label].1;
+                    let number = match tag.attr("number") {
+                        Some(Literal::Number(number)) => *number,
+                        _ => number,
+                    };
+                    format!("{prefix} {number}")
+                } else {
+                    id.to_string()
+                };
+                anchors.push(Anchor {
+                    id: id.to_string(),
+                    path: file.path.to_string(),
+                    text,
+                    at: file.locate(&tag.name),
+                });
+            }
+            self.collect(file, &tag.children, numbers, anchors);
+        }
+    }
+
+    /// Fills in the `ref`s of `nodes`, which were taken out of `file`.
+    fn fill_nodes(
+        &self,
+        file: &File,
+        nodes: &mut [Node],
+        anchors: &Anchors,
+        changes: &mut Changes,
+        errors: &mut Vec<ReferenceError>,
+    ) {
+        for node in nodes {
+            let Node::Tag(tag) = node else {
+                continue;
+            };
+            if tag.name != "ref" {
+                self.fill_nodes(file, &mut tag.children, anchors, changes, errors);
+                continue;
+            }
+            let at = file.locate(&tag.name);
+            let Some(Literal::String(id)) = tag.attr("to") else {
+                errors.push(ReferenceError::MissingTarget { at });
+                continue;
+            };
+            let Some(anchor) = anchors.get(id) else {
+                errors.push(ReferenceError::UnknownId {
+                    at,
+                    id: id.to_string(),
+                });
+                continue;
+            };
+            if tag.children.is_empty() {
+                tag.children.push(Node::String(anchor.text.clone().into()));
+                tag.body = Body::String;
+                changes.replaced += 1;
+            }
+            if anchor.path != file.path {
+                set_path(tag, &anchor.path, changes);
+            }
+        }
+    }
+}
+
+fn set_path(tag: &mut Tag, path: &str, changes: &mut Changes) {
+    let value = Literal::String(path.to_string().into());
+    match tag.attributes.iter_mut().find(|attr| attr.name == "path") {
+        Some(attr) if attr.value == value => {}
+        Some(attr) => {
+            attr.value = value;
+            changes.attributes += 1;
+        }
+        None => {
+            tag.attributes.push(Attribute {
+                name: "path".into(),
+                value,
+            });
+            changes.attributes += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CrossReferences, ReferenceError};
+    use crate::{
+        printer::print,
+        project::{Location, Project},
+    };
+    use pretty_assertions::assert_eq;
+
+    fn at(path: &str, line: usize, column: usize) -> Location {
+        Location {
+            path: path.to_string(),
+            position: Some((line, column)),
+        }
+    }
+
+    #[test]
+    fn resolves_across_files() {
+        let mut project = Project::new();
+        project.add(
+            "guide.mu",
+            r#"h1(id: "intro") { "The " b { "intro" } }
+figure { "a" }
+figure(id: "chart") { "b" }
+p { "See " ref(to: "chart"); ", " ref(to: "api") { "the api" } " and " ref(to: "table"); }
+"#,
+        );
+        project.add(
+            "api.mu",
+            r#"h2(id: "api") { "API" }
+table(id: "table", number: 7) {}
+p { ref(to: "intro"); }
+"#,
+        );
+        let mut files = project.parse().unwrap();
+        let (changes, errors) = CrossReferences::default()
+            .label("table", "Table")
+            .resolve(&mut files);
+        assert_eq!(errors, vec![]);
+        assert_eq!((changes.replaced, changes.attributes), (3, 3));
+        assert_eq!(
+            print(&files[0].document.nodes[3..]),
+            r#"p {
+    "See "
+    ref(to: "chart") "Figure 2"
+    ", "
+    ref(to: "api", path: "api.mu") { "the api" }
+    " and "
+    ref(to: "table", path: "api.mu") "Table 7"
+}
+"#
+        );
+        assert_eq!(
+            print(&files[1].document.nodes[2..]),
+            "p {\n    ref(to: \"intro\", path: \"guide.mu\") \"The intro\"\n}\n"
+        );
+
+        let (changes, _) = CrossReferences::default().resolve(&mut files);
+        assert!(changes.is_empty());
+    }
+
+    #[test]
+    fn reports_broken_references() {
+        let mut project = Project::new();
+        project.add("a.mu", "h1(id: \"x\") {}\nref(to: \"y\");");
+        project.add("b.mu", "p {\n    div(id: \"x\") { ref; }\n}");
+        let mut files = project.parse().unwrap();
+        let (_, errors) = CrossReferences::default().resolve(&mut files);
+        assert_eq!(
+            errors,
+            vec![
+                ReferenceError::DuplicateId {
+                    at: at("b.mu", 2, 5),
+                    id: "x".to_string(),
+                    first: at("a.mu", 1, 1),
+                },
+                ReferenceError::UnknownId {
+                    at: at("a.mu", 2, 1),
+                    id: "y".to_string(),
+                },
+                ReferenceError::MissingTarget {
+                    at: at("b.mu", 2, 20),
+                },
+            ]
+        );
+        assert_eq!(
+            errors[0].to_string(),
+            "b.mu:2:5: Duplicate id `x`, it is already defined at a.mu:1:1"
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use crate::{
+    ast::{Body, Document, Literal, Node, Tag},
+    lexer::Quoted,
+};
+use std::fmt::Write;
+
+const INDENT: &str = "    ";
+
+/// Serialises `nodes` back to markup source.
+///
+/// Tags without children are printed as `name {}`, or `name;` if they were written that way, tags
+/// with a single single-line string child stay on one line, as `name "text"` if they were written
+/// that way, and everything else gets one child per line indented with four spaces. String
+/// contents are printed as they are since their whitespace is significant, as raw strings if they
+/// contain a `"` or a `\`.
+pub fn print(nodes: &[Node]) -> String {
+    let mut out = String::new();
+    for node in nodes {
+        print_node(&mut out, node, 0);
+        out.push('\n');
+    }
+    out
+}
+
+/// Serialises a document, its `meta` tag first, the same way [`print`] does.
+pub fn print_document(document: &Document) -> String {
+    let mut out = String::new();
+    if let Some(meta) = &document.meta {
+        out.push_str(&print_tag(meta));
+    }
+    out.push_str(&print(&document.nodes));
+    out
+}
+
+/// Serialises a single tag the same way [`print`] does.
+pub fn print_tag(tag: &Tag) -> String {
+    let mut out = String::new();
+    print_tag_into(&mut out, tag, 0);
+    out.push('\n');
+    out
+}
+
+fn print_node(out: &mut String, node: &Node, depth: usize) {
+    match node {
+        Node::String(s) => write!(out, "{}", Quoted(s)).expect("writing to a string can't fail"),
+        Node::Tag(tag) => print_tag_into(out, tag, depth),
+    }
+}
+
+fn print_tag_into(out: &mut String, tag: &Tag, depth: usize) {
+    out.push_str(&tag.name);
+    if !tag.attributes.is_empty() {
+        out.push('(');
+        for (i, attr) in tag.attributes.iter().enumerate() {
+            if i != 0 {
+                out.push_str(", ");
+            }
+            match attr.value {
+                Literal::Flag => out.push_str(&attr.name),
+                _ => write!(out, "{}: {}", attr.name, attr.value)
+                    .expect("writing to a string can't fail"),
+            }
+        }
+        out.push(')');
+    }
+    //A pass could have given children to a `hr;`, which then needs braces.
+    match (tag.children.as_slice(), tag.body) {
+        ([], Body::None) => out.push(';'),
+        ([Node::String(s)], Body::String) => {
+            write!(out, " {}", Quoted(s)).expect("writing to a string can't fail")
+        }
+        ([], _) => out.push_str(" {}"),
+        ([Node::String(s)], _) if !s.contains('\n') => {
+            write!(out, " {{ {} }}", Quoted(s)).expect("writing to a string can't fail")
+        }
+        (children, _) => {
+            out.push_str(" {\n");
+            for child in children {
+                out.push_str(&INDENT.repeat(depth + 1));
+                print_node(out, child, depth + 1);
+                out.push('\n');
+            }
+            out.push_str(&INDENT.repeat(depth));
+            out.push('}');
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::print;
+    use crate::parser::Parser;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn round_trip() {
+        let source = r##"code-block(highlights: [1, 3..5, 7..], lang: "ts", theme: dark, wrap: false, numbered) {
+    "
+        const a = 1;
+    "
+    p { "a js snippet" }
+}
+image(size: {w: 640, h: 480}, crops: [{}, {at: [1, 2..4], fit: cover}]) {}
+hr {}
+br;
+image(src: "a.png");
+p "a multiline
+shorthand"
+code(lang: r"C:\") { r#"{"a": "b"}"# }
+"##;
+        let ast = Parser::new().parse(source.as_bytes()).unwrap();
+        assert_eq!(print(&ast), source);
+    }
+
+    #[test]
+    fn nested_tags_are_indented() {
+        let source = r#"div{p{"first"}"second" ul{li{}}}"#;
+        let ast = Parser::new().parse(source.as_bytes()).unwrap();
+        assert_eq!(
+            print(&ast),
+            r#"div {
+    p { "first" }
+    "second"
+    ul {
+        li {}
+    }
+}
+"#
+        );
+    }
+}
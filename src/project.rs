@@ -0,0 +1,144 @@
+use crate::{ast::Document, parser::Parser};
+use std::{fmt::Display, path::Path};
+use thiserror::Error;
+
+/// A set of documents that are processed together, e.g. so references can point from one file
+/// into another.
+///
+/// The project owns the sources, [`Project::parse`] borrows them so every node of a [`File`] can
+/// still be traced back to its file, line and column.
+///
+/// ```
+/// use markup::project::Project;
+///
+/// let mut project = Project::new();
+/// project.add("a.mu", r#"h1(id: "a") { "A" }"#);
+/// project.add("b.mu", "p {");
+/// assert_eq!(
+///     project.parse().unwrap_err().to_string(),
+///     "b.mu:1:4: Unexpected token at 3. Expected an identifier or a string literal"
+/// );
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct Project {
+    files: Vec<(String, String)>,
+}
+
+/// A parsed file of a [`Project`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct File<'a> {
+    pub path: &'a str,
+    pub source: &'a str,
+    pub document: Document<'a>,
+}
+
+/// Where something is in a [`Project`], printed as `path:line:column`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub path: String,
+    /// The line and column, both starting at 1, `None` for nodes a pass created.
+    pub position: Option<(usize, usize)>,
+}
+
+#[derive(Debug, Error)]
+pub enum ProjectError {
+    #[error("{}: {}", .path, .source)]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("{}: {}", .at, .message)]
+    Parse { at: Location, message: String },
+}
+
+impl Project {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a file that is already in memory.
+    pub fn add(&mut self, path: impl Into<String>, source: impl Into<String>) {
+        self.files.push((path.into(), source.into()));
+    }
+
+    /// Reads the file at `path` and adds it.
+    pub fn load(&mut self, path: impl AsRef<Path>) -> Result<(), ProjectError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).map_err(|source| ProjectError::Io {
+            path: path.display().to_string(),
+            source,
+        })?;
+        self.add(path.display().to_string(), source);
+        Ok(())
+    }
+
+    /// The paths of the files in the order they were added.
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        self.files.iter().map(|(path, _)| path.as_str())
+    }
+
+    /// Parses every file, in the order they were added, stopping at the first syntax error.
+    pub fn parse(&self) -> Result<Vec<File<'_>>, ProjectError> {
+        self.files
+            .iter()
+            .map(|(path, source)| {
+                let document =
+                    Parser::new()
+                        .parse_document(source.as_bytes())
+                        .map_err(|error| ProjectError::Parse {
+                            at: Location {
+                                path: path.clone(),
+                                position: Some(line_column(source, error.position())),
+                            },
+                            message: error.to_string(),
+                        })?;
+                Ok(File {
+                    path,
+                    source,
+                    document,
+                })
+            })
+            .collect()
+    }
+}
+
+impl File<'_> {
+    /// The location of `s`, which should be borrowed from the file, like a tag name.
+    pub fn locate(&self, s: &str) -> Location {
+        Location {
+            path: self.path.to_string(),
+            position: offset_of(self.source, s).map(|offset| line_column(self.source, offset)),
+        }
+    }
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.position {
+            Some((line, column)) => write!(f, "{}:{}:{}", self.path, line, column),
+            None => write!(f, "{}", self.path),
+        }
+    }
+}
+
+/// The line and column, both starting at 1, of the byte `offset` of `source`.
+pub(crate) fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source.as_bytes()[..offset.min(source.len())];
+    let line_start = before
+        .iter()
+        .rposition(|b| *b == b'\n')
+        .map_or(0, |i| i + 1);
+    let line = before.iter().filter(|b| **b == b'\n').count() + 1;
+    let column = String::from_utf8_lossy(&before[line_start..])
+        .chars()
+        .count()
+        + 1;
+    (line, column)
+}
+
+/// The offset of `s` in `source` if it's borrowed from it, strings a pass created have none.
+pub(crate) fn offset_of(source: &str, s: &str) -> Option<usize> {
+    let start = source.as_ptr() as usize;
+    let offset = (s.as_ptr() as usize).wrapping_sub(start);
+    (offset <= source.len()).then_some(offset)
+}
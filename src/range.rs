@@ -0,0 +1,284 @@
+use crate::ast::Literal;
+use serde::{
+    de::{self, MapAccess, Visitor},
+    Deserialize, Deserializer,
+};
+use std::{fmt, ops::RangeInclusive};
+use thiserror::Error;
+
+/// A sorted set of numbers stored as merged, non-adjacent intervals, e.g. the lines a
+/// `code-block` highlights.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LineSet {
+    intervals: Vec<RangeInclusive<u32>>,
+}
+
+/// An item of a list like `highlights`, see [`Tag::get`](crate::ast::Tag::get).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeOrNumber {
+    Number(u32),
+    Range {
+        start: u32,
+        end: Option<u32>,
+        inclusive: bool,
+        step: Option<u32>,
+    },
+}
+
+impl From<RangeOrNumber> for Literal<'static> {
+    fn from(item: RangeOrNumber) -> Self {
+        match item {
+            RangeOrNumber::Number(n) => Literal::Number(n),
+            RangeOrNumber::Range {
+                start,
+                end,
+                inclusive,
+                step,
+            } => Literal::Range {
+                start,
+                end,
+                inclusive,
+                step,
+            },
+        }
+    }
+}
+
+/// Deserializes from a number or a `start`, `end`, `step` map with an exclusive `end`, which is
+/// what [`from_attributes`](crate::from_attributes) turns range literals into.
+impl<'de> Deserialize<'de> for RangeOrNumber {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(RangeOrNumberVisitor)
+    }
+}
+
+struct RangeOrNumberVisitor;
+
+impl<'de> Visitor<'de> for RangeOrNumberVisitor {
+    type Value = RangeOrNumber;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a number or a range")
+    }
+
+    fn visit_u64<E: de::Error>(self, n: u64) -> Result<Self::Value, E> {
+        u32::try_from(n)
+            .map(RangeOrNumber::Number)
+            .map_err(|_| E::invalid_value(de::Unexpected::Unsigned(n), &self))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        const FIELDS: &[&str] = &["start", "end", "step"];
+        let (mut start, mut end, mut step) = (None, None, None);
+        while let Some(key) = map.next_key::<String>()? {
+            let field = match key.as_str() {
+                "start" => &mut start,
+                "end" => &mut end,
+                "step" => &mut step,
+                _ => return Err(de::Error::unknown_field(&key, FIELDS)),
+            };
+            *field = Some(map.next_value::<u32>()?);
+        }
+        Ok(RangeOrNumber::Range {
+            start: start.ok_or_else(|| de::Error::missing_field("start"))?,
+            end,
+            inclusive: false,
+            step,
+        })
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq, Clone)]
+pub enum RangeError {
+    #[error("Expected a number or a range, got {}", .0)]
+    UnexpectedLiteral(String),
+    #[error("Reversed range {}..{}, the start of a range can't be after its end", .start, .end)]
+    ReversedRange { start: u32, end: u32 },
+    #[error("Range step must be greater than zero")]
+    ZeroStep,
+}
+
+impl LineSet {
+    /// Resolves a number, a range or a list of them against `bound`, the largest number the set
+    /// can contain.
+    ///
+    /// Open ranges like `5..` run until `bound` and numbers above it are dropped, so
+    /// `[1, 3..=5, 8..]` resolved against `9` is `1, 3, 4, 5, 8, 9`. The parser already rejects
+    /// reversed ranges and zero steps but they are checked again for hand built literals.
+    pub fn resolve(literal: &Literal, bound: u32) -> Result<Self, RangeError> {
+        let mut intervals = vec![];
+        collect(literal, bound, &mut intervals)?;
+        Ok(Self::from_intervals(intervals))
+    }
+
+    /// Resolves already converted numbers and ranges the same way [`LineSet::resolve`] does.
+    pub fn from_items(items: &[RangeOrNumber], bound: u32) -> Result<Self, RangeError> {
+        let mut intervals = vec![];
+        for item in items {
+            collect(&Literal::from(*item), bound, &mut intervals)?;
+        }
+        Ok(Self::from_intervals(intervals))
+    }
+
+    /// Sorts and merges the given intervals, empty ones are dropped.
+    pub fn from_intervals(intervals: impl IntoIterator<Item = RangeInclusive<u32>>) -> Self {
+        let mut intervals: Vec<_> = intervals
+            .into_iter()
+            .filter(|interval| !interval.is_empty())
+            .collect();
+        intervals.sort_by_key(|interval| *interval.start());
+        let mut merged: Vec<RangeInclusive<u32>> = Vec::with_capacity(intervals.len());
+        for interval in intervals {
+            match merged.last_mut() {
+                Some(last) if *interval.start() <= last.end().saturating_add(1) => {
+                    *last = *last.start()..=*last.end().max(interval.end());
+                }
+                _ => merged.push(interval),
+            }
+        }
+        Self { intervals: merged }
+    }
+
+    pub fn contains(&self, n: u32) -> bool {
+        let i = self
+            .intervals
+            .partition_point(|interval| *interval.end() < n);
+        self.intervals
+            .get(i)
+            .is_some_and(|interval| interval.contains(&n))
+    }
+
+    pub fn intervals(&self) -> &[RangeInclusive<u32>] {
+        &self.intervals
+    }
+
+    /// Every number of the set in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = u32> + '_ {
+        self.intervals.iter().cloned().flatten()
+    }
+
+    pub fn len(&self) -> usize {
+        self.intervals
+            .iter()
+            .map(|interval| (interval.end() - interval.start()) as usize + 1)
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+}
+
+fn collect(
+    literal: &Literal,
+    bound: u32,
+    intervals: &mut Vec<RangeInclusive<u32>>,
+) -> Result<(), RangeError> {
+    match literal {
+        Literal::Number(n) => {
+            if *n <= bound {
+                intervals.push(*n..=*n);
+            }
+        }
+        Literal::Range {
+            start,
+            end,
+            inclusive,
+            step,
+        } => {
+            let last = match (end, inclusive) {
+                (Some(end), _) if start > end => {
+                    return Err(RangeError::ReversedRange {
+                        start: *start,
+                        end: *end,
+                    })
+                }
+                (Some(end), true) => *end,
+                //`start..start` is empty.
+                (Some(end), false) if end == start => return Ok(()),
+                (Some(end), false) => end - 1,
+                (None, _) => bound,
+            }
+            .min(bound);
+            match step {
+                Some(0) => return Err(RangeError::ZeroStep),
+                Some(step) if *step > 1 => {
+                    intervals.extend((*start..=last).step_by(*step as usize).map(|n| n..=n))
+                }
+                _ => intervals.push(*start..=last),
+            }
+        }
+        Literal::List(items) => {
+            for item in items {
+                collect(item, bound, intervals)?;
+            }
+        }
+        literal => return Err(RangeError::UnexpectedLiteral(literal.to_string())),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LineSet, RangeError};
+    use crate::{ast::Literal, parser::Parser};
+    use pretty_assertions::assert_eq;
+
+    fn resolve(source: &str, bound: u32) -> Result<Vec<u32>, RangeError> {
+        let source = format!("code-block(highlights: {source}) {{}}");
+        let ast = Parser::new().parse(source.as_bytes()).unwrap();
+        let crate::ast::Node::Tag(tag) = &ast[0] else {
+            unreachable!()
+        };
+        LineSet::resolve(&tag.attributes[0].value, bound).map(|set| set.iter().collect())
+    }
+
+    #[test]
+    fn exclusive_and_inclusive() {
+        assert_eq!(resolve("[1, 3..5]", 10), Ok(vec![1, 3, 4]));
+        assert_eq!(resolve("[1, 3..=5]", 10), Ok(vec![1, 3, 4, 5]));
+        assert_eq!(resolve("3..3", 10), Ok(vec![]));
+    }
+
+    #[test]
+    fn open_ranges_and_bound() {
+        assert_eq!(resolve("[1, 8.., 20]", 9), Ok(vec![1, 8, 9]));
+        assert_eq!(resolve("5..=100", 7), Ok(vec![5, 6, 7]));
+    }
+
+    #[test]
+    fn steps() {
+        assert_eq!(resolve("1..10 by 3", 100), Ok(vec![1, 4, 7]));
+        assert_eq!(resolve("[2.. by 2, 3]", 8), Ok(vec![2, 3, 4, 6, 8]));
+    }
+
+    #[test]
+    fn merging() {
+        let set = LineSet::from_intervals([5..=7, 1..=2, 3..=3, 6..=9]);
+        assert_eq!(set.intervals(), &[1..=3, 5..=9]);
+        assert_eq!(set.len(), 8);
+        assert!(set.contains(6));
+        assert!(!set.contains(4));
+        assert!(!set.contains(10));
+    }
+
+    #[test]
+    fn invalid_literals() {
+        assert_eq!(
+            resolve(r#"[1, "two"]"#, 10),
+            Err(RangeError::UnexpectedLiteral("\"two\"".to_string()))
+        );
+        assert_eq!(
+            LineSet::resolve(
+                &Literal::Range {
+                    start: 5,
+                    end: Some(3),
+                    inclusive: false,
+                    step: None
+                },
+                10
+            ),
+            Err(RangeError::ReversedRange { start: 5, end: 3 })
+        );
+    }
+}
@@ -0,0 +1,439 @@
+use crate::ast::{Literal, Node, Tag};
+use thiserror::Error;
+
+/// A CSS-like selector over [`Tag`]s.
+///
+/// ```text
+/// selectors = selector (',' selector)*;
+/// selector  = compound (('>' | ' ') compound)*;
+/// compound  = (IDENTIFIER | '*')? (attribute | not)*;
+/// attribute = '[' IDENTIFIER ('=' value)? ']';
+/// not       = ':not(' compound ')';
+/// ```
+///
+/// Attribute values can be quoted (`[lang="ts"]`) or bare (`[width=640]`). String literals are
+/// compared by their content, every other literal by its markup representation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Selector {
+    alternatives: Vec<Complex>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Complex {
+    compounds: Vec<Compound>,
+    //`combinators[i]` connects `compounds[i]` and `compounds[i + 1]`.
+    combinators: Vec<Combinator>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Combinator {
+    Descendant,
+    Child,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+struct Compound {
+    name: Option<String>,
+    conditions: Vec<Condition>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Condition {
+    Has(String),
+    Equals(String, String),
+    Not(Compound),
+}
+
+#[derive(Debug, Error, PartialEq, Eq, Clone)]
+pub enum SelectorError {
+    #[error("Empty selector")]
+    Empty,
+    #[error("Unexpected character {} at position {}", .character, .position)]
+    UnexpectedCharacter { character: char, position: usize },
+    #[error("Unexpected end of selector, expected {}", .expected)]
+    UnexpectedEnd { expected: &'static str },
+}
+
+impl Selector {
+    pub fn parse(source: &str) -> Result<Self, SelectorError> {
+        let mut parser = SelectorParser {
+            chars: source.char_indices().collect(),
+            current: 0,
+        };
+        let mut alternatives = vec![parser.complex()?];
+        while parser.match_char(',') {
+            alternatives.push(parser.complex()?);
+        }
+        parser.skip_whitespace();
+        match parser.peek() {
+            None => Ok(Self { alternatives }),
+            Some(character) => Err(SelectorError::UnexpectedCharacter {
+                character,
+                position: parser.position(),
+            }),
+        }
+    }
+
+    /// Returns every tag in `nodes` matching the selector in document order.
+    pub fn select<'n, 'a>(&self, nodes: &'n [Node<'a>]) -> Vec<&'n Tag<'a>> {
+        let mut matches = vec![];
+        let mut ancestors = vec![];
+        self.collect(nodes, &mut ancestors, &mut matches);
+        matches
+    }
+
+    /// Returns whether `tag` matches the selector, `ancestors` starts from the root of the document.
+    pub fn matches(&self, tag: &Tag, ancestors: &[&Tag]) -> bool {
+        self.alternatives
+            .iter()
+            .any(|complex| complex.matches(tag, ancestors))
+    }
+
+    fn collect<'n, 'a>(
+        &self,
+        nodes: &'n [Node<'a>],
+        ancestors: &mut Vec<&'n Tag<'a>>,
+        matches: &mut Vec<&'n Tag<'a>>,
+    ) {
+        for node in nodes {
+            if let Node::Tag(tag) = node {
+                if self.matches(tag, ancestors) {
+                    matches.push(tag);
+                }
+                ancestors.push(tag);
+                self.collect(&tag.children, ancestors, matches);
+                ancestors.pop();
+            }
+        }
+    }
+}
+
+impl Complex {
+    fn matches(&self, tag: &Tag, ancestors: &[&Tag]) -> bool {
+        //Matching goes from right to left, the right-most compound has to match the tag itself.
+        let Some((last, compounds)) = self.compounds.split_last() else {
+            return false;
+        };
+        last.matches(tag) && Self::matches_ancestors(compounds, &self.combinators, ancestors)
+    }
+
+    fn matches_ancestors(
+        compounds: &[Compound],
+        combinators: &[Combinator],
+        ancestors: &[&Tag],
+    ) -> bool {
+        let (Some((compound, compounds)), Some((combinator, combinators))) =
+            (compounds.split_last(), combinators.split_last())
+        else {
+            return true;
+        };
+        match combinator {
+            Combinator::Child => match ancestors.split_last() {
+                Some((parent, ancestors)) => {
+                    compound.matches(parent)
+                        && Self::matches_ancestors(compounds, combinators, ancestors)
+                }
+                None => false,
+            },
+            Combinator::Descendant => (0..ancestors.len()).rev().any(|i| {
+                compound.matches(ancestors[i])
+                    && Self::matches_ancestors(compounds, combinators, &ancestors[..i])
+            }),
+        }
+    }
+}
+
+impl Compound {
+    fn matches(&self, tag: &Tag) -> bool {
+        if let Some(name) = &self.name {
+            if *name != tag.name {
+                return false;
+            }
+        }
+        self.conditions
+            .iter()
+            .all(|condition| condition.matches(tag))
+    }
+}
+
+impl Condition {
+    fn matches(&self, tag: &Tag) -> bool {
+        match self {
+            Condition::Has(name) => tag.attributes.iter().any(|attr| attr.name == name.as_str()),
+            Condition::Equals(name, value) => tag.attributes.iter().any(|attr| {
+                attr.name == name.as_str()
+                    && match &attr.value {
+                        Literal::String(s) => s == value,
+                        literal => &literal.to_string() == value,
+                    }
+            }),
+            Condition::Not(compound) => !compound.matches(tag),
+        }
+    }
+}
+
+struct SelectorParser {
+    chars: Vec<(usize, char)>,
+    current: usize,
+}
+
+impl SelectorParser {
+    fn complex(&mut self) -> Result<Complex, SelectorError> {
+        self.skip_whitespace();
+        let mut compounds = vec![self.compound()?];
+        let mut combinators = vec![];
+        loop {
+            let had_whitespace = self.skip_whitespace();
+            let combinator = match self.peek() {
+                Some('>') => {
+                    self.current += 1;
+                    self.skip_whitespace();
+                    Combinator::Child
+                }
+                Some(',') | None => break,
+                Some(_) if had_whitespace => Combinator::Descendant,
+                Some(character) => {
+                    return Err(SelectorError::UnexpectedCharacter {
+                        character,
+                        position: self.position(),
+                    })
+                }
+            };
+            combinators.push(combinator);
+            compounds.push(self.compound()?);
+        }
+        Ok(Complex {
+            compounds,
+            combinators,
+        })
+    }
+
+    fn compound(&mut self) -> Result<Compound, SelectorError> {
+        let mut compound = Compound::default();
+        let universal = self.match_char('*');
+        if !universal && self.peek().is_some_and(is_name_char) {
+            compound.name = Some(self.name());
+        }
+        loop {
+            match self.peek() {
+                Some('[') => {
+                    self.current += 1;
+                    compound.conditions.push(self.attribute()?);
+                }
+                Some(':') => {
+                    self.current += 1;
+                    self.expect_word("not(")?;
+                    self.skip_whitespace();
+                    let inner = self.compound()?;
+                    self.skip_whitespace();
+                    self.expect(')', "`)`")?;
+                    compound.conditions.push(Condition::Not(inner));
+                }
+                _ => break,
+            }
+        }
+        if !universal && compound == Compound::default() {
+            return match self.peek() {
+                Some(character) => Err(SelectorError::UnexpectedCharacter {
+                    character,
+                    position: self.position(),
+                }),
+                None if self.chars.is_empty() => Err(SelectorError::Empty),
+                None => Err(SelectorError::UnexpectedEnd {
+                    expected: "a tag name",
+                }),
+            };
+        }
+        Ok(compound)
+    }
+
+    fn attribute(&mut self) -> Result<Condition, SelectorError> {
+        self.skip_whitespace();
+        if !self.peek().is_some_and(is_name_char) {
+            return self.unexpected("an attribute name");
+        }
+        let name = self.name();
+        self.skip_whitespace();
+        if self.match_char(']') {
+            return Ok(Condition::Has(name));
+        }
+        self.expect('=', "`=` or `]`")?;
+        self.skip_whitespace();
+        let value = if self.match_char('"') {
+            let mut value = String::new();
+            loop {
+                match self.peek() {
+                    Some('"') => break,
+                    Some(c) => {
+                        value.push(c);
+                        self.current += 1;
+                    }
+                    None => {
+                        return Err(SelectorError::UnexpectedEnd {
+                            expected: "a closing quote",
+                        })
+                    }
+                }
+            }
+            self.current += 1;
+            value
+        } else {
+            let mut value = String::new();
+            while let Some(c) = self.peek().filter(|c| *c != ']' && !c.is_whitespace()) {
+                value.push(c);
+                self.current += 1;
+            }
+            value
+        };
+        self.skip_whitespace();
+        self.expect(']', "`]`")?;
+        Ok(Condition::Equals(name, value))
+    }
+
+    fn name(&mut self) -> String {
+        let mut name = String::new();
+        while let Some(c) = self.peek().filter(|c| is_name_char(*c)) {
+            name.push(c);
+            self.current += 1;
+        }
+        name
+    }
+
+    fn expect(&mut self, c: char, expected: &'static str) -> Result<(), SelectorError> {
+        if self.match_char(c) {
+            Ok(())
+        } else {
+            self.unexpected(expected)
+        }
+    }
+
+    fn expect_word(&mut self, word: &'static str) -> Result<(), SelectorError> {
+        for c in word.chars() {
+            if !self.match_char(c) {
+                return self.unexpected(word);
+            }
+        }
+        Ok(())
+    }
+
+    fn unexpected<T>(&self, expected: &'static str) -> Result<T, SelectorError> {
+        match self.peek() {
+            Some(character) => Err(SelectorError::UnexpectedCharacter {
+                character,
+                position: self.position(),
+            }),
+            None => Err(SelectorError::UnexpectedEnd { expected }),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.current).map(|(_, c)| *c)
+    }
+
+    fn position(&self) -> usize {
+        self.chars.get(self.current).map_or_else(
+            || self.chars.last().map_or(0, |(i, c)| i + c.len_utf8()),
+            |c| c.0,
+        )
+    }
+
+    fn match_char(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.current += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn skip_whitespace(&mut self) -> bool {
+        let start = self.current;
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.current += 1;
+        }
+        self.current != start
+    }
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Selector, SelectorError};
+    use crate::parser::Parser;
+    use pretty_assertions::assert_eq;
+
+    const SOURCE: &str = r#"
+section {
+    code-block(lang: "ts", highlights: [1, 3..5]) { "let a = 1;" }
+    div {
+        code-block { "echo hi" }
+        p(width: 640) { "text" }
+    }
+}
+code-block(lang: "rs") { "let a = 1;" }
+"#;
+
+    fn names(selector: &str) -> Vec<String> {
+        let ast = Parser::new().parse(SOURCE.as_bytes()).unwrap();
+        Selector::parse(selector)
+            .unwrap()
+            .select(&ast)
+            .into_iter()
+            .map(|tag| match tag.attributes.first() {
+                Some(attr) => format!("{}({})", tag.name, attr.value),
+                None => tag.name.to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn type_selector() {
+        assert_eq!(
+            names("code-block"),
+            vec![r#"code-block("ts")"#, "code-block", r#"code-block("rs")"#]
+        );
+    }
+
+    #[test]
+    fn attribute_selectors() {
+        assert_eq!(names(r#"[lang="rs"]"#), vec![r#"code-block("rs")"#]);
+        assert_eq!(names("[width=640]"), vec!["p(640)"]);
+        assert_eq!(names("code-block:not([lang])"), vec!["code-block"]);
+    }
+
+    #[test]
+    fn combinators() {
+        assert_eq!(
+            names("section code-block"),
+            vec![r#"code-block("ts")"#, "code-block"]
+        );
+        assert_eq!(names("section > code-block"), vec![r#"code-block("ts")"#]);
+        assert_eq!(names("section > * > p"), vec!["p(640)"]);
+    }
+
+    #[test]
+    fn selector_list() {
+        assert_eq!(names("p, div"), vec!["div", "p(640)"]);
+    }
+
+    #[test]
+    fn invalid_selectors() {
+        assert_eq!(Selector::parse(""), Err(SelectorError::Empty));
+        assert_eq!(
+            Selector::parse("p[lang"),
+            Err(SelectorError::UnexpectedEnd {
+                expected: "`=` or `]`"
+            })
+        );
+        assert_eq!(
+            Selector::parse("p > )"),
+            Err(SelectorError::UnexpectedCharacter {
+                character: ')',
+                position: 4
+            })
+        );
+    }
+}
@@ -0,0 +1,350 @@
+use serde::Serialize;
+use std::ops::Range;
+
+/// What a piece of markup source is, for editors and documentation sites to highlight it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TokenKind {
+    TagName,
+    AttributeName,
+    String,
+    Number,
+    /// `true` and `false`.
+    Boolean,
+    /// A bare identifier used as a value, like the `ts` of `lang: ts`.
+    EnumMember,
+    /// The `..`, `..=` and `by` of a range.
+    RangeOperator,
+    Punctuation,
+    Comment,
+    /// Characters that can't appear in markup at all.
+    Invalid,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SemanticToken {
+    pub kind: TokenKind,
+    /// The byte range of the token in the source.
+    pub range: Range<usize>,
+}
+
+/// Classifies every token of `source`, skipping whitespace.
+///
+/// Unlike [`Lexer`](crate::lexer::Lexer) this never fails so it can be used while a document is
+/// being edited: an unclosed string runs until the end of the source and unknown characters are
+/// classified as [`TokenKind::Invalid`]. Identifiers are tag names unless they are inside the
+/// parentheses of an attribute list, where they are attribute names and map keys or, after a `:`
+/// or inside a list, values.
+pub fn classify(source: &str) -> Vec<SemanticToken> {
+    let mut tokens = vec![];
+    let mut chars = source.char_indices().peekable();
+    //The `(`, `[` and `{` of the attribute list being classified.
+    let mut nesting: Vec<char> = vec![];
+    while let Some((start, c)) = chars.next() {
+        let mut end = start + c.len_utf8();
+        let kind = match c {
+            _ if c.is_whitespace() => continue,
+            '/' if source[end..].starts_with('/') => {
+                end = source[start..]
+                    .find('\n')
+                    .map_or(source.len(), |i| start + i);
+                while chars.next_if(|(i, _)| *i < end).is_some() {}
+                TokenKind::Comment
+            }
+            '"' => {
+                end = string_end(source, end);
+                while chars.next_if(|(i, _)| *i < end).is_some() {}
+                TokenKind::String
+            }
+            'r' if raw_string_end(source, start).is_some() => {
+                end = raw_string_end(source, start).expect("checked by the guard");
+                while chars.next_if(|(i, _)| *i < end).is_some() {}
+                TokenKind::String
+            }
+            '.' if source[end..].starts_with('.') => {
+                chars.next();
+                end += 1;
+                if chars.next_if(|(_, c)| *c == '=').is_some() {
+                    end += 1;
+                }
+                TokenKind::RangeOperator
+            }
+            '(' => {
+                nesting.push(c);
+                TokenKind::Punctuation
+            }
+            //Closes any list or map left open too.
+            ')' => {
+                nesting.clear();
+                TokenKind::Punctuation
+            }
+            '[' | '{' if !nesting.is_empty() && is_value(source, &tokens, &nesting) => {
+                nesting.push(c);
+                TokenKind::Punctuation
+            }
+            ']' | '}' if nesting.last() == Some(&if c == ']' { '[' } else { '{' }) => {
+                nesting.pop();
+                TokenKind::Punctuation
+            }
+            //A tag body can't start inside an attribute list, so the list was never closed.
+            '{' | '}' => {
+                nesting.clear();
+                TokenKind::Punctuation
+            }
+            '[' | ']' => TokenKind::Punctuation,
+            ',' | ':' | '.' | ';' => TokenKind::Punctuation,
+            _ if c.is_numeric() => {
+                while let Some((i, c)) = chars.next_if(|(_, c)| c.is_numeric()) {
+                    end = i + c.len_utf8();
+                }
+                TokenKind::Number
+            }
+            _ if c.is_alphabetic() => {
+                while let Some((i, c)) = chars.next_if(|(_, c)| is_identifier_char(*c)) {
+                    end = i + c.len_utf8();
+                }
+                let after_range = tokens.last().is_some_and(|token: &SemanticToken| {
+                    matches!(token.kind, TokenKind::Number | TokenKind::RangeOperator)
+                });
+                let is_value = !nesting.is_empty() && is_value(source, &tokens, &nesting);
+                if &source[start..end] == "by" && after_range {
+                    TokenKind::RangeOperator
+                } else if is_value && matches!(&source[start..end], "true" | "false") {
+                    TokenKind::Boolean
+                } else if is_value {
+                    TokenKind::EnumMember
+                } else if !nesting.is_empty() {
+                    TokenKind::AttributeName
+                } else {
+                    TokenKind::TagName
+                }
+            }
+            _ => TokenKind::Invalid,
+        };
+        tokens.push(SemanticToken {
+            kind,
+            range: start..end,
+        });
+    }
+    tokens
+}
+
+/// Whether `source` has `//` comments, which aren't part of the [`ast`](crate::ast) so printing a
+/// parsed document drops them.
+pub fn has_comments(source: &str) -> bool {
+    classify(source)
+        .iter()
+        .any(|token| token.kind == TokenKind::Comment)
+}
+
+/// The end of the string whose content starts at `start`, skipping escaped quotes.
+fn string_end(source: &str, start: usize) -> usize {
+    let mut escaped = false;
+    for (i, c) in source[start..].char_indices() {
+        match c {
+            '"' if !escaped => return start + i + 1,
+            '\\' => escaped = !escaped,
+            _ => escaped = false,
+        }
+    }
+    source.len()
+}
+
+/// The end of the raw string starting with the `r` at `start`, if there is one.
+fn raw_string_end(source: &str, start: usize) -> Option<usize> {
+    let rest = &source[start + 1..];
+    let hashes = rest.len() - rest.trim_start_matches('#').len();
+    let content = rest[hashes..].strip_prefix('"')?;
+    let content_start = source.len() - content.len();
+    let terminator = format!("\"{}", "#".repeat(hashes));
+    Some(
+        content
+            .find(&terminator)
+            .map_or(source.len(), |i| content_start + i + terminator.len()),
+    )
+}
+
+/// Whether the next token inside an attribute list is a value rather than a name or a key.
+fn is_value(source: &str, tokens: &[SemanticToken], nesting: &[char]) -> bool {
+    let after_colon = tokens
+        .last()
+        .is_some_and(|token| &source[token.range.clone()] == ":");
+    after_colon || nesting.last() == Some(&'[')
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{classify, has_comments, TokenKind};
+    use pretty_assertions::assert_eq;
+
+    fn kinds(source: &str) -> Vec<(TokenKind, &str)> {
+        classify(source)
+            .into_iter()
+            .map(|token| (token.kind, &source[token.range]))
+            .collect()
+    }
+
+    #[test]
+    fn tags_and_attributes() {
+        assert_eq!(
+            kinds(r#"code-block(highlights: [1, 3..=5 by 2]) { "x" } // done"#),
+            vec![
+                (TokenKind::TagName, "code-block"),
+                (TokenKind::Punctuation, "("),
+                (TokenKind::AttributeName, "highlights"),
+                (TokenKind::Punctuation, ":"),
+                (TokenKind::Punctuation, "["),
+                (TokenKind::Number, "1"),
+                (TokenKind::Punctuation, ","),
+                (TokenKind::Number, "3"),
+                (TokenKind::RangeOperator, "..="),
+                (TokenKind::Number, "5"),
+                (TokenKind::RangeOperator, "by"),
+                (TokenKind::Number, "2"),
+                (TokenKind::Punctuation, "]"),
+                (TokenKind::Punctuation, ")"),
+                (TokenKind::Punctuation, "{"),
+                (TokenKind::String, "\"x\""),
+                (TokenKind::Punctuation, "}"),
+                (TokenKind::Comment, "// done"),
+            ]
+        );
+    }
+
+    #[test]
+    fn values() {
+        assert_eq!(
+            kinds("details(open, lang: ts, hidden: false, langs: [rs, true]) {}"),
+            vec![
+                (TokenKind::TagName, "details"),
+                (TokenKind::Punctuation, "("),
+                (TokenKind::AttributeName, "open"),
+                (TokenKind::Punctuation, ","),
+                (TokenKind::AttributeName, "lang"),
+                (TokenKind::Punctuation, ":"),
+                (TokenKind::EnumMember, "ts"),
+                (TokenKind::Punctuation, ","),
+                (TokenKind::AttributeName, "hidden"),
+                (TokenKind::Punctuation, ":"),
+                (TokenKind::Boolean, "false"),
+                (TokenKind::Punctuation, ","),
+                (TokenKind::AttributeName, "langs"),
+                (TokenKind::Punctuation, ":"),
+                (TokenKind::Punctuation, "["),
+                (TokenKind::EnumMember, "rs"),
+                (TokenKind::Punctuation, ","),
+                (TokenKind::Boolean, "true"),
+                (TokenKind::Punctuation, "]"),
+                (TokenKind::Punctuation, ")"),
+                (TokenKind::Punctuation, "{"),
+                (TokenKind::Punctuation, "}"),
+            ]
+        );
+    }
+
+    #[test]
+    fn maps() {
+        assert_eq!(
+            kinds("image(size: {w: 1, fit: cover}, tags: [{a: b}]) {}"),
+            vec![
+                (TokenKind::TagName, "image"),
+                (TokenKind::Punctuation, "("),
+                (TokenKind::AttributeName, "size"),
+                (TokenKind::Punctuation, ":"),
+                (TokenKind::Punctuation, "{"),
+                (TokenKind::AttributeName, "w"),
+                (TokenKind::Punctuation, ":"),
+                (TokenKind::Number, "1"),
+                (TokenKind::Punctuation, ","),
+                (TokenKind::AttributeName, "fit"),
+                (TokenKind::Punctuation, ":"),
+                (TokenKind::EnumMember, "cover"),
+                (TokenKind::Punctuation, "}"),
+                (TokenKind::Punctuation, ","),
+                (TokenKind::AttributeName, "tags"),
+                (TokenKind::Punctuation, ":"),
+                (TokenKind::Punctuation, "["),
+                (TokenKind::Punctuation, "{"),
+                (TokenKind::AttributeName, "a"),
+                (TokenKind::Punctuation, ":"),
+                (TokenKind::EnumMember, "b"),
+                (TokenKind::Punctuation, "}"),
+                (TokenKind::Punctuation, "]"),
+                (TokenKind::Punctuation, ")"),
+                (TokenKind::Punctuation, "{"),
+                (TokenKind::Punctuation, "}"),
+            ]
+        );
+    }
+
+    #[test]
+    fn bodyless_tags() {
+        assert_eq!(
+            kinds(r#"hr; p "x""#),
+            vec![
+                (TokenKind::TagName, "hr"),
+                (TokenKind::Punctuation, ";"),
+                (TokenKind::TagName, "p"),
+                (TokenKind::String, "\"x\""),
+            ]
+        );
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(
+            kinds(r##"code(a: "say \"hi\"") { r#"{"a": 1}"# } row"##),
+            vec![
+                (TokenKind::TagName, "code"),
+                (TokenKind::Punctuation, "("),
+                (TokenKind::AttributeName, "a"),
+                (TokenKind::Punctuation, ":"),
+                (TokenKind::String, r#""say \"hi\"""#),
+                (TokenKind::Punctuation, ")"),
+                (TokenKind::Punctuation, "{"),
+                (TokenKind::String, r##"r#"{"a": 1}"#"##),
+                (TokenKind::Punctuation, "}"),
+                (TokenKind::TagName, "row"),
+            ]
+        );
+    }
+
+    #[test]
+    fn comments() {
+        assert!(has_comments("// keep me\np { \"a\" }"));
+        assert!(!has_comments(
+            r#"a(href: "https://a.b") "// not a comment""#
+        ));
+    }
+
+    #[test]
+    fn broken_input() {
+        assert_eq!(
+            kinds("p(a: % { \"unclosed\n}"),
+            vec![
+                (TokenKind::TagName, "p"),
+                (TokenKind::Punctuation, "("),
+                (TokenKind::AttributeName, "a"),
+                (TokenKind::Punctuation, ":"),
+                (TokenKind::Invalid, "%"),
+                (TokenKind::Punctuation, "{"),
+                (TokenKind::String, "\"unclosed\n}"),
+            ]
+        );
+        assert_eq!(
+            kinds("p( div { \u{e9}t\u{e9} }"),
+            vec![
+                (TokenKind::TagName, "p"),
+                (TokenKind::Punctuation, "("),
+                (TokenKind::AttributeName, "div"),
+                (TokenKind::Punctuation, "{"),
+                (TokenKind::TagName, "\u{e9}t\u{e9}"),
+                (TokenKind::Punctuation, "}"),
+            ]
+        );
+    }
+}
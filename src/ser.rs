@@ -0,0 +1,702 @@
+use crate::{
+    ast::{Attribute, Literal, Node, Tag},
+    printer,
+};
+use serde::{
+    ser::{self, Impossible},
+    Serialize,
+};
+use std::fmt::Display;
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq, Eq, Clone)]
+pub enum Error {
+    #[error("{} can't be represented in markup", .0)]
+    Unsupported(&'static str),
+    #[error("Numbers must be between 0 and {}, got {}", u32::MAX, .0)]
+    NumberOutOfRange(String),
+    #[error("`{}` isn't a valid tag or attribute name", .0)]
+    InvalidName(String),
+    #[error("Only structs, maps and enum variants with fields can be serialized as a document")]
+    ExpectedTag,
+    #[error("{}", .0)]
+    Custom(String),
+}
+
+impl ser::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Self::Custom(msg.to_string())
+    }
+}
+
+/// Serializes `value` as a document holding a single tag.
+///
+/// The value must be a struct, a map or an enum variant with fields. Structs become tags named
+/// after their type in kebab-case at the top of the document and after their field everywhere
+/// else. Their fields map to
+///
+/// * attributes for numbers, strings, booleans, `Range`, `RangeInclusive` and lists of those,
+///   unit enum variants are written as bare identifiers like `lang: rust`,
+/// * child tags for structs and maps,
+/// * repeated child tags for sequences of structs and maps, empty sequences are written as `[]`,
+/// * map literals like `{w: 640, h: 480}` for structs and maps without nested structs inside any
+///   other list, like a list of lists of structs,
+/// * a child tag holding a tag named after the variant for newtype and struct enum variants.
+///
+/// Strings containing `"` or `\` are written as raw strings. `None` fields are left out. Anything
+/// else, like floating point or negative numbers and numbers above `u32::MAX`, is an error instead
+/// of being written in a way [`from_str`](crate::from_str) can't read back, so a value that
+/// serializes deserializes to the same value.
+///
+/// ```
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize, Debug, PartialEq)]
+/// struct Config {
+///     name: String,
+///     server: Server,
+///     users: Vec<User>,
+/// }
+///
+/// #[derive(Serialize, Deserialize, Debug, PartialEq)]
+/// struct Server {
+///     port: u16,
+/// }
+///
+/// #[derive(Serialize, Deserialize, Debug, PartialEq)]
+/// struct User {
+///     name: String,
+///     groups: Vec<String>,
+/// }
+///
+/// let config = Config {
+///     name: "site".to_string(),
+///     server: Server { port: 8080 },
+///     users: vec![User { name: "ada".to_string(), groups: vec!["admin".to_string()] }],
+/// };
+/// let source = markup::to_string(&config).unwrap();
+/// assert_eq!(
+///     source,
+///     r#"config(name: "site") {
+///     server(port: 8080) {}
+///     users(name: "ada", groups: ["admin"]) {}
+/// }
+/// "#
+/// );
+/// assert_eq!(markup::from_str::<Config>(&source).unwrap(), config);
+/// ```
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String, Error> {
+    match value.serialize(ValueSerializer)? {
+        Value::Tag(tag) => Ok(printer::print_tag(&tag)),
+        _ => Err(Error::ExpectedTag),
+    }
+}
+
+/// A serialized value before its parent decides whether it's an attribute or child tags.
+enum Value {
+    Absent,
+    Literal(Literal<'static>),
+    Tag(Tag<'static>),
+    Tags(Vec<Tag<'static>>),
+}
+
+fn is_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+fn check_name(name: &str) -> Result<(), Error> {
+    if is_name(name) {
+        Ok(())
+    } else {
+        Err(Error::InvalidName(name.to_string()))
+    }
+}
+
+fn kebab_case(name: &str) -> String {
+    let mut kebab = String::with_capacity(name.len());
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() && i != 0 {
+            kebab.push('-');
+        }
+        kebab.extend(c.to_lowercase());
+    }
+    kebab
+}
+
+fn number<T: TryInto<u32> + Display + Copy>(n: T) -> Result<Value, Error> {
+    n.try_into()
+        .map(|n| Value::Literal(Literal::Number(n)))
+        .map_err(|_| Error::NumberOutOfRange(n.to_string()))
+}
+
+/// Wraps the tag of a newtype or struct variant in a tag named after its enum.
+fn variant(enum_name: &'static str, variant: &'static str, value: Value) -> Result<Value, Error> {
+    let Value::Tag(mut inner) = value else {
+        return Err(Error::Unsupported(
+            "an enum variant holding a value without fields",
+        ));
+    };
+    check_name(variant)?;
+    inner.name = variant.into();
+    let mut outer = Tag::new(kebab_case(enum_name));
+    outer.children.push(Node::Tag(inner));
+    Ok(Value::Tag(outer))
+}
+
+struct ValueSerializer;
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = Error;
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = SeqSerializer;
+    type SerializeTupleStruct = SeqSerializer;
+    type SerializeTupleVariant = Impossible<Value, Error>;
+    type SerializeMap = TagSerializer;
+    type SerializeStruct = TagSerializer;
+    type SerializeStructVariant = TagSerializer;
+
+    fn serialize_bool(self, b: bool) -> Result<Value, Error> {
+        Ok(Value::Literal(Literal::Bool(b)))
+    }
+
+    fn serialize_i8(self, n: i8) -> Result<Value, Error> {
+        number(n)
+    }
+
+    fn serialize_i16(self, n: i16) -> Result<Value, Error> {
+        number(n)
+    }
+
+    fn serialize_i32(self, n: i32) -> Result<Value, Error> {
+        number(n)
+    }
+
+    fn serialize_i64(self, n: i64) -> Result<Value, Error> {
+        number(n)
+    }
+
+    fn serialize_i128(self, n: i128) -> Result<Value, Error> {
+        number(n)
+    }
+
+    fn serialize_u8(self, n: u8) -> Result<Value, Error> {
+        number(n)
+    }
+
+    fn serialize_u16(self, n: u16) -> Result<Value, Error> {
+        number(n)
+    }
+
+    fn serialize_u32(self, n: u32) -> Result<Value, Error> {
+        number(n)
+    }
+
+    fn serialize_u64(self, n: u64) -> Result<Value, Error> {
+        number(n)
+    }
+
+    fn serialize_u128(self, n: u128) -> Result<Value, Error> {
+        number(n)
+    }
+
+    fn serialize_f32(self, _: f32) -> Result<Value, Error> {
+        Err(Error::Unsupported("a floating point number"))
+    }
+
+    fn serialize_f64(self, _: f64) -> Result<Value, Error> {
+        Err(Error::Unsupported("a floating point number"))
+    }
+
+    fn serialize_char(self, c: char) -> Result<Value, Error> {
+        self.serialize_str(c.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, s: &str) -> Result<Value, Error> {
+        Ok(Value::Literal(Literal::String(s.to_string().into())))
+    }
+
+    fn serialize_bytes(self, bytes: &[u8]) -> Result<Value, Error> {
+        let bytes = bytes.iter().map(|b| Literal::Number(*b as u32)).collect();
+        Ok(Value::Literal(Literal::List(bytes)))
+    }
+
+    fn serialize_none(self) -> Result<Value, Error> {
+        Ok(Value::Absent)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value, Error> {
+        Err(Error::Unsupported("a unit value"))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value, Error> {
+        Err(Error::Unsupported("a unit struct"))
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Value, Error> {
+        if is_name(variant) {
+            Ok(Value::Literal(Literal::Identifier(variant.into())))
+        } else {
+            self.serialize_str(variant)
+        }
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Value, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        _index: u32,
+        variant_name: &'static str,
+        value: &T,
+    ) -> Result<Value, Error> {
+        variant(name, variant_name, value.serialize(self)?)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqSerializer, Error> {
+        Ok(SeqSerializer {
+            items: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Err(Error::Unsupported("a tuple variant"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<TagSerializer, Error> {
+        Ok(TagSerializer::new("map".to_string()))
+    }
+
+    fn serialize_struct(self, name: &'static str, _len: usize) -> Result<TagSerializer, Error> {
+        let mut serializer = TagSerializer::new(kebab_case(name));
+        serializer.range = match name {
+            "Range" => Some(false),
+            "RangeInclusive" => Some(true),
+            _ => None,
+        };
+        Ok(serializer)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<TagSerializer, Error> {
+        let mut serializer = TagSerializer::new(variant.to_string());
+        serializer.variant = Some((name, variant));
+        Ok(serializer)
+    }
+}
+
+struct SeqSerializer {
+    items: Vec<Value>,
+}
+
+impl SeqSerializer {
+    /// Sequences of structs and maps are repeated child tags, anything else is a list literal
+    /// where structs and maps are written as map literals.
+    fn end(self) -> Result<Value, Error> {
+        if !self.items.is_empty() && self.items.iter().all(|item| matches!(item, Value::Tag(_))) {
+            let tags = self.items.into_iter().map(|item| match item {
+                Value::Tag(tag) => tag,
+                _ => unreachable!(),
+            });
+            return Ok(Value::Tags(tags.collect()));
+        }
+        let items = self.items.into_iter().map(into_literal);
+        Ok(Value::Literal(Literal::List(
+            items.collect::<Result<_, _>>()?,
+        )))
+    }
+}
+
+/// Writes a value inside a list literal.
+fn into_literal(value: Value) -> Result<Literal<'static>, Error> {
+    match value {
+        Value::Literal(literal) => Ok(literal),
+        Value::Tag(tag) if tag.children.is_empty() => Ok(Literal::Map(
+            tag.attributes
+                .into_iter()
+                .map(|attr| (attr.name, attr.value))
+                .collect(),
+        )),
+        Value::Tag(_) => Err(Error::Unsupported(
+            "a struct holding structs inside a list of values",
+        )),
+        Value::Tags(tags) => Ok(Literal::List(
+            tags.into_iter()
+                .map(|tag| into_literal(Value::Tag(tag)))
+                .collect::<Result<_, _>>()?,
+        )),
+        Value::Absent => Err(Error::Unsupported("`None` inside a sequence")),
+    }
+}
+
+impl ser::SerializeSeq for SeqSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.items.push(value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        SeqSerializer::end(self)
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        SeqSerializer::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        SeqSerializer::end(self)
+    }
+}
+
+struct TagSerializer {
+    tag: Tag<'static>,
+    key: Option<String>,
+    /// Set for `Range` and `RangeInclusive`, which are written as range literals.
+    range: Option<bool>,
+    variant: Option<(&'static str, &'static str)>,
+}
+
+impl TagSerializer {
+    fn new(name: String) -> Self {
+        Self {
+            tag: Tag::new(name),
+            key: None,
+            range: None,
+            variant: None,
+        }
+    }
+
+    fn field(&mut self, name: &str, value: Value) -> Result<(), Error> {
+        check_name(name)?;
+        match value {
+            Value::Absent => {}
+            Value::Literal(literal) => self
+                .tag
+                .attributes
+                .push(Attribute::new(name.to_string(), literal)),
+            Value::Tag(mut tag) => {
+                tag.name = name.to_string().into();
+                self.tag.children.push(Node::Tag(tag));
+            }
+            Value::Tags(tags) => {
+                for mut tag in tags {
+                    tag.name = name.to_string().into();
+                    self.tag.children.push(Node::Tag(tag));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        if let Some(inclusive) = self.range {
+            let bound = |name| {
+                self.tag.attr(name).and_then(|value| match value {
+                    Literal::Number(n) => Some(*n),
+                    _ => None,
+                })
+            };
+            let (Some(start), Some(end)) = (bound("start"), bound("end")) else {
+                return Err(Error::Unsupported("a range of anything but numbers"));
+            };
+            return Ok(Value::Literal(Literal::Range {
+                start,
+                end: Some(end),
+                inclusive,
+                step: None,
+            }));
+        }
+        match self.variant {
+            Some((name, variant_name)) => variant(name, variant_name, Value::Tag(self.tag)),
+            None => Ok(Value::Tag(self.tag)),
+        }
+    }
+}
+
+impl ser::SerializeMap for TagSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        match key.serialize(ValueSerializer)? {
+            Value::Literal(Literal::String(key)) => {
+                self.key = Some(key.into_owned());
+                Ok(())
+            }
+            _ => Err(Error::Unsupported("a map key that isn't a string")),
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self
+            .key
+            .take()
+            .expect("serialize_value is called after serialize_key");
+        let value = value.serialize(ValueSerializer)?;
+        self.field(&key, value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        TagSerializer::end(self)
+    }
+}
+
+impl ser::SerializeStruct for TagSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        let value = value.serialize(ValueSerializer)?;
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        TagSerializer::end(self)
+    }
+}
+
+impl ser::SerializeStructVariant for TagSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        ser::SerializeStruct::serialize_field(self, key, value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        TagSerializer::end(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{to_string, Error};
+    use crate::{de, from_str};
+    use pretty_assertions::assert_eq;
+    use serde::{Deserialize, Serialize};
+    use std::{
+        collections::BTreeMap,
+        ops::{Range, RangeInclusive},
+    };
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    #[serde(rename_all = "kebab-case")]
+    enum Lang {
+        Rust,
+        TypeScript,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    #[serde(rename_all = "kebab-case")]
+    enum Source {
+        File { path: String },
+        Inline(Snippet),
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Snippet {
+        lang: Lang,
+        lines: Range<u32>,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Site {
+        title: String,
+        draft: bool,
+        port: Option<u16>,
+        highlight: RangeInclusive<u32>,
+        snippet: Snippet,
+        #[serde(rename = "source")]
+        sources: Vec<Source>,
+        redirects: BTreeMap<String, String>,
+        tags: Vec<String>,
+    }
+
+    fn site() -> Site {
+        Site {
+            title: "Docs".to_string(),
+            draft: false,
+            port: None,
+            highlight: 1..=3,
+            snippet: Snippet {
+                lang: Lang::TypeScript,
+                lines: 2..5,
+            },
+            sources: vec![
+                Source::File {
+                    path: "a.mu".to_string(),
+                },
+                Source::Inline(Snippet {
+                    lang: Lang::Rust,
+                    lines: 0..1,
+                }),
+            ],
+            redirects: BTreeMap::from([("old".to_string(), "/new".to_string())]),
+            tags: vec![],
+        }
+    }
+
+    #[test]
+    fn mapping() {
+        assert_eq!(
+            to_string(&site()).unwrap(),
+            r#"site(title: "Docs", draft: false, highlight: 1..=3, tags: []) {
+    snippet(lang: type-script, lines: 2..5) {}
+    source {
+        file(path: "a.mu") {}
+    }
+    source {
+        inline(lang: rust, lines: 0..1) {}
+    }
+    redirects(old: "/new") {}
+}
+"#
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let source = to_string(&site()).unwrap();
+        assert_eq!(from_str::<Site>(&source), Ok(site()));
+
+        let quotes = BTreeMap::from([("s".to_string(), r#"say "hi" \o/"#.to_string())]);
+        let source = to_string(&quotes).unwrap();
+        assert_eq!(source, "map(s: r#\"say \"hi\" \\o/\"#) {}\n");
+        assert_eq!(from_str::<BTreeMap<String, String>>(&source), Ok(quotes));
+    }
+
+    #[test]
+    fn maps_inside_lists() {
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Cell {
+            width: u32,
+            lang: Lang,
+        }
+
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Grid {
+            rows: Vec<Vec<Cell>>,
+        }
+
+        let grid = Grid {
+            rows: vec![
+                vec![Cell {
+                    width: 1,
+                    lang: Lang::Rust,
+                }],
+                vec![],
+            ],
+        };
+        let source = to_string(&grid).unwrap();
+        assert_eq!(source, "grid(rows: [[{width: 1, lang: rust}], []]) {}\n");
+        assert_eq!(from_str::<Grid>(&source), Ok(grid));
+    }
+
+    #[test]
+    fn unrepresentable_values() {
+        assert_eq!(to_string(&vec![1, 2]), Err(Error::ExpectedTag));
+        assert_eq!(
+            to_string(&BTreeMap::from([("n", -1)])),
+            Err(Error::NumberOutOfRange("-1".to_string()))
+        );
+        assert_eq!(
+            to_string(&BTreeMap::from([("not a name", 1)])),
+            Err(Error::InvalidName("not a name".to_string()))
+        );
+    }
+
+    #[test]
+    fn errors_have_locations() {
+        let error = |source| from_str::<Snippet>(source).unwrap_err().to_string();
+        assert_eq!(
+            error("snippet(lang: \"rust\") {}"),
+            "Missing attribute `lines` at line 1, column 1"
+        );
+        assert_eq!(
+            error("// a snippet\nsnippet(lang: \"go\", lines: 1..2) {}"),
+            "Invalid attribute `lang`: unknown variant `go`, expected `rust` or `type-script` \
+             at line 2, column 9"
+        );
+        assert_eq!(
+            error("snippet(lang: \"rust\", lines: 1..2) {\n    extra {}\n}"),
+            "Unknown tag `extra`, expected one of `lang`, `lines` at line 2, column 5"
+        );
+        assert_eq!(
+            error("snippet {} snippet {}"),
+            "Expected a single tag at the top of the document, got 2 at line 1, column 12"
+        );
+        assert!(matches!(
+            from_str::<Snippet>("snippet("),
+            Err(de::Error::Located { line: 1, .. })
+        ));
+    }
+}
@@ -0,0 +1,451 @@
+use crate::{highlight::escape_html, project::ProjectError, site::SiteError};
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    path::{Component, Path, PathBuf},
+    sync::{Arc, Condvar, Mutex},
+    thread,
+    time::Duration,
+};
+
+/// The path pages listen on for reloads.
+const RELOAD_PATH: &str = "/__markup/reload";
+const RELOAD_SCRIPT: &str =
+    r#"<script>new EventSource("/__markup/reload").onmessage = () => location.reload();</script>"#;
+/// How often an idle reload stream is written to, to find out the browser went away.
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+/// How long to wait before accepting connections again after failing to.
+const ACCEPT_RETRY: Duration = Duration::from_millis(100);
+/// How many lines are shown around the line of a syntax error.
+const CONTEXT_LINES: usize = 2;
+
+/// Serves the output directory of a site over http and reloads the pages open in browsers.
+///
+/// Every html page gets a script that waits for [`Server::reload`] through server-sent events.
+/// After [`Server::fail`] every page is replaced with the error page until the next reload, so a
+/// browser never shows a page that is out of date. The server only uses the standard library and
+/// is meant for previewing a site on the local machine, not for putting it online.
+#[derive(Debug, Clone)]
+pub struct Server {
+    root: PathBuf,
+    state: Arc<(Mutex<State>, Condvar)>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// Incremented on every reload.
+    version: u64,
+    error: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl Server {
+    /// Serves the files of `root`.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            state: Arc::default(),
+        }
+    }
+
+    /// Reloads the pages open in browsers and stops showing the error page.
+    pub fn reload(&self) {
+        self.update(None);
+    }
+
+    /// Shows `page`, a whole html document like [`error_page`] makes, instead of every page
+    /// until the next [`Server::reload`].
+    pub fn fail(&self, page: String) {
+        self.update(Some(page));
+    }
+
+    fn update(&self, error: Option<String>) {
+        let (state, changed) = &*self.state;
+        let mut state = state.lock().expect("the lock is never poisoned");
+        state.version += 1;
+        state.error = error;
+        changed.notify_all();
+    }
+
+    /// Answers the connections to `listener`, each on its own thread, for as long as the process
+    /// runs. A connection that can't be accepted, like when the process is out of file
+    /// descriptors for a moment, is reported and the server goes on.
+    pub fn listen(&self, listener: TcpListener) {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(err) => {
+                    eprintln!("error: can't accept a connection: {err}");
+                    //Don't spin on an error that lasts a little.
+                    thread::sleep(ACCEPT_RETRY);
+                    continue;
+                }
+            };
+            let server = self.clone();
+            //A browser going away in the middle of a response is nothing to report.
+            thread::spawn(move || server.handle(stream));
+        }
+    }
+
+    fn handle(&self, mut stream: TcpStream) -> io::Result<()> {
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+        loop {
+            let mut header = String::new();
+            if reader.read_line(&mut header)? == 0 || header.trim_end().is_empty() {
+                break;
+            }
+        }
+        let mut parts = request_line.split_whitespace();
+        let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or("/"));
+        if method == "GET" && target == RELOAD_PATH {
+            return self.events(stream);
+        }
+        let response = self.respond(method, target);
+        write!(
+            stream,
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+            response.status,
+            reason(response.status),
+            response.content_type,
+            response.body.len()
+        )?;
+        if method != "HEAD" {
+            stream.write_all(&response.body)?;
+        }
+        stream.flush()
+    }
+
+    /// Streams a `reload` event every time the site changes until the browser goes away.
+    fn events(&self, mut stream: TcpStream) -> io::Result<()> {
+        let (state, changed) = &*self.state;
+        let mut seen = state.lock().expect("the lock is never poisoned").version;
+        stream.write_all(
+            b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-store\r\n\r\n",
+        )?;
+        stream.flush()?;
+        loop {
+            let version = {
+                let state = state.lock().expect("the lock is never poisoned");
+                let (state, _) = changed
+                    .wait_timeout_while(state, KEEP_ALIVE, |state| state.version == seen)
+                    .expect("the lock is never poisoned");
+                state.version
+            };
+            if version == seen {
+                stream.write_all(b": keep-alive\n\n")?;
+            } else {
+                seen = version;
+                stream.write_all(b"data: reload\n\n")?;
+            }
+            stream.flush()?;
+        }
+    }
+
+    fn respond(&self, method: &str, target: &str) -> Response {
+        if method != "GET" && method != "HEAD" {
+            return Response {
+                status: 405,
+                content_type: "text/plain; charset=utf-8",
+                body: b"Only GET and HEAD are supported".to_vec(),
+            };
+        }
+        let path = target.split(['?', '#']).next().unwrap_or_default();
+        let file = self.resolve(&percent_decode(path));
+        let is_html = file.as_ref().is_none_or(|file| content_type(file) == HTML);
+        let state = self.state.0.lock().expect("the lock is never poisoned");
+        if let (Some(error), true) = (&state.error, is_html) {
+            return html(200, error);
+        }
+        drop(state);
+        match file.and_then(|file| Some((fs::read(&file).ok()?, file))) {
+            Some((body, file)) => match content_type(&file) {
+                HTML => html(200, &String::from_utf8_lossy(&body)),
+                content_type => Response {
+                    status: 200,
+                    content_type,
+                    body,
+                },
+            },
+            None => {
+                let mut page =
+                    String::from("<!DOCTYPE html>\n<title>Not found</title>\n<p>No page at <code>");
+                escape_html(&mut page, path);
+                page.push_str("</code></p>\n");
+                html(404, &page)
+            }
+        }
+    }
+
+    /// The file at the url `path`, which may leave out `index.html` or the `.html` extension.
+    fn resolve(&self, path: &str) -> Option<PathBuf> {
+        let mut file = self.root.clone();
+        for component in Path::new(path.trim_start_matches('/')).components() {
+            match component {
+                Component::Normal(name) => file.push(name),
+                Component::CurDir => {}
+                _ => return None,
+            }
+        }
+        if file.is_dir() {
+            file.push("index.html");
+        } else if !file.exists() && file.extension().is_none() {
+            file.set_extension("html");
+        }
+        file.is_file().then_some(file)
+    }
+}
+
+/// A page describing `error`, for [`Server::fail`]. Syntax errors show the lines around the
+/// error, read from the `source` directory of the site.
+pub fn error_page(error: &SiteError, source: &Path) -> String {
+    let mut page = String::from(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Build error</title>
+<style>
+body { font-family: sans-serif; margin: 2em; }
+pre { background: #fdf2f2; padding: 1em; overflow-x: auto; }
+.error { color: #b91c1c; font-weight: bold; }
+</style>
+</head>
+<body>
+<h1>Build error</h1>
+<pre class="message">"#,
+    );
+    escape_html(&mut page, &error.to_string());
+    page.push_str("</pre>\n");
+    if let SiteError::Project(ProjectError::Parse { at, .. }) = error {
+        let text = fs::read_to_string(source.join(&at.path));
+        if let (Some((line, column)), Ok(text)) = (at.position, text) {
+            page.push_str("<pre class=\"snippet\">");
+            let first = line.saturating_sub(CONTEXT_LINES).max(1);
+            let width = (line + CONTEXT_LINES).to_string().len();
+            //Splitting on newlines keeps the empty line after the last one, where an unexpected end
+            //of the file is.
+            let lines: Vec<&str> = text.split('\n').collect();
+            for (number, text) in lines.iter().enumerate().map(|(i, text)| (i + 1, text)) {
+                let is_last_empty = number == lines.len() && text.is_empty() && number != line;
+                if number < first || number > line + CONTEXT_LINES || is_last_empty {
+                    continue;
+                }
+                let text = text.strip_suffix('\r').unwrap_or(text);
+                let mut escaped = String::new();
+                escape_html(&mut escaped, text);
+                if number == line {
+                    page.push_str(&format!(
+                        "<span class=\"error\">{number:>width$} | {escaped}\n{:>width$} | {:>column$}</span>\n",
+                        "", "^"
+                    ));
+                } else {
+                    page.push_str(&format!("{number:>width$} | {escaped}\n"));
+                }
+            }
+            page.push_str("</pre>\n");
+        }
+    }
+    page.push_str("</body>\n</html>\n");
+    page
+}
+
+const HTML: &str = "text/html; charset=utf-8";
+
+fn content_type(file: &Path) -> &'static str {
+    match file.extension().and_then(|extension| extension.to_str()) {
+        Some("html" | "htm") => HTML,
+        Some("css") => "text/css; charset=utf-8",
+        Some("js" | "mjs") => "text/javascript; charset=utf-8",
+        Some("json") => "application/json",
+        Some("txt" | "mu") => "text/plain; charset=utf-8",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("ico") => "image/x-icon",
+        Some("woff2") => "font/woff2",
+        Some("woff") => "font/woff",
+        _ => "application/octet-stream",
+    }
+}
+
+/// An html response for `page` with the reload script added at the end of its body.
+fn html(status: u16, page: &str) -> Response {
+    let at = page.rfind("</body>").unwrap_or(page.len());
+    let page = format!("{}{RELOAD_SCRIPT}{}", &page[..at], &page[at..]);
+    Response {
+        status,
+        content_type: HTML,
+        body: page.into_bytes(),
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "",
+    }
+}
+
+/// Decodes the `%XX` escapes of a url path.
+fn percent_decode(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escape = (bytes[i] == b'%')
+            .then(|| bytes.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escape {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{error_page, Server, RELOAD_SCRIPT};
+    use crate::{
+        project::{Location, ProjectError},
+        site::SiteError,
+    };
+    use pretty_assertions::assert_eq;
+    use std::{
+        fs,
+        io::{BufRead, BufReader, Read, Write},
+        net::{TcpListener, TcpStream},
+    };
+
+    fn body(server: &Server, target: &str) -> (u16, String) {
+        let response = server.respond("GET", target);
+        (response.status, String::from_utf8(response.body).unwrap())
+    }
+
+    #[test]
+    fn serves_pages_with_the_reload_script() {
+        //The secret sits next to the served directory, in a directory of this process only.
+        let parent = std::env::temp_dir().join(format!("markup-serve-{}", std::process::id()));
+        let root = parent.join("site");
+        let _ = fs::remove_dir_all(&parent);
+        fs::create_dir_all(root.join("guide")).unwrap();
+        fs::write(root.join("index.html"), "<body><p>home</p></body>").unwrap();
+        fs::write(root.join("guide/intro page.html"), "<p>intro</p>").unwrap();
+        fs::write(root.join("style.css"), "p {}").unwrap();
+        fs::write(parent.join("secret"), "").unwrap();
+        let server = Server::new(&root);
+
+        let home = format!("<body><p>home</p>{RELOAD_SCRIPT}</body>");
+        assert_eq!(body(&server, "/"), (200, home.clone()));
+        assert_eq!(body(&server, "/index.html?x#y"), (200, home.clone()));
+        assert_eq!(
+            body(&server, "/guide/intro%20page"),
+            (200, format!("<p>intro</p>{RELOAD_SCRIPT}"))
+        );
+        let css = server.respond("GET", "/style.css");
+        assert_eq!(
+            (css.content_type, css.body),
+            ("text/css; charset=utf-8", b"p {}".to_vec())
+        );
+        assert_eq!(body(&server, "/../secret").0, 404);
+        assert_eq!(body(&server, "/missing").0, 404);
+        assert_eq!(server.respond("POST", "/").status, 405);
+
+        server.fail("<body>broken</body>".to_string());
+        let broken = format!("<body>broken{RELOAD_SCRIPT}</body>");
+        assert_eq!(body(&server, "/"), (200, broken.clone()));
+        assert_eq!(body(&server, "/missing"), (200, broken));
+        assert_eq!(body(&server, "/style.css"), (200, "p {}".to_string()));
+        server.reload();
+        assert_eq!(body(&server, "/"), (200, home));
+        fs::remove_dir_all(&parent).unwrap();
+    }
+
+    #[test]
+    fn error_page_shows_the_source() {
+        let source = std::env::temp_dir().join(format!("markup-error-{}", std::process::id()));
+        fs::create_dir_all(&source).unwrap();
+        fs::write(source.join("a.mu"), "h1 \"A\"\np {\n  div(a: 1 { }\n}\n").unwrap();
+        let error = SiteError::Project(ProjectError::Parse {
+            at: Location {
+                path: "a.mu".to_string(),
+                position: Some((3, 12)),
+            },
+            message: "Unexpected <".to_string(),
+        });
+        let page = error_page(&error, &source);
+        let message = page.split("<pre class=\"message\">").nth(1).unwrap();
+        assert!(message.starts_with("a.mu:3:12: Unexpected &lt;</pre>"));
+        let snippet = page.split("<pre class=\"snippet\">").nth(1).unwrap();
+        assert_eq!(
+            snippet,
+            r#"1 | h1 &quot;A&quot;
+2 | p {
+<span class="error">3 |   div(a: 1 { }
+  |            ^</span>
+4 | }
+</pre>
+</body>
+</html>
+"#
+        );
+        fs::remove_dir_all(&source).unwrap();
+    }
+
+    #[test]
+    fn http_transport() {
+        let root = std::env::temp_dir().join(format!("markup-http-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("index.html"), "home").unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = Server::new(&root);
+        let listening = server.clone();
+        std::thread::spawn(move || listening.listen(listener));
+
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: x\r\n\r\n")
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with(&format!("\r\n\r\nhome{RELOAD_SCRIPT}")));
+
+        let mut events = TcpStream::connect(address).unwrap();
+        events
+            .write_all(b"GET /__markup/reload HTTP/1.1\r\n\r\n")
+            .unwrap();
+        let mut events = BufReader::new(events);
+        let mut line = String::new();
+        while line != "\r\n" {
+            line.clear();
+            events.read_line(&mut line).unwrap();
+        }
+        server.reload();
+        line.clear();
+        events.read_line(&mut line).unwrap();
+        assert_eq!(line, "data: reload\n");
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,591 @@
+use crate::{
+    ast::{Literal, Node},
+    attributes::AttributeError,
+    highlight::escape_html,
+    html::Html,
+    pass::{Anchor, Anchors, CrossReferences, PassError, Pipeline, ReferenceError},
+    project::{File, Project, ProjectError},
+};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+/// The page template used unless [`Site::template`] sets another one.
+pub const DEFAULT_TEMPLATE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{{title}}</title>
+{{head}}
+</head>
+<body>
+{{content}}
+</body>
+</html>
+"#;
+
+/// Builds a static html site out of a directory of `.mu` files.
+///
+/// Every `.mu` file of the source directory is parsed, transformed by the passes, rendered with
+/// [`Html`] and written to the output directory at the same relative path with an `.html`
+/// extension. Every other file is copied as it is, files and directories starting with a `.` are
+/// skipped and so is the output directory if it is inside the source directory.
+///
+/// All the pages form one [`Project`], so `ref` tags can point to ids in other pages, and links
+/// between pages are rewritten: `a(href: "guide/intro.mu#usage")` links to `guide/intro.html#usage`.
+///
+/// Pages are wrapped in a template in which `{{title}}` is the title from the page's `meta` tag,
+/// or its file name, `{{head}}` the `<meta>` tags for its `authors`, `date` and `description`,
+/// `{{content}}` the page itself and `{{root}}` the relative path to the root of the site, like
+/// `../`, for links to stylesheets and other assets.
+#[derive(Debug, Clone)]
+pub struct Site {
+    source: PathBuf,
+    output: PathBuf,
+    template: String,
+    passes: Vec<String>,
+    /// What the last build found in every page, by its path relative to the source directory.
+    pages: BTreeMap<String, Page>,
+    /// The files the last build copied, by their path relative to the source directory.
+    assets: BTreeSet<String>,
+}
+
+/// What a [`Site`] remembers about a page between builds.
+#[derive(Debug, Clone)]
+struct Page {
+    anchors: Vec<Anchor>,
+    targets: Vec<String>,
+    errors: Vec<ReferenceError>,
+}
+
+/// What [`Site::build`] or [`Site::rebuild`] did.
+#[derive(Debug, Default)]
+pub struct Build {
+    /// The paths of the pages written, relative to the output directory.
+    pub pages: Vec<String>,
+    /// The paths of the files copied, relative to the output directory.
+    pub assets: Vec<String>,
+    /// The paths of the files that were removed from the source directory, relative to it.
+    pub removed: Vec<String>,
+    /// Broken references and duplicate ids in the whole site, the pages are still written.
+    pub errors: Vec<ReferenceError>,
+}
+
+#[derive(Debug, Error)]
+pub enum SiteError {
+    #[error("{}: {}", .path, .source)]
+    Io { path: String, source: io::Error },
+    #[error(transparent)]
+    Project(#[from] ProjectError),
+    #[error("{}: {}", .path, .source)]
+    Metadata {
+        path: String,
+        source: AttributeError,
+    },
+    #[error("the build panicked: {}", .0)]
+    Panicked(String),
+}
+
+impl Site {
+    pub fn new(source: impl Into<PathBuf>, output: impl Into<PathBuf>) -> Self {
+        Self {
+            source: source.into(),
+            output: output.into(),
+            template: DEFAULT_TEMPLATE.to_string(),
+            passes: vec![],
+            pages: BTreeMap::new(),
+            assets: BTreeSet::new(),
+        }
+    }
+
+    /// Wraps pages in `template` instead of [`DEFAULT_TEMPLATE`].
+    pub fn template(mut self, template: impl Into<String>) -> Self {
+        self.template = template.into();
+        self
+    }
+
+    /// Runs the passes of `specs`, see [`from_spec`](crate::pass::from_spec), over every page
+    /// before references are resolved.
+    pub fn passes<S: AsRef<str>>(mut self, specs: &[S]) -> Result<Self, PassError> {
+        Pipeline::from_specs(specs)?;
+        self.passes = specs.iter().map(|spec| spec.as_ref().to_string()).collect();
+        Ok(self)
+    }
+
+    pub fn source(&self) -> &Path {
+        &self.source
+    }
+
+    pub fn output(&self) -> &Path {
+        &self.output
+    }
+
+    /// Builds the whole site.
+    pub fn build(&mut self) -> Result<Build, SiteError> {
+        self.pages.clear();
+        self.assets.clear();
+        let output = self.create_output()?;
+        let mut paths = vec![];
+        walk(&self.source, &output, &mut paths)?;
+        paths.sort();
+        let changes = paths
+            .into_iter()
+            .map(|path| (relative_path(&self.source, &path), path))
+            .collect();
+        self.update(changes)
+    }
+
+    /// Updates the site after a [`Site::build`] for the files at `paths`, which changed, were
+    /// created or were removed.
+    ///
+    /// Only the pages among them are parsed again, along with the pages whose `ref`s point to ids
+    /// they define or used to define, the other pages are left as they are. A directory stands for
+    /// the files inside it, or the ones the last build found there if it was removed. Paths
+    /// outside the source directory, hidden ones and the ones inside the output directory are
+    /// ignored.
+    pub fn rebuild(&mut self, paths: &[PathBuf]) -> Result<Build, SiteError> {
+        let output = self.create_output()?;
+        let source = self.source.canonicalize().map_err(io_error(&self.source))?;
+        let mut changes: Vec<(String, PathBuf)> = vec![];
+        for path in paths {
+            if path.starts_with(&output) || path.starts_with(&self.output) {
+                continue;
+            }
+            let relative = match (path.strip_prefix(&source), path.strip_prefix(&self.source)) {
+                (Ok(relative), _) | (_, Ok(relative)) => relative,
+                _ => continue,
+            };
+            let hidden = relative
+                .components()
+                .any(|component| component.as_os_str().to_string_lossy().starts_with('.'));
+            if hidden {
+                continue;
+            }
+            let relative = relative_path(Path::new(""), relative);
+            let mut files = vec![];
+            if path.is_dir() {
+                let mut walked = vec![];
+                walk(path, &output, &mut walked)?;
+                files.extend(walked.into_iter().map(|file| {
+                    let inside = relative_path(path, &file);
+                    (format!("{relative}/{inside}"), file)
+                }));
+            } else if path.exists() || self.is_known(&relative) {
+                files.push((relative, path.clone()));
+            } else {
+                let prefix = format!("{relative}/");
+                let known = self.pages.keys().chain(&self.assets);
+                files.extend(
+                    known
+                        .filter(|known| known.starts_with(&prefix))
+                        .map(|known| (known.clone(), self.source.join(known))),
+                );
+            }
+            for (relative, path) in files {
+                if !changes.iter().any(|(known, _)| *known == relative) {
+                    changes.push((relative, path));
+                }
+            }
+        }
+        changes.sort();
+        self.update(changes)
+    }
+
+    /// Whether the last build found a page or a file at `relative`.
+    fn is_known(&self, relative: &str) -> bool {
+        self.pages.contains_key(relative) || self.assets.contains(relative)
+    }
+
+    fn create_output(&self) -> Result<PathBuf, SiteError> {
+        fs::create_dir_all(&self.output).map_err(io_error(&self.output))?;
+        self.output.canonicalize().map_err(io_error(&self.output))
+    }
+
+    /// Copies, renders or removes the files of `changes`, given by their path relative to the
+    /// source directory and their full path, and renders the pages referring to them again.
+    fn update(&mut self, changes: Vec<(String, PathBuf)>) -> Result<Build, SiteError> {
+        let references = CrossReferences::default();
+        let mut build = Build::default();
+        let mut project = Project::new();
+        //The anchors before and after the change, the ids that differ are the ones pages may
+        //have to show differently now.
+        let mut old = vec![];
+        for (relative, path) in changes {
+            let is_page = relative.ends_with(".mu");
+            if is_page {
+                old.extend(
+                    self.pages
+                        .get(&relative)
+                        .into_iter()
+                        .flat_map(|page| &page.anchors)
+                        .cloned(),
+                );
+            }
+            if !path.is_file() {
+                let target = self.output.join(if is_page {
+                    html_path(&relative)
+                } else {
+                    relative.clone()
+                });
+                if target.is_dir() {
+                    fs::remove_dir_all(&target).map_err(io_error(&target))?;
+                } else if target.exists() {
+                    fs::remove_file(&target).map_err(io_error(&target))?;
+                }
+                remove_empty_parents(&target, &self.output);
+                self.pages.remove(&relative);
+                self.assets.remove(&relative);
+                build.removed.push(relative);
+            } else if is_page {
+                let source = fs::read_to_string(&path).map_err(io_error(&path))?;
+                project.add(relative, source);
+            } else {
+                let target = self.output.join(&relative);
+                create_parent(&target)?;
+                fs::copy(&path, &target).map_err(io_error(&path))?;
+                self.assets.insert(relative.clone());
+                build.assets.push(relative);
+            }
+        }
+        let mut files = project.parse()?;
+        self.transform(&mut files);
+        let new: Vec<_> = files
+            .iter()
+            .flat_map(|file| references.anchors(file))
+            .collect();
+        let key = |anchor: &Anchor| (anchor.id.clone(), anchor.path.clone(), anchor.text.clone());
+        let old_keys: HashSet<_> = old.iter().map(key).collect();
+        let new_keys: HashSet<_> = new.iter().map(key).collect();
+        let changed_ids: HashSet<String> = old_keys
+            .symmetric_difference(&new_keys)
+            .map(|(id, _, _)| id.clone())
+            .collect();
+        for file in &files {
+            self.pages.insert(
+                file.path.to_string(),
+                Page {
+                    anchors: references.anchors(file),
+                    targets: references
+                        .targets(file)
+                        .into_iter()
+                        .map(str::to_string)
+                        .collect(),
+                    errors: vec![],
+                },
+            );
+        }
+
+        let mut dependents = Project::new();
+        for (relative, page) in &self.pages {
+            let is_changed = files.iter().any(|file| file.path == relative);
+            if !is_changed && page.targets.iter().any(|id| changed_ids.contains(id)) {
+                let path = self.source.join(relative);
+                let source = fs::read_to_string(&path).map_err(io_error(&path))?;
+                dependents.add(relative.clone(), source);
+            }
+        }
+        let mut dependents = dependents.parse()?;
+        self.transform(&mut dependents);
+
+        let all = self.pages.values().flat_map(|page| page.anchors.clone());
+        let (anchors, mut errors) = Anchors::new(all.collect::<Vec<_>>());
+        let html = Html::new();
+        for file in files.iter_mut().chain(&mut dependents) {
+            let (_, page_errors) = references.fill(file, &anchors);
+            self.pages.get_mut(file.path).expect("added above").errors = page_errors;
+            rewrite_links(&mut file.document.nodes, file.path);
+            let page = self.page(&html, file)?;
+            let relative = html_path(file.path);
+            let target = self.output.join(&relative);
+            create_parent(&target)?;
+            fs::write(&target, page).map_err(io_error(&target))?;
+            build.pages.push(relative);
+        }
+        build.pages.sort();
+        errors.extend(self.pages.values().flat_map(|page| page.errors.clone()));
+        build.errors = errors;
+        Ok(build)
+    }
+
+    fn transform(&self, files: &mut [File]) {
+        for file in files {
+            let mut pipeline = Pipeline::from_specs(&self.passes).expect("checked by `passes`");
+            pipeline.run(&mut file.document.nodes);
+        }
+    }
+
+    fn page(&self, html: &Html, file: &File) -> Result<String, SiteError> {
+        let metadata = |source| SiteError::Metadata {
+            path: file.path.to_string(),
+            source,
+        };
+        let document = &file.document;
+        let stem = file.path.rsplit('/').next().unwrap_or(file.path);
+        let title = document
+            .title()
+            .map_err(metadata)?
+            .unwrap_or_else(|| stem.trim_end_matches(".mu"));
+        let mut head = String::new();
+        let authors = document.authors().map_err(metadata)?;
+        let meta = [
+            ("author", (!authors.is_empty()).then(|| authors.join(", "))),
+            (
+                "date",
+                document.date().map_err(metadata)?.map(str::to_string),
+            ),
+            (
+                "description",
+                document
+                    .get::<Option<&str>>("description")
+                    .map_err(metadata)?
+                    .map(str::to_string),
+            ),
+        ];
+        for (name, content) in meta {
+            if let Some(content) = content {
+                head.push_str(&format!("<meta name=\"{name}\" content=\""));
+                escape_html(&mut head, &content);
+                head.push_str("\">\n");
+            }
+        }
+        let mut escaped_title = String::new();
+        escape_html(&mut escaped_title, title);
+        let depth = file.path.matches('/').count();
+        Ok(self
+            .template
+            .replace("{{title}}", &escaped_title)
+            .replace("{{head}}", head.trim_end())
+            .replace("{{root}}", &"../".repeat(depth))
+            .replace("{{content}}", &html.render(&document.nodes)))
+    }
+}
+
+fn io_error(path: &Path) -> impl FnOnce(io::Error) -> SiteError + '_ {
+    move |source| SiteError::Io {
+        path: path.display().to_string(),
+        source,
+    }
+}
+
+fn create_parent(path: &Path) -> Result<(), SiteError> {
+    match path.parent() {
+        Some(parent) => fs::create_dir_all(parent).map_err(io_error(parent)),
+        None => Ok(()),
+    }
+}
+
+/// Removes the directories containing `path` that are left empty, up to `root`.
+fn remove_empty_parents(path: &Path, root: &Path) {
+    for dir in path.ancestors().skip(1).take_while(|dir| *dir != root) {
+        if fs::remove_dir(dir).is_err() {
+            break;
+        }
+    }
+}
+
+/// Collects the files of `dir`, skipping hidden ones and the `output` directory.
+fn walk(dir: &Path, output: &Path, paths: &mut Vec<PathBuf>) -> Result<(), SiteError> {
+    for entry in fs::read_dir(dir).map_err(io_error(dir))? {
+        let entry = entry.map_err(io_error(dir))?;
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        if path.is_dir() {
+            if path.canonicalize().ok().as_deref() != Some(output) {
+                walk(&path, output, paths)?;
+            }
+        } else {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// `path` relative to `root` with `/` separators, as used for pages and links.
+fn relative_path(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn html_path(path: &str) -> String {
+    format!("{}.html", path.strip_suffix(".mu").unwrap_or(path))
+}
+
+/// The link from the page `from` to the page `to`, both relative to the root of the site.
+fn relative_link(from: &str, to: &str) -> String {
+    let from: Vec<&str> = from.split('/').collect();
+    let from = &from[..from.len() - 1];
+    let to: Vec<&str> = to.split('/').collect();
+    let common = from
+        .iter()
+        .zip(&to[..to.len() - 1])
+        .take_while(|(a, b)| a == b)
+        .count();
+    "../".repeat(from.len() - common) + &to[common..].join("/")
+}
+
+/// Points `ref`s resolved to another page and relative links to `.mu` files at the html pages.
+fn rewrite_links(nodes: &mut [Node], page: &str) {
+    for node in nodes {
+        let Node::Tag(tag) = node else {
+            continue;
+        };
+        for attribute in &mut tag.attributes {
+            let Literal::String(value) = &mut attribute.value else {
+                continue;
+            };
+            match (tag.name.as_ref(), attribute.name.as_ref()) {
+                ("ref", "path") => *value = relative_link(page, &html_path(value)).into(),
+                (_, "href") => {
+                    if let Some(link) = mu_link(value) {
+                        *value = link.into();
+                    }
+                }
+                _ => {}
+            }
+        }
+        rewrite_links(&mut tag.children, page);
+    }
+}
+
+/// `href` with its `.mu` extension replaced if it's a relative link to a `.mu` file.
+fn mu_link(href: &str) -> Option<String> {
+    let end = href.find(['#', '?']).unwrap_or(href.len());
+    let (path, rest) = href.split_at(end);
+    let is_relative = !path.contains(':') && !path.starts_with('/');
+    let path = path.strip_suffix(".mu").filter(|_| is_relative)?;
+    Some(format!("{path}.html{rest}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{mu_link, relative_link, Site};
+    use pretty_assertions::assert_eq;
+    use std::fs;
+
+    #[test]
+    fn links() {
+        assert_eq!(mu_link("intro.mu"), Some("intro.html".to_string()));
+        assert_eq!(mu_link("../a/b.mu#x?"), Some("../a/b.html#x?".to_string()));
+        assert_eq!(mu_link("https://a.b/c.mu"), None);
+        assert_eq!(mu_link("/c.mu"), None);
+        assert_eq!(mu_link("c.mud"), None);
+        assert_eq!(relative_link("index.mu", "guide/a.html"), "guide/a.html");
+        assert_eq!(relative_link("guide/a.mu", "index.html"), "../index.html");
+        assert_eq!(relative_link("guide/a.mu", "guide/b.html"), "b.html");
+        assert_eq!(
+            relative_link("guide/x/a.mu", "api/b.html"),
+            "../../api/b.html"
+        );
+    }
+
+    #[test]
+    fn builds_a_directory() {
+        let root = std::env::temp_dir().join(format!("markup-site-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let source = root.join("src");
+        fs::create_dir_all(source.join("guide")).unwrap();
+        fs::create_dir_all(source.join(".git")).unwrap();
+        fs::write(source.join(".git/HEAD"), "").unwrap();
+        fs::write(source.join("style.css"), "p {}").unwrap();
+        fs::write(
+            source.join("index.mu"),
+            r#"meta(title: "Home & away", authors: ["ada"]); p { a(href: "guide/intro.mu") "Intro" " " ref(to: "usage"); }"#,
+        )
+        .unwrap();
+        fs::write(
+            source.join("guide/intro.mu"),
+            r#"h1 "Intro" h2(id: "usage") "Usage" ref(to: "nope");"#,
+        )
+        .unwrap();
+
+        let mut site = Site::new(&source, source.join("out"))
+            .template("{{title}}|{{head}}|{{root}}|{{content}}");
+        let build = site.build().unwrap();
+        assert_eq!(build.pages, ["guide/intro.html", "index.html"]);
+        assert_eq!(build.assets, ["style.css"]);
+        assert_eq!(
+            build
+                .errors
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            ["guide/intro.mu:1:36: Reference to unknown id `nope`"]
+        );
+        assert_eq!(
+            fs::read_to_string(source.join("out/index.html")).unwrap(),
+            r##"Home &amp; away|<meta name="author" content="ada">||<p><a href="guide/intro.html">Intro</a> <a href="guide/intro.html#usage">Usage</a></p>"##
+        );
+        assert_eq!(
+            fs::read_to_string(source.join("out/guide/intro.html")).unwrap(),
+            r##"intro||../|<h1>Intro</h1><h2 id="usage">Usage</h2><a href="#nope"></a>"##
+        );
+        assert!(!source.join("out/.git").exists());
+        assert!(!source.join("out/out").exists());
+
+        //Only the page and the pages referring to the ids it changed are rendered again.
+        fs::write(
+            source.join("guide/intro.mu"),
+            r#"h1 "Intro" h2(id: "usage") "How to""#,
+        )
+        .unwrap();
+        let build = site.rebuild(&[source.join("guide/intro.mu")]).unwrap();
+        assert_eq!(build.pages, ["guide/intro.html", "index.html"]);
+        assert_eq!(build.errors, []);
+        assert!(fs::read_to_string(source.join("out/index.html"))
+            .unwrap()
+            .ends_with(r##"<a href="guide/intro.html#usage">How to</a></p>"##));
+        let build = site
+            .rebuild(&[source.join("guide/intro.mu"), source.join("out/index.html")])
+            .unwrap();
+        assert_eq!(build.pages, ["guide/intro.html"]);
+
+        fs::remove_file(source.join("guide/intro.mu")).unwrap();
+        let build = site.rebuild(&[source.join("guide/intro.mu")]).unwrap();
+        assert_eq!(build.removed, ["guide/intro.mu"]);
+        assert_eq!(build.pages, ["index.html"]);
+        assert_eq!(
+            build
+                .errors
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            ["index.mu:1:89: Reference to unknown id `usage`"]
+        );
+        assert!(!source.join("out/guide").exists());
+
+        //A directory stands for the files inside it, or the ones it had once it's gone.
+        fs::write(source.join("guide/intro.mu"), r#"h2(id: "usage") "Usage""#).unwrap();
+        fs::write(source.join("guide/logo.svg"), "<svg/>").unwrap();
+        let build = site.rebuild(&[source.join("guide")]).unwrap();
+        assert_eq!(build.pages, ["guide/intro.html", "index.html"]);
+        assert_eq!(build.assets, ["guide/logo.svg"]);
+        fs::rename(source.join("guide"), source.join("docs")).unwrap();
+        let build = site
+            .rebuild(&[source.join("guide"), source.join("docs")])
+            .unwrap();
+        assert_eq!(build.removed, ["guide/intro.mu", "guide/logo.svg"]);
+        assert_eq!(build.pages, ["docs/intro.html", "index.html"]);
+        assert_eq!(build.assets, ["docs/logo.svg"]);
+        assert!(!source.join("out/guide").exists());
+        assert!(fs::read_to_string(source.join("out/index.html"))
+            .unwrap()
+            .ends_with(r##"<a href="docs/intro.html#usage">Usage</a></p>"##));
+        fs::remove_dir_all(source.join("docs")).unwrap();
+        let build = site.rebuild(&[source.join("docs")]).unwrap();
+        assert_eq!(build.removed, ["docs/intro.mu", "docs/logo.svg"]);
+        assert!(!source.join("out/docs").exists());
+        let build = site.rebuild(&[source.join("docs")]).unwrap();
+        assert_eq!(build.removed, Vec::<String>::new());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}